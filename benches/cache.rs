@@ -0,0 +1,91 @@
+//! Benchmarks the throughput of the shared cache under concurrent searches, which read and write
+//! the cached results of different queries at the same time.
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::future::join_all;
+use websurfx::{
+    cache::cacher::{Cacher, InMemoryCache, SharedCache},
+    config::parser::Config,
+    models::aggregation_models::{SearchResult, SearchResults},
+};
+
+/// The number of distinct queries cached before the benchmark is run.
+const CACHED_QUERIES: usize = 256;
+
+/// The number of searches run by every concurrent task.
+const SEARCHES_PER_TASK: usize = 32;
+
+/// A helper function which builds the results of a search holding ten results.
+fn search_results(query: usize) -> SearchResults {
+    let results = (0..10)
+        .map(|index| {
+            SearchResult::new(
+                &format!("Result {index} of query {query}"),
+                &format!("https://example.com/{query}/{index}"),
+                "A description of the search result long enough to resemble a real one.",
+                &["duckduckgo", "searx"],
+            )
+        })
+        .collect();
+    SearchResults::new(results, Box::new([]), Box::new([]))
+}
+
+/// A helper function which builds the cache key of a query.
+fn cache_key(query: usize) -> String {
+    format!("http://127.0.0.1:8080/search?q=query{query}&page=0&engines=duckduckgo,searx")
+}
+
+/// Runs the concurrent searches, where every search reads the cached results of a query and
+/// every eighth search caches them again as if it had refreshed them.
+fn concurrent_searches(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let config = Config::parse(true).unwrap();
+
+    let cache = runtime.block_on(async {
+        let cache = Arc::new(SharedCache::new(
+            InMemoryCache::build(&config).await,
+            &config,
+        ));
+        for query in 0..CACHED_QUERIES {
+            cache
+                .cache_results(&[search_results(query)], &[cache_key(query)])
+                .await
+                .unwrap();
+        }
+        cache
+    });
+
+    let mut group = c.benchmark_group("concurrent_searches");
+    for tasks in [1, 8, 64] {
+        group.throughput(Throughput::Elements((tasks * SEARCHES_PER_TASK) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(tasks), &tasks, |b, &tasks| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    join_all((0..tasks).map(|task| {
+                        let cache = cache.clone();
+                        tokio::spawn(async move {
+                            for search in 0..SEARCHES_PER_TASK {
+                                let query = (task * SEARCHES_PER_TASK + search) % CACHED_QUERIES;
+                                let key = cache_key(query);
+                                let results = cache.cached_results(&key).await.unwrap();
+                                if search % 8 == 0 {
+                                    cache.cache_results(&[results], &[key]).await.unwrap();
+                                }
+                            }
+                        })
+                    }))
+                    .await
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, concurrent_searches);
+criterion_main!(benches);
@@ -0,0 +1,166 @@
+//! This module provides the structured keys of the cached results, which only depend on the
+//! options changing the results, so that they are shared by all the instances of the server using
+//! the same cache whatever the address they are bound to.
+
+use std::{borrow::Cow, collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+/// A constant holding the version of the schema of the cache keys and of the cached results. It
+/// should be bumped whenever either of them changes, so that the results cached by the previous
+/// versions are not read.
+pub const CACHE_KEY_VERSION: u8 = 3;
+
+/// A named struct which stores the options identifying the cached results of a search query. It
+/// is serialized into a canonical string, which is hashed with `Cacher::hash_url` to get the key
+/// of the results in the cache backend. Choosing the locale or the time range of the search is not
+/// supported yet, the upstream search engines are always requested with their default locale and
+/// without a time range, so neither is part of the key.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CacheKey<'a> {
+    /// It stores the version of the schema of the key.
+    version: u8,
+    /// It stores the normalized search query.
+    query: String,
+    /// It stores the zero based page number of the results or `None` if the results are not
+    /// split into pages.
+    page: Option<u32>,
+    /// It stores the safe search level or mode the results were filtered with.
+    safe_search: Cow<'a, str>,
+    /// It stores the sorted names of the upstream search engines the results were fetched from.
+    engines: Vec<Cow<'a, str>>,
+    /// It stores the other options changing the results mapped to their names.
+    options: BTreeMap<&'static str, String>,
+}
+
+/// A named struct which deserializes the search query of a canonical cache key.
+#[derive(Deserialize)]
+struct KeyQuery<'a> {
+    /// It stores the normalized search query of the key.
+    #[serde(borrow)]
+    query: Cow<'a, str>,
+}
+
+impl<'a> CacheKey<'a> {
+    /// A function which builds the key of the results of a search query fetched from the given
+    /// upstream search engines. The query is normalized and the engines are sorted, so that the
+    /// queries only differing by their case or whitespace and the same engines selected in a
+    /// different order share their results.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query as an argument.
+    /// * `engines` - It takes the names of the upstream search engines as an argument.
+    /// * `safe_search` - It takes the safe search level or mode as an argument.
+    ///
+    /// # Returns
+    ///
+    /// Returns a newly constructed `CacheKey` struct.
+    pub fn new(
+        query: &str,
+        engines: impl IntoIterator<Item = impl AsRef<str>>,
+        safe_search: impl ToString,
+    ) -> Self {
+        let mut engines: Vec<Cow<'a, str>> = engines
+            .into_iter()
+            .map(|engine| Cow::Owned(engine.as_ref().trim().to_lowercase()))
+            .collect();
+        engines.sort();
+        engines.dedup();
+
+        CacheKey {
+            version: CACHE_KEY_VERSION,
+            query: normalize_query(query),
+            page: None,
+            safe_search: Cow::Owned(safe_search.to_string()),
+            engines,
+            options: BTreeMap::new(),
+        }
+    }
+
+    /// A builder function which sets the zero based page number of the results.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - It takes the zero based page number as an argument.
+    pub fn with_page(mut self, page: u32) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    /// A builder function which sets another option changing the results.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - It takes the name of the option as an argument.
+    /// * `value` - It takes the value of the option as an argument.
+    pub fn with_option(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.options.insert(name, value.to_string());
+        self
+    }
+}
+
+impl fmt::Display for CacheKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&serde_json::to_string(self).map_err(|_| fmt::Error)?)
+    }
+}
+
+/// A function which normalizes a search query by lowercasing it and collapsing its whitespace.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query as an argument.
+///
+/// # Returns
+///
+/// Returns the normalized search query.
+pub fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// A helper function which reads the normalized search query of a canonical cache key.
+///
+/// # Arguments
+///
+/// * `key` - It takes the canonical cache key as an argument.
+///
+/// # Returns
+///
+/// Returns the search query of the key or `None` if the key is not a canonical cache key.
+pub(super) fn key_query(key: &str) -> Option<Cow<'_, str>> {
+    serde_json::from_str::<KeyQuery<'_>>(key)
+        .ok()
+        .map(|key| key.query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equivalent_searches_share_their_key() {
+        let key = CacheKey::new("Rust  lang ", ["bing", "DuckDuckGo"], 2).with_page(1);
+        assert_eq!(
+            key.to_string(),
+            CacheKey::new("rust lang", ["duckduckgo", "bing"], 2)
+                .with_page(1)
+                .to_string()
+        );
+        assert_ne!(key, CacheKey::new("rust lang", ["bing"], 2).with_page(1));
+        assert_ne!(key, key.clone().with_page(2));
+        assert_ne!(key, key.clone().with_option("reveal", true));
+        assert_eq!(key_query(&key.to_string()).as_deref(), Some("rust lang"));
+    }
+
+    #[test]
+    fn test_queries_are_normalized() {
+        assert_eq!(normalize_query("  Rust\t\nLANG  "), "rust lang");
+        assert_eq!(normalize_query("ÉCOLE"), "école");
+        assert_eq!(normalize_query("\"c++\" & rust"), "\"c++\" & rust");
+    }
+}
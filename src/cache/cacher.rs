@@ -2,22 +2,43 @@
 //! from the upstream search engines in a json format.
 
 use error_stack::Report;
+#[cfg(feature = "memory-cache")]
 use futures::future::join_all;
 #[cfg(feature = "memory-cache")]
 use moka::future::Cache as MokaCache;
+use serde::Serialize;
 
 #[cfg(feature = "memory-cache")]
 use std::time::Duration;
-use tokio::sync::Mutex;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        PoisonError,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::OnceCell;
 
 use crate::{config::parser::Config, models::aggregation_models::SearchResults};
 
-use super::error::CacheError;
+#[cfg(feature = "disk-cache")]
+use super::disk_cacher::DiskCache;
 #[cfg(feature = "redis-cache")]
 use super::redis_cacher::RedisCache;
+use super::{
+    cache_key::{key_query, normalize_query},
+    error::CacheError,
+};
 
 #[cfg(any(feature = "encrypt-cache-results", feature = "cec-cache-results"))]
-use super::encryption::*;
+use super::encryption::keyring;
+
+// The cache backend is chosen by the features, so the build fails instead of silently picking one
+// of the backends when both the disk and redis caches are enabled.
+#[cfg(all(feature = "disk-cache", feature = "redis-cache"))]
+compile_error!("The `disk-cache` and `redis-cache` features cannot be combined, enable only one of them to choose the cache backend");
 
 /// Abstraction trait for common methods provided by a cache backend.
 #[async_trait::async_trait]
@@ -45,7 +66,7 @@ pub trait Cacher: Send + Sync {
     ///
     /// Returns the `SearchResults` from the cache if the program executes normally otherwise
     /// returns a `CacheError` if the results cannot be retrieved from the cache.
-    async fn cached_results(&mut self, url: &str) -> Result<SearchResults, Report<CacheError>>;
+    async fn cached_results(&self, url: &str) -> Result<SearchResults, Report<CacheError>>;
 
     /// A function which caches the results by using the `url` as the key and
     /// `json results` as the value and stores it in the cache
@@ -61,67 +82,75 @@ pub trait Cacher: Send + Sync {
     /// otherwise it returns a `CacheError` if the search results cannot be cached due to a
     /// failure.
     async fn cache_results(
-        &mut self,
+        &self,
         search_results: &[SearchResults],
         urls: &[String],
     ) -> Result<(), Report<CacheError>>;
 
-    /// A helper function which computes the hash of the url and formats and returns it as string.
+    /// A function which computes the statistics of the cache backend. The hits and misses are
+    /// counted by the `SharedCache`, so they are left to zero by the backends.
+    ///
+    /// # Error
+    ///
+    /// Returns the number of evictions, entries and bytes of the cache backend on success
+    /// otherwise returns a `CacheError` if the statistics cannot be retrieved.
+    async fn stats(&self) -> Result<CacheStats, Report<CacheError>>;
+
+    /// A function which removes the cached results of a search query from the cache, or all the
+    /// cached results when no query is given.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query whose results should be removed as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the number of removed results on success otherwise returns a `CacheError` if the
+    /// results cannot be removed.
+    async fn purge(&self, query: Option<&str>) -> Result<u64, Report<CacheError>>;
+
+    /// A helper function which computes the hash of the canonical cache key (see `CacheKey`) and
+    /// formats and returns it as string. The hash is prefixed with the hash of the search query
+    /// of the key, so that all the cached results of a query can be found to purge them.
     ///
     /// # Arguments
     ///
-    /// * `url` - It takes an url as string.
+    /// * `url` - It takes the canonical cache key as string.
     fn hash_url(&self, url: &str) -> String {
-        blake3::hash(url.as_bytes()).to_string()
+        format!(
+            "{}{}",
+            key_prefix(Some(&key_query(url).unwrap_or_default())),
+            blake3::hash(url.as_bytes())
+        )
     }
 
-    /// A helper function that returns  either encrypted or decrypted results.
+    /// A helper function that returns  either encrypted or decrypted results. Every entry is
+    /// encrypted with a fresh nonce and the keys loaded with `init_keyring`.
     ///  Feature flags (**encrypt-cache-results or cec-cache-results**) are required  for this to work.
     ///
     /// # Arguments
     ///
     /// * `bytes` - It takes a slice of bytes as an argument.
+    /// * `key` - It takes the hashed cache key the bytes are stored under as an argument, which is
+    ///   authenticated along with the bytes.
     /// * `encrypt` - A boolean to choose whether to encrypt or decrypt the bytes
-
     ///
     /// # Error
     /// Returns  either encrypted or decrypted bytes on success otherwise it returns a CacheError
     /// on failure.
-    #[cfg(any(
-      //  feature = "compress-cache-results",
-        feature = "encrypt-cache-results",
-        feature = "cec-cache-results"
-    ))]
+    #[cfg(any(feature = "encrypt-cache-results", feature = "cec-cache-results"))]
     async fn encrypt_or_decrypt_results(
-        &mut self,
-        mut bytes: Vec<u8>,
+        &self,
+        bytes: Vec<u8>,
+        key: &str,
         encrypt: bool,
     ) -> Result<Vec<u8>, Report<CacheError>> {
-        use chacha20poly1305::{
-            aead::{Aead, AeadCore, KeyInit, OsRng},
-            ChaCha20Poly1305,
-        };
-
-        let cipher = CIPHER.get_or_init(|| {
-            let key = ChaCha20Poly1305::generate_key(&mut OsRng);
-            ChaCha20Poly1305::new(&key)
-        });
-
-        let encryption_key = ENCRYPTION_KEY.get_or_init(
-            || ChaCha20Poly1305::generate_nonce(&mut OsRng), // 96-bits; unique per message
-        );
-
-        bytes = if encrypt {
-            cipher
-                .encrypt(encryption_key, bytes.as_ref())
-                .map_err(|_| CacheError::EncryptionError)?
+        let keyring = keyring();
+        Ok(if encrypt {
+            keyring.encrypt(&bytes, key)?
         } else {
-            cipher
-                .decrypt(encryption_key, bytes.as_ref())
-                .map_err(|_| CacheError::EncryptionError)?
-        };
-
-        Ok(bytes)
+            keyring.decrypt(&bytes, key)?
+        })
     }
 
     /// A helper function that returns compressed results.
@@ -136,10 +165,7 @@ pub trait Cacher: Send + Sync {
     /// Returns the compressed bytes on success otherwise it returns a CacheError
     /// on failure.
     #[cfg(any(feature = "compress-cache-results", feature = "cec-cache-results"))]
-    async fn compress_results(
-        &mut self,
-        mut bytes: Vec<u8>,
-    ) -> Result<Vec<u8>, Report<CacheError>> {
+    async fn compress_results(&self, mut bytes: Vec<u8>) -> Result<Vec<u8>, Report<CacheError>> {
         use tokio::io::AsyncWriteExt;
         let mut writer = async_compression::tokio::write::BrotliEncoder::new(Vec::new());
         writer
@@ -160,6 +186,7 @@ pub trait Cacher: Send + Sync {
     /// # Arguments
     ///
     /// * `bytes` - It takes a slice of bytes as an argument.
+    /// * `key` - It takes the hashed cache key the bytes are stored under as an argument.
 
     ///
     /// # Error
@@ -167,13 +194,14 @@ pub trait Cacher: Send + Sync {
     /// on failure.
     #[cfg(feature = "cec-cache-results")]
     async fn compress_encrypt_compress_results(
-        &mut self,
+        &self,
         mut bytes: Vec<u8>,
+        key: &str,
     ) -> Result<Vec<u8>, Report<CacheError>> {
         // compress first
         bytes = self.compress_results(bytes).await?;
         // encrypt
-        bytes = self.encrypt_or_decrypt_results(bytes, true).await?;
+        bytes = self.encrypt_or_decrypt_results(bytes, key, true).await?;
 
         // compress again;
         bytes = self.compress_results(bytes).await?;
@@ -187,6 +215,7 @@ pub trait Cacher: Send + Sync {
     /// # Arguments
     ///
     /// * `bytes` - It takes a slice of bytes as an argument.
+    /// * `key` - It takes the hashed cache key the bytes are stored under as an argument.
 
     ///
     /// # Error
@@ -194,20 +223,25 @@ pub trait Cacher: Send + Sync {
     /// on failure.
 
     #[cfg(any(feature = "compress-cache-results", feature = "cec-cache-results"))]
-    async fn decompress_results(&mut self, bytes: &[u8]) -> Result<Vec<u8>, Report<CacheError>> {
+    #[allow(unused_variables)] // the key is only read when the results are encrypted
+    async fn decompress_results(
+        &self,
+        bytes: &[u8],
+        key: &str,
+    ) -> Result<Vec<u8>, Report<CacheError>> {
         cfg_if::cfg_if! {
-             if #[cfg(feature = "compress-cache-results")]
+            // The `cec-cache-results` feature enables the `compress-cache-results` feature, so it
+            // has to be checked first.
+            if #[cfg(feature = "cec-cache-results")]
             {
-               decompress_util(bytes).await
+                let decompressed = decompress_util(bytes).await?;
+                let decrypted = self.encrypt_or_decrypt_results(decompressed, key, false).await?;
 
+                decompress_util(&decrypted).await
             }
-            else if  #[cfg(feature = "cec-cache-results")]
+            else if #[cfg(feature = "compress-cache-results")]
             {
-                let decompressed = decompress_util(bytes)?;
-                let decrypted = self.encrypt_or_decrypt_results(decompressed, false)?;
-
-                decompress_util(&decrypted).await
-
+               decompress_util(bytes).await
             }
         }
     }
@@ -217,34 +251,38 @@ pub trait Cacher: Send + Sync {
     /// # Arguments
     ///
     /// * `search_results` - A reference to the search_Results to process.
+    /// * `key` - It takes the hashed cache key the results are stored under as an argument, which
+    ///   the encrypted results are bound to.
     ///
 
     ///
     /// # Error
     /// Returns a Vec of compressed or encrypted bytes on success otherwise it returns a CacheError
     /// on failure.
+    #[allow(unused_variables)] // the key is only read when the results are encrypted
     async fn pre_process_search_results(
-        &mut self,
+        &self,
         search_results: &SearchResults,
+        key: &str,
     ) -> Result<Vec<u8>, Report<CacheError>> {
         #[allow(unused_mut)] // needs to be mutable when any of the features is enabled
         let mut bytes: Vec<u8> = search_results.try_into()?;
-        #[cfg(feature = "compress-cache-results")]
+        #[cfg(all(feature = "compress-cache-results", not(feature = "cec-cache-results")))]
         {
             let compressed = self.compress_results(bytes).await?;
             bytes = compressed;
         }
 
-        #[cfg(feature = "encrypt-cache-results")]
+        #[cfg(all(feature = "encrypt-cache-results", not(feature = "cec-cache-results")))]
         {
-            let encrypted = self.encrypt_or_decrypt_results(bytes, true).await?;
+            let encrypted = self.encrypt_or_decrypt_results(bytes, key, true).await?;
             bytes = encrypted;
         }
 
         #[cfg(feature = "cec-cache-results")]
         {
             let compressed_encrypted_compressed =
-                self.compress_encrypt_compress_results(bytes).await?;
+                self.compress_encrypt_compress_results(bytes, key).await?;
             bytes = compressed_encrypted_compressed;
         }
 
@@ -256,6 +294,7 @@ pub trait Cacher: Send + Sync {
     /// # Arguments
     ///
     /// * `bytes` - A Vec of bytes stores in the cache.
+    /// * `key` - It takes the hashed cache key the bytes are stored under as an argument.
     ///
 
     ///
@@ -263,26 +302,27 @@ pub trait Cacher: Send + Sync {
     /// Returns the SearchResults struct on success otherwise it returns a CacheError
     /// on failure.
 
-    #[allow(unused_mut)] // needs to be mutable when any of the features is enabled
+    #[allow(unused_mut, unused_variables)] // needs to be mutable and read the key when any of the features is enabled
     async fn post_process_search_results(
-        &mut self,
+        &self,
         mut bytes: Vec<u8>,
+        key: &str,
     ) -> Result<SearchResults, Report<CacheError>> {
-        #[cfg(feature = "compress-cache-results")]
+        #[cfg(all(feature = "compress-cache-results", not(feature = "cec-cache-results")))]
         {
-            let decompressed = self.decompress_results(&bytes).await?;
+            let decompressed = self.decompress_results(&bytes, key).await?;
             bytes = decompressed
         }
 
-        #[cfg(feature = "encrypt-cache-results")]
+        #[cfg(all(feature = "encrypt-cache-results", not(feature = "cec-cache-results")))]
         {
-            let decrypted = self.encrypt_or_decrypt_results(bytes, false).await?;
+            let decrypted = self.encrypt_or_decrypt_results(bytes, key, false).await?;
             bytes = decrypted
         }
 
         #[cfg(feature = "cec-cache-results")]
         {
-            let decompressed_decrypted = self.decompress_results(&bytes).await?;
+            let decompressed_decrypted = self.decompress_results(&bytes, key).await?;
             bytes = decompressed_decrypted;
         }
 
@@ -324,15 +364,15 @@ async fn decompress_util(input: &[u8]) -> Result<Vec<u8>, Report<CacheError>> {
 impl Cacher for RedisCache {
     async fn build(config: &Config) -> Self {
         log::info!(
-            "Initialising redis cache. Listening to {}",
-            &config.redis_url
+            "Initialising redis cache. Connecting to the {:?} deployment",
+            &config.redis.topology
         );
-        RedisCache::new(&config.redis_url, 5, config.cache_expiry_time)
+        RedisCache::new(&config.redis, 5, cache_ttl(config))
             .await
             .expect("Redis cache configured")
     }
 
-    async fn cached_results(&mut self, url: &str) -> Result<SearchResults, Report<CacheError>> {
+    async fn cached_results(&self, url: &str) -> Result<SearchResults, Report<CacheError>> {
         use base64::Engine;
         let hashed_url_string: &str = &self.hash_url(url);
         let base64_string = self.cached_json(hashed_url_string).await?;
@@ -340,11 +380,12 @@ impl Cacher for RedisCache {
         let bytes = base64::engine::general_purpose::STANDARD_NO_PAD
             .decode(base64_string)
             .map_err(|_| CacheError::Base64DecodingOrEncodingError)?;
-        self.post_process_search_results(bytes).await
+        self.post_process_search_results(bytes, hashed_url_string)
+            .await
     }
 
     async fn cache_results(
-        &mut self,
+        &self,
         search_results: &[SearchResults],
         urls: &[String],
     ) -> Result<(), Report<CacheError>> {
@@ -353,27 +394,84 @@ impl Cacher for RedisCache {
         // size of search_results is expected to be equal to size of urls -> key/value pairs  for cache;
         let search_results_len = search_results.len();
 
+        let mut hashed_url_strings = Vec::with_capacity(search_results_len);
+
+        for url in urls {
+            let hash = self.hash_url(url);
+            hashed_url_strings.push(hash);
+        }
+
         let mut bytes = Vec::with_capacity(search_results_len);
 
-        for result in search_results {
-            let processed = self.pre_process_search_results(result).await?;
+        for (result, hash) in search_results.iter().zip(hashed_url_strings.iter()) {
+            let processed = self.pre_process_search_results(result, hash).await?;
             bytes.push(processed);
         }
 
         let base64_strings = bytes
             .iter()
             .map(|bytes_vec| base64::engine::general_purpose::STANDARD_NO_PAD.encode(bytes_vec));
+        self.cache_json(base64_strings, hashed_url_strings.into_iter())
+            .await
+    }
 
-        let mut hashed_url_strings = Vec::with_capacity(search_results_len);
+    async fn stats(&self) -> Result<CacheStats, Report<CacheError>> {
+        self.usage().await
+    }
 
-        for url in urls {
-            let hash = self.hash_url(url);
-            hashed_url_strings.push(hash);
-        }
-        self.cache_json(base64_strings, hashed_url_strings.into_iter())
+    async fn purge(&self, query: Option<&str>) -> Result<u64, Report<CacheError>> {
+        self.remove_keys(&format!("{}*", key_prefix(query))).await
+    }
+}
+#[cfg(feature = "disk-cache")]
+#[async_trait::async_trait]
+impl Cacher for DiskCache {
+    async fn build(config: &Config) -> Self {
+        log::info!(
+            "Initialising disk cache. Storing the results in {}",
+            &config.disk_cache_path
+        );
+        DiskCache::new(
+            &config.disk_cache_path,
+            config.disk_cache_max_size,
+            cache_ttl(config),
+        )
+        .await
+        .expect("Disk cache configured")
+    }
+
+    async fn cached_results(&self, url: &str) -> Result<SearchResults, Report<CacheError>> {
+        let hashed_url_string = self.hash_url(url);
+        let bytes = self.cached_bytes(&hashed_url_string).await?;
+        self.post_process_search_results(bytes, &hashed_url_string)
             .await
     }
+
+    async fn cache_results(
+        &self,
+        search_results: &[SearchResults],
+        urls: &[String],
+    ) -> Result<(), Report<CacheError>> {
+        let mut entries = Vec::with_capacity(urls.len());
+        for (url, search_result) in urls.iter().zip(search_results.iter()) {
+            let hashed_url_string = self.hash_url(url);
+            let bytes = self
+                .pre_process_search_results(search_result, &hashed_url_string)
+                .await?;
+            entries.push((hashed_url_string, bytes));
+        }
+        self.cache_bytes(entries.into_iter()).await
+    }
+
+    async fn stats(&self) -> Result<CacheStats, Report<CacheError>> {
+        Ok(self.usage())
+    }
+
+    async fn purge(&self, query: Option<&str>) -> Result<u64, Report<CacheError>> {
+        self.remove_keys(&key_prefix(query)).await
+    }
 }
+
 /// TryInto implementation for SearchResults from Vec<u8>
 use std::{convert::TryInto, sync::Arc};
 
@@ -398,6 +496,8 @@ impl TryInto<Vec<u8>> for &SearchResults {
 pub struct InMemoryCache {
     /// The backend cache which stores data.
     cache: Arc<MokaCache<String, Vec<u8>>>,
+    /// The number of results removed by the backend cache as they expired.
+    evictions: Arc<AtomicU64>,
 }
 
 #[cfg(feature = "memory-cache")]
@@ -405,6 +505,7 @@ impl Clone for InMemoryCache {
     fn clone(&self) -> Self {
         Self {
             cache: self.cache.clone(),
+            evictions: self.evictions.clone(),
         }
     }
 }
@@ -415,32 +516,50 @@ impl Cacher for InMemoryCache {
     async fn build(config: &Config) -> Self {
         log::info!("Initialising in-memory cache");
 
+        let evictions: Arc<AtomicU64> = Default::default();
+        let evicted = evictions.clone();
         InMemoryCache {
             cache: Arc::new(
                 MokaCache::builder()
-                    .time_to_live(Duration::from_secs(config.cache_expiry_time.into()))
+                    .time_to_live(Duration::from_secs(cache_ttl(config).into()))
+                    // The entries are weighted by their number of bytes, so that the weighted
+                    // size of the cache is the memory taken by the cached results.
+                    .weigher(|key: &String, bytes: &Vec<u8>| {
+                        u32::try_from(key.len() + bytes.len()).unwrap_or(u32::MAX)
+                    })
+                    .eviction_listener(move |_key, _bytes, cause| {
+                        if cause.was_evicted() {
+                            evicted.fetch_add(1, Ordering::Relaxed);
+                        }
+                    })
                     .build(),
             ),
+            evictions,
         }
     }
 
-    async fn cached_results(&mut self, url: &str) -> Result<SearchResults, Report<CacheError>> {
+    async fn cached_results(&self, url: &str) -> Result<SearchResults, Report<CacheError>> {
         let hashed_url_string = self.hash_url(url);
         match self.cache.get(&hashed_url_string).await {
-            Some(res) => self.post_process_search_results(res).await,
+            Some(res) => {
+                self.post_process_search_results(res, &hashed_url_string)
+                    .await
+            }
             None => Err(Report::new(CacheError::MissingValue)),
         }
     }
 
     async fn cache_results(
-        &mut self,
+        &self,
         search_results: &[SearchResults],
         urls: &[String],
     ) -> Result<(), Report<CacheError>> {
         let mut tasks: Vec<_> = Vec::with_capacity(urls.len());
         for (url, search_result) in urls.iter().zip(search_results.iter()) {
             let hashed_url_string = self.hash_url(url);
-            let bytes = self.pre_process_search_results(search_result).await?;
+            let bytes = self
+                .pre_process_search_results(search_result, &hashed_url_string)
+                .await?;
             let new_self = self.clone();
             tasks.push(tokio::spawn(async move {
                 new_self.cache.insert(hashed_url_string, bytes).await
@@ -451,6 +570,30 @@ impl Cacher for InMemoryCache {
 
         Ok(())
     }
+
+    async fn stats(&self) -> Result<CacheStats, Report<CacheError>> {
+        self.cache.run_pending_tasks().await;
+        Ok(CacheStats {
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: self.cache.entry_count(),
+            size: self.cache.weighted_size(),
+            ..Default::default()
+        })
+    }
+
+    async fn purge(&self, query: Option<&str>) -> Result<u64, Report<CacheError>> {
+        let prefix = key_prefix(query);
+        let keys: Vec<Arc<String>> = self
+            .cache
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, _)| key)
+            .collect();
+        for key in keys.iter() {
+            self.cache.invalidate(key.as_str()).await;
+        }
+        Ok(keys.len() as u64)
+    }
 }
 
 /// Cache backend which utilises both memory and redis based caches.
@@ -477,7 +620,7 @@ impl Cacher for HybridCache {
         }
     }
 
-    async fn cached_results(&mut self, url: &str) -> Result<SearchResults, Report<CacheError>> {
+    async fn cached_results(&self, url: &str) -> Result<SearchResults, Report<CacheError>> {
         match self.redis_cache.cached_results(url).await {
             Ok(res) => Ok(res),
             Err(_) => self.memory_cache.cached_results(url).await,
@@ -485,7 +628,7 @@ impl Cacher for HybridCache {
     }
 
     async fn cache_results(
-        &mut self,
+        &self,
         search_results: &[SearchResults],
         urls: &[String],
     ) -> Result<(), Report<CacheError>> {
@@ -496,6 +639,19 @@ impl Cacher for HybridCache {
 
         Ok(())
     }
+
+    async fn stats(&self) -> Result<CacheStats, Report<CacheError>> {
+        match self.redis_cache.stats().await {
+            Ok(stats) => Ok(stats),
+            Err(_) => self.memory_cache.stats().await,
+        }
+    }
+
+    async fn purge(&self, query: Option<&str>) -> Result<u64, Report<CacheError>> {
+        // Both the caches hold the same results, so the results are only counted once.
+        let purged = self.memory_cache.purge(query).await?;
+        Ok(self.redis_cache.purge(query).await?.max(purged))
+    }
 }
 
 /// Dummy cache backend
@@ -508,23 +664,73 @@ impl Cacher for DisabledCache {
         DisabledCache
     }
 
-    async fn cached_results(&mut self, _url: &str) -> Result<SearchResults, Report<CacheError>> {
+    async fn cached_results(&self, _url: &str) -> Result<SearchResults, Report<CacheError>> {
         Err(Report::new(CacheError::MissingValue))
     }
 
     async fn cache_results(
-        &mut self,
+        &self,
         _search_results: &[SearchResults],
         _urls: &[String],
     ) -> Result<(), Report<CacheError>> {
         Ok(())
     }
+
+    async fn stats(&self) -> Result<CacheStats, Report<CacheError>> {
+        Ok(CacheStats::default())
+    }
+
+    async fn purge(&self, _query: Option<&str>) -> Result<u64, Report<CacheError>> {
+        Ok(0)
+    }
+}
+
+/// A named struct which stores the statistics of the cache, which are served to the admins of
+/// the server.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct CacheStats {
+    /// It stores the number of lookups which found the results in the cache.
+    pub hits: u64,
+    /// It stores the number of lookups which did not find the results in the cache.
+    pub misses: u64,
+    /// It stores the number of results removed by the cache backend as they expired or the cache
+    /// was full.
+    pub evictions: u64,
+    /// It stores the number of results held by the cache backend.
+    pub entries: u64,
+    /// It stores the number of bytes taken by the results held by the cache backend.
+    pub size: u64,
 }
 
-/// A structure to efficiently share the cache between threads - as it is protected by a Mutex.
+/// A type alias for the results of a search which is in flight, which are shared by all the
+/// identical searches waiting for it.
+type InFlightResults = Arc<OnceCell<Result<SearchResults, String>>>;
+
+/// A structure to efficiently share the cache between threads - as the cache backends handle the
+/// concurrent reads and writes themselves, it is shared without being locked.
+///
+/// The cached results are stamped with the time at which they were cached. They are fresh until
+/// the cache expiry time has passed, after which they are still served for the stale time while
+/// they are refreshed in the background (stale-while-revalidate). The cached failures of the
+/// upstream search engines, and the pagination buffers holding them, are only fresh for the
+/// shorter failure time, so that the engines are requested again soon after a transient failure. The identical searches which
+/// run concurrently are coalesced, so that only one of them fetches the results from the upstream
+/// search engines while the others wait for its results.
 pub struct SharedCache {
-    /// The internal cache protected from concurrent access by a mutex
-    cache: Mutex<Box<dyn Cacher>>,
+    /// The internal cache which is accessed concurrently.
+    cache: Box<dyn Cacher>,
+    /// The time (secs) for which the cached results are fresh.
+    expiry_time: u64,
+    /// The time (secs) for which the expired results are still served while they are refreshed.
+    stale_time: u64,
+    /// The time (secs) for which the cached failures of the upstream search engines are fresh.
+    failure_time: u64,
+    /// The results of the searches in flight mapped to their keys.
+    in_flight: std::sync::Mutex<HashMap<String, InFlightResults>>,
+    /// The number of lookups which found the results in the cache.
+    hits: AtomicU64,
+    /// The number of lookups which did not find the results in the cache.
+    misses: AtomicU64,
 }
 
 impl SharedCache {
@@ -533,15 +739,41 @@ impl SharedCache {
     /// # Arguments
     ///
     /// * `cache` - It takes the `Cache` enum variant as an argument with the prefered cache type.
+    /// * `config` - It takes the config struct holding the cache expiry, stale and failure times
+    ///   as an argument.
     ///
     /// Returns a newly constructed `SharedCache` struct.
-    pub fn new(cache: impl Cacher + 'static) -> Self {
+    #[allow(unused_variables)] // the config is only read when a cache backend is enabled
+    pub fn new(cache: impl Cacher + 'static, config: &Config) -> Self {
+        #[cfg(any(
+            feature = "redis-cache",
+            feature = "memory-cache",
+            feature = "disk-cache"
+        ))]
+        let (expiry_time, stale_time, failure_time) = (
+            config.cache_expiry_time.into(),
+            config.cache_stale_time.into(),
+            config.cache_failure_time.into(),
+        );
+        #[cfg(not(any(
+            feature = "redis-cache",
+            feature = "memory-cache",
+            feature = "disk-cache"
+        )))]
+        let (expiry_time, stale_time, failure_time) = (0, 0, 0);
+
         Self {
-            cache: Mutex::new(Box::new(cache)),
+            cache: Box::new(cache),
+            expiry_time,
+            stale_time,
+            failure_time,
+            in_flight: Default::default(),
+            hits: Default::default(),
+            misses: Default::default(),
         }
     }
 
-    /// A getter function which retrieves the cached SearchResulsts from the internal cache.
+    /// A getter function which retrieves the fresh cached SearchResulsts from the internal cache.
     ///
     /// # Arguments
     ///
@@ -551,14 +783,18 @@ impl SharedCache {
     /// # Error
     ///
     /// Returns a `SearchResults` struct containing the search results from the cache if nothing
-    /// goes wrong otherwise returns a `CacheError`.
+    /// goes wrong otherwise returns a `CacheError` if the results are missing or have expired.
     pub async fn cached_results(&self, url: &str) -> Result<SearchResults, Report<CacheError>> {
-        let mut mut_cache = self.cache.lock().await;
-        mut_cache.cached_results(url).await
+        match self.cached_entry(url).await? {
+            (results, Freshness::Fresh) => Ok(results),
+            (_, Freshness::Stale) => Err(Report::new(CacheError::MissingValue)),
+        }
     }
 
     /// A setter function which caches the results by using the `url` as the key and
-    /// `SearchResults` as the value.
+    /// `SearchResults` as the value. The results which have not been cached before are stamped
+    /// with the current time, while the results read from the cache keep their stamp so that
+    /// updating a stale entry does not make it fresh.
     ///
     /// # Arguments
     ///
@@ -576,24 +812,437 @@ impl SharedCache {
         search_results: &[SearchResults],
         urls: &[String],
     ) -> Result<(), Report<CacheError>> {
-        let mut mut_cache = self.cache.lock().await;
-        mut_cache.cache_results(search_results, urls).await
+        let now = unix_time();
+        let search_results: Vec<SearchResults> = search_results
+            .iter()
+            .map(|results| {
+                let mut results = results.clone();
+                if results.cached_at == 0 {
+                    results.cached_at = now;
+                }
+                results
+            })
+            .collect();
+
+        self.cache.cache_results(&search_results, urls).await
     }
+
+    /// A function which retrieves the statistics of the cache, which combine the lookups counted
+    /// since the server has started with the usage of the cache backend.
+    ///
+    /// # Error
+    ///
+    /// Returns the `CacheStats` on success otherwise returns a `CacheError` if the usage of the
+    /// cache backend cannot be retrieved.
+    pub async fn stats(&self) -> Result<CacheStats, Report<CacheError>> {
+        Ok(CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            ..self.cache.stats().await?
+        })
+    }
+
+    /// A function which removes the cached results of a search query, or all the cached results
+    /// when no query is given.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query whose results should be removed as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the number of removed results on success otherwise returns a `CacheError` on a
+    /// failure.
+    pub async fn purge(&self, query: Option<&str>) -> Result<u64, Report<CacheError>> {
+        self.cache.purge(query).await
+    }
+
+    /// A function which retrieves the cached results of the `url`, fetching them with `fetch`
+    /// when they are not cached. The expired results are served while they are refreshed in the
+    /// background within the stale time, and the concurrent fetches of the same `url` are
+    /// coalesced into one.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the search url as an argument which will be used as the key of the
+    ///   results in the cache.
+    /// * `fetch` - It takes a function fetching the fresh results of the `url` as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the cached or fetched `SearchResults` on success otherwise returns the error of
+    /// `fetch` or of the cache.
+    pub async fn cached_or_fetched<F, Fut>(
+        &'static self,
+        url: &str,
+        fetch: F,
+    ) -> Result<SearchResults, Box<dyn std::error::Error>>
+    where
+        F: FnOnce() -> Fut + 'static,
+        Fut: Future<Output = Result<SearchResults, Box<dyn std::error::Error>>>,
+    {
+        match self.cached_entry(url).await {
+            Ok((results, Freshness::Fresh)) => Ok(results),
+            Ok((results, Freshness::Stale)) => {
+                let url = url.to_owned();
+                actix_web::rt::spawn(async move {
+                    if let Err(error) = self
+                        .coalesce(&url, || self.fetch_and_cache(&url, fetch))
+                        .await
+                    {
+                        log::error!("Cache Error: failed to refresh the stale results: {error}");
+                    }
+                });
+                Ok(results)
+            }
+            Err(_) => Ok(self
+                .coalesce(url, || self.fetch_and_cache(url, fetch))
+                .await?),
+        }
+    }
+
+    /// A function which coalesces the concurrent calls with the same key, so that `fetch` runs
+    /// only once and all the callers get its results. If the caller running `fetch` is cancelled,
+    /// one of the waiting callers runs its own `fetch` instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - It takes the key identifying the identical calls as an argument.
+    /// * `fetch` - It takes a function fetching the results as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the shared `SearchResults` on success otherwise returns the shared message of the
+    /// error of `fetch`.
+    pub async fn coalesce<F, Fut>(&self, key: &str, fetch: F) -> Result<SearchResults, String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<SearchResults, Box<dyn std::error::Error>>>,
+    {
+        let in_flight_results = self
+            .in_flight
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(key.to_owned())
+            .or_default()
+            .clone();
+
+        let results = in_flight_results
+            .get_or_init(|| async { fetch().await.map_err(|error| error.to_string()) })
+            .await
+            .clone();
+
+        // The next calls with the key run a new fetch, unless a newer one is already in flight.
+        let mut in_flight = self
+            .in_flight
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if in_flight
+            .get(key)
+            .is_some_and(|entry| Arc::ptr_eq(entry, &in_flight_results))
+        {
+            in_flight.remove(key);
+        }
+
+        results
+    }
+
+    /// A helper function which fetches the results with `fetch` and caches them with the `url`
+    /// as the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the key of the results in the cache as an argument.
+    /// * `fetch` - It takes a function fetching the results as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the fetched `SearchResults` on success otherwise returns the error of `fetch` or
+    /// of the cache.
+    async fn fetch_and_cache<F, Fut>(
+        &self,
+        url: &str,
+        fetch: F,
+    ) -> Result<SearchResults, Box<dyn std::error::Error>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<SearchResults, Box<dyn std::error::Error>>>,
+    {
+        let mut results = fetch().await?;
+        results.cached_at = 0;
+        self.cache_results(&[results.clone()], &[url.to_owned()])
+            .await?;
+        Ok(results)
+    }
+
+    /// A helper function which retrieves the cached results of the `url` along with their
+    /// freshness.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the key of the results in the cache as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the cached `SearchResults` along with their freshness on success otherwise
+    /// returns a `CacheError` if the results are missing or have expired beyond the stale time.
+    async fn cached_entry(
+        &self,
+        url: &str,
+    ) -> Result<(SearchResults, Freshness), Report<CacheError>> {
+        let entry = self.cache.cached_results(url).await.and_then(|results| {
+            let expiry_time = match results.engine_errors_info.is_empty() {
+                true => self.expiry_time,
+                false => self.failure_time,
+            };
+            let age = unix_time().saturating_sub(results.cached_at);
+            match age {
+                _ if age < expiry_time => Ok((results, Freshness::Fresh)),
+                _ if age < expiry_time + self.stale_time => Ok((results, Freshness::Stale)),
+                _ => Err(Report::new(CacheError::MissingValue)),
+            }
+        });
+
+        match entry {
+            Ok(_) => &self.hits,
+            Err(_) => &self.misses,
+        }
+        .fetch_add(1, Ordering::Relaxed);
+        entry
+    }
+}
+
+/// An enum which tells whether the cached results are fresh or have expired and are being served
+/// while they are refreshed.
+enum Freshness {
+    /// The results were cached less than the cache expiry time ago.
+    Fresh,
+    /// The results have expired but are still within the stale time.
+    Stale,
+}
+
+/// A helper function which computes the time (secs) for which the cache backends keep the
+/// results, which covers both the time for which they are fresh and for which they are stale.
+///
+/// # Arguments
+///
+/// * `config` - It takes the config struct as an argument.
+#[cfg(any(
+    feature = "redis-cache",
+    feature = "memory-cache",
+    feature = "disk-cache"
+))]
+fn cache_ttl(config: &Config) -> u16 {
+    config
+        .cache_expiry_time
+        .saturating_add(config.cache_stale_time)
 }
 
-/// A function to initialise the cache backend.
+/// A helper function which builds the prefix of the keys of the cached results of a search query,
+/// which is empty when no query is given so that it is shared by all the keys.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query as an argument, which is normalized like the queries of
+///   the cache keys.
+fn key_prefix(query: Option<&str>) -> String {
+    query
+        .map(|query| format!("{}:", blake3::hash(normalize_query(query).as_bytes())))
+        .unwrap_or_default()
+}
+
+/// A helper function which returns the current unix time in seconds.
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// A function to initialise the cache backend. The disk cache is used instead of the in-memory
+/// cache when the `disk-cache` feature is enabled, which cannot be combined with the redis cache.
 pub async fn create_cache(config: &Config) -> impl Cacher {
-    #[cfg(all(feature = "redis-cache", feature = "memory-cache"))]
+    #[cfg(feature = "disk-cache")]
+    return DiskCache::build(config).await;
+
+    #[cfg(all(
+        feature = "redis-cache",
+        feature = "memory-cache",
+        not(feature = "disk-cache")
+    ))]
     return HybridCache::build(config).await;
 
-    #[cfg(all(feature = "memory-cache", not(feature = "redis-cache")))]
+    #[cfg(all(
+        feature = "memory-cache",
+        not(feature = "redis-cache"),
+        not(feature = "disk-cache")
+    ))]
     return InMemoryCache::build(config).await;
 
-    #[cfg(all(feature = "redis-cache", not(feature = "memory-cache")))]
+    #[cfg(all(
+        feature = "redis-cache",
+        not(feature = "memory-cache"),
+        not(feature = "disk-cache")
+    ))]
     return RedisCache::build(config).await;
 
-    #[cfg(not(any(feature = "memory-cache", feature = "redis-cache")))]
+    #[cfg(not(any(
+        feature = "memory-cache",
+        feature = "redis-cache",
+        feature = "disk-cache"
+    )))]
     return DisabledCache::build(config).await;
 }
 
 //#[cfg(feature = "Compress-cache-results")]
+
+#[cfg(all(test, feature = "memory-cache"))]
+mod tests {
+    use super::*;
+    use crate::cache::cache_key::CacheKey;
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    /// A helper function which builds a shared in-memory cache with the given expiry and stale
+    /// times, which is leaked so that it can refresh the stale results in the background.
+    async fn shared_cache(expiry_time: u64, stale_time: u64) -> &'static SharedCache {
+        let config = Config::parse(true).unwrap();
+        let mut cache = SharedCache::new(InMemoryCache::build(&config).await, &config);
+        cache.expiry_time = expiry_time;
+        cache.stale_time = stale_time;
+        cache.failure_time = 0;
+        Box::leak(Box::new(cache))
+    }
+
+    /// A helper function which mocks the results of a search with the given related search.
+    fn mock_results(related_search: &str) -> SearchResults {
+        SearchResults::new(
+            Box::default(),
+            Box::default(),
+            Box::new([related_search.to_owned()]),
+        )
+    }
+
+    #[actix_web::test]
+    async fn test_identical_searches_are_coalesced() {
+        let cache = shared_cache(600, 0).await;
+        let fetches = AtomicU32::new(0);
+        let fetch = || async {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(mock_results("fetched"))
+        };
+
+        let (first, second) =
+            tokio::join!(cache.coalesce("rust", fetch), cache.coalesce("rust", fetch));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(first.unwrap().related_searches[0], "fetched");
+        assert_eq!(second.unwrap().related_searches[0], "fetched");
+
+        // Once the search has completed, the next one runs a new fetch.
+        cache.coalesce("rust", fetch).await.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_stale_results_are_served_while_refreshed() {
+        // The results expire as soon as they are cached but are served while stale.
+        let cache = shared_cache(0, 600).await;
+        let url = CacheKey::new("rust", ["bing"], 2).to_string();
+        cache
+            .cache_results(&[mock_results("stale")], &[url.clone()])
+            .await
+            .unwrap();
+        assert!(cache.cached_results(&url).await.is_err());
+
+        let results = cache
+            .cached_or_fetched(&url, || async { Ok(mock_results("refreshed")) })
+            .await
+            .unwrap();
+        assert_eq!(results.related_searches[0], "stale");
+
+        // The refresh runs in the background and replaces the stale results.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let (results, _) = cache.cached_entry(&url).await.unwrap();
+        assert_eq!(results.related_searches[0], "refreshed");
+    }
+
+    #[actix_web::test]
+    async fn test_engine_failures_expire_sooner() {
+        use crate::models::{aggregation_models::EngineErrorInfo, engine_models::EngineError};
+
+        // The failures of the engines expire as soon as they are cached, unlike their results.
+        let cache = shared_cache(600, 0).await;
+        let mut failure = mock_results("failed");
+        failure.engine_errors_info =
+            Box::new([EngineErrorInfo::new(&EngineError::RequestError, "bing")]);
+        let urls = ["rust", "lua"].map(|query| CacheKey::new(query, ["bing"], 2).to_string());
+        cache
+            .cache_results(&[mock_results("succeeded"), failure], &urls)
+            .await
+            .unwrap();
+
+        assert!(cache.cached_results(&urls[0]).await.is_ok());
+        assert!(cache.cached_results(&urls[1]).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_transient_failures_are_retried_after_the_failure_time() {
+        use crate::models::{aggregation_models::EngineErrorInfo, engine_models::EngineError};
+
+        let config = Config::parse(true).unwrap();
+        let mut cache = SharedCache::new(InMemoryCache::build(&config).await, &config);
+        cache.expiry_time = 600;
+        cache.stale_time = 0;
+        cache.failure_time = 1;
+        let cache: &'static SharedCache = Box::leak(Box::new(cache));
+
+        // The aggregated results are fetched while an engine fails to be requested.
+        let url = CacheKey::new("rust", ["bing", "brave"], 2).to_string();
+        let fetches = Arc::new(AtomicU32::new(0));
+        let fetch = |fetches: Arc<AtomicU32>| {
+            move || async move {
+                let mut results = mock_results("fetched");
+                if fetches.fetch_add(1, Ordering::SeqCst) == 0 {
+                    results.engine_errors_info =
+                        Box::new([EngineErrorInfo::new(&EngineError::RequestError, "bing")]);
+                }
+                Ok(results)
+            }
+        };
+        let results = cache
+            .cached_or_fetched(&url, fetch(fetches.clone()))
+            .await
+            .unwrap();
+        assert_eq!(results.engine_errors_info.len(), 1);
+
+        // The results holding the failure are served until the failure time has passed.
+        let results = cache
+            .cached_or_fetched(&url, fetch(fetches.clone()))
+            .await
+            .unwrap();
+        assert_eq!(results.engine_errors_info.len(), 1);
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // The engine is then requested again, and its results are cached for the expiry time.
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let results = cache
+            .cached_or_fetched(&url, fetch(fetches.clone()))
+            .await
+            .unwrap();
+        assert!(results.engine_errors_info.is_empty());
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert!(cache.cached_results(&url).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_keys_are_prefixed_by_their_query() {
+        let key = CacheKey::new("Rust & Lua", ["bing"], 2);
+        let hash = DisabledCache.hash_url(&key.to_string());
+        assert!(hash.starts_with(&key_prefix(Some("rust  &  lua"))));
+        assert_ne!(hash, DisabledCache.hash_url(&key.with_page(1).to_string()));
+    }
+}
@@ -0,0 +1,544 @@
+//! This module provides the functionality to cache the aggregated results fetched and aggregated
+//! from the upstream search engines in an embedded on-disk database, so that the cached results
+//! survive the restarts of the server.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{cacher::CacheStats, error::CacheError};
+use error_stack::Report;
+use redb::{Database, Durability, ReadableTable, TableDefinition};
+
+/// The table of the database holding the cached results mapped to their keys, along with the
+/// unix time (in seconds) at which they expire.
+const SEARCH_RESULTS_TABLE: TableDefinition<'_, &str, (u64, &[u8])> =
+    TableDefinition::new("search_results");
+
+/// The number of bytes stored along with every cached result (its expiry time), which is counted
+/// towards the size of the cache.
+const ENTRY_OVERHEAD: u64 = 8;
+
+/// A named struct which stores the information needed to evict a cached result.
+struct EntryInfo {
+    /// It stores the tick of the last access to the entry, which orders the entries from the
+    /// least to the most recently used.
+    tick: u64,
+    /// It stores the number of bytes taken by the entry.
+    size: u64,
+    /// It stores the unix time (in seconds) at which the entry expires.
+    expires_at: u64,
+}
+
+/// A named struct which stores the recency of the cached results, which are evicted from the
+/// least recently used once the cache grows beyond its max size.
+struct Recency {
+    /// It stores the information of every cached result mapped to its key.
+    entries: HashMap<String, EntryInfo>,
+    /// It stores the keys of the cached results ordered from the least to the most recently
+    /// used.
+    recency: BTreeMap<u64, String>,
+    /// It stores the tick given to the next accessed entry.
+    next_tick: u64,
+    /// It stores the number of bytes taken by all the cached results.
+    total_size: u64,
+    /// It stores the max number of bytes the cached results can take.
+    max_size: u64,
+    /// It stores the number of results removed as they expired or the cache was full.
+    evictions: u64,
+}
+
+/// A named struct which stores the on-disk database along with the recency of its entries.
+///
+/// The database handles the concurrent reads and writes itself, so only the recency of the
+/// entries is protected by a lock, which is never held while the database is accessed.
+pub struct DiskCache {
+    /// It stores the embedded database holding the cached results.
+    database: Arc<Database>,
+    /// It stores the recency of the cached results.
+    recency: Mutex<Recency>,
+    /// It stores the max TTL for keys.
+    cache_ttl: u16,
+}
+
+impl DiskCache {
+    /// A function which opens (or creates) the on-disk database and drops the expired results
+    /// left from a previous run. The recency of the remaining results is rebuilt from the order
+    /// in which they were cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - It takes the path of the database file.
+    /// * `max_size` - It takes the max number of bytes the cached results can take.
+    /// * `cache_ttl` - It takes the the time to live for cached results to live in the cache.
+    ///
+    /// # Error
+    ///
+    /// Returns a newly constructed `DiskCache` struct on success otherwise returns a standard
+    /// error type.
+    pub async fn new(
+        path: &str,
+        max_size: u64,
+        cache_ttl: u16,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(folder) = Path::new(path).parent() {
+            std::fs::create_dir_all(folder)?;
+        }
+
+        let path = path.to_owned();
+        let (database, mut stored_entries) =
+            tokio::task::spawn_blocking(move || -> Result<_, CacheError> {
+                let database = Database::create(path).map_err(disk_error)?;
+                let now = unix_time();
+                let mut stored_entries = Vec::new();
+
+                let transaction = database.begin_write().map_err(disk_error)?;
+                {
+                    let mut table = transaction
+                        .open_table(SEARCH_RESULTS_TABLE)
+                        .map_err(disk_error)?;
+                    let mut expired_keys = Vec::new();
+                    for entry in table.iter().map_err(disk_error)? {
+                        let (key, value) = entry.map_err(disk_error)?;
+                        let (expires_at, bytes) = value.value();
+                        if expires_at <= now {
+                            expired_keys.push(key.value().to_owned());
+                        } else {
+                            stored_entries.push((
+                                key.value().to_owned(),
+                                entry_size(key.value(), bytes),
+                                expires_at,
+                            ));
+                        }
+                    }
+                    for key in expired_keys {
+                        table.remove(key.as_str()).map_err(disk_error)?;
+                    }
+                }
+                transaction.commit().map_err(disk_error)?;
+
+                Ok((database, stored_entries))
+            })
+            .await?
+            .map_err(|error| error.to_string())?;
+
+        let mut recency = Recency {
+            entries: HashMap::with_capacity(stored_entries.len()),
+            recency: BTreeMap::new(),
+            next_tick: Default::default(),
+            total_size: Default::default(),
+            max_size,
+            evictions: Default::default(),
+        };
+
+        // All the results share the same time to live, so the results expiring first are the
+        // ones cached first.
+        stored_entries.sort_by_key(|(_, _, expires_at)| *expires_at);
+        for (key, size, expires_at) in stored_entries {
+            recency.track(key, size, expires_at);
+        }
+        let evicted_keys = recency.evict();
+
+        let disk_cache = DiskCache {
+            database: Arc::new(database),
+            recency: Mutex::new(recency),
+            cache_ttl,
+        };
+        disk_cache
+            .write(Vec::new(), evicted_keys)
+            .await
+            .map_err(|error| error.to_string())?;
+
+        Ok(disk_cache)
+    }
+
+    /// A function which fetches the cached bytes from the on-disk database.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - It takes a string as key.
+    ///
+    /// # Error
+    ///
+    /// Returns the cached bytes on success otherwise returns a `CacheError` if the key is missing,
+    /// has expired or the database cannot be read.
+    pub async fn cached_bytes(&self, key: &str) -> Result<Vec<u8>, Report<CacheError>> {
+        let expires_at = match self.recency().entries.get(key) {
+            Some(entry) => entry.expires_at,
+            None => return Err(Report::new(CacheError::MissingValue)),
+        };
+
+        if expires_at <= unix_time() {
+            {
+                let mut recency = self.recency();
+                recency.untrack(key);
+                recency.evictions += 1;
+            }
+            self.write(Vec::new(), vec![key.to_owned()]).await?;
+            return Err(Report::new(CacheError::MissingValue));
+        }
+
+        let database = self.database.clone();
+        let owned_key = key.to_owned();
+        let bytes = tokio::task::spawn_blocking(move || -> Result<_, CacheError> {
+            let transaction = database.begin_read().map_err(disk_error)?;
+            let table = transaction
+                .open_table(SEARCH_RESULTS_TABLE)
+                .map_err(disk_error)?;
+            Ok(table
+                .get(owned_key.as_str())
+                .map_err(disk_error)?
+                .map(|value| value.value().1.to_vec()))
+        })
+        .await
+        .map_err(join_error)??;
+
+        match bytes {
+            Some(bytes) => {
+                self.recency().touch(key);
+                Ok(bytes)
+            }
+            None => {
+                self.recency().untrack(key);
+                Err(Report::new(CacheError::MissingValue))
+            }
+        }
+    }
+
+    /// A function which caches the bytes by using the keys and stores them in the on-disk
+    /// database with the ttl(time to live) of the cache, evicting the least recently used
+    /// results if the cache grows beyond its max size.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - It takes the keys along with the bytes to cache as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an unit type if the results are cached succesfully otherwise returns a `CacheError`
+    /// on a failure.
+    pub async fn cache_bytes(
+        &self,
+        entries: impl Iterator<Item = (String, Vec<u8>)>,
+    ) -> Result<(), Report<CacheError>> {
+        let expires_at = unix_time() + u64::from(self.cache_ttl);
+        let entries: Vec<(String, Vec<u8>)> = entries.collect();
+
+        let (inserted_entries, evicted_keys) = {
+            let mut recency = self.recency();
+            for (key, bytes) in entries.iter() {
+                recency.untrack(key);
+                recency.track(key.clone(), entry_size(key, bytes), expires_at);
+            }
+            let evicted_keys = recency.evict();
+
+            let inserted_entries = entries
+                .into_iter()
+                .filter(|(key, _)| recency.entries.contains_key(key))
+                .map(|(key, bytes)| (key, expires_at, bytes))
+                .collect();
+            (inserted_entries, evicted_keys)
+        };
+        self.write(inserted_entries, evicted_keys).await
+    }
+
+    /// A function which computes the usage of the on-disk database.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of evictions, entries and bytes of the cache.
+    pub fn usage(&self) -> CacheStats {
+        let recency = self.recency();
+        CacheStats {
+            evictions: recency.evictions,
+            entries: recency.entries.len() as u64,
+            size: recency.total_size,
+            ..Default::default()
+        }
+    }
+
+    /// A function which removes the cached results whose keys start with the given prefix from
+    /// the on-disk database.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - It takes the prefix of the keys to remove as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the number of removed results on success otherwise returns a `CacheError` if the
+    /// database cannot be written.
+    pub async fn remove_keys(&self, prefix: &str) -> Result<u64, Report<CacheError>> {
+        let removed_keys: Vec<String> = {
+            let mut recency = self.recency();
+            let removed_keys: Vec<String> = recency
+                .entries
+                .keys()
+                .filter(|key| key.starts_with(prefix))
+                .cloned()
+                .collect();
+            for key in removed_keys.iter() {
+                recency.untrack(key);
+            }
+            removed_keys
+        };
+        let removed = removed_keys.len() as u64;
+        self.write(Vec::new(), removed_keys).await?;
+        Ok(removed)
+    }
+
+    /// A helper function which inserts and removes the entries of the on-disk database in a
+    /// single transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `inserted_entries` - It takes the keys, the expiry times and the bytes of the entries to
+    ///   insert as an argument.
+    /// * `removed_keys` - It takes the keys of the entries to remove as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an unit type on success otherwise returns a `CacheError` if the database cannot
+    /// be written.
+    async fn write(
+        &self,
+        inserted_entries: Vec<(String, u64, Vec<u8>)>,
+        removed_keys: Vec<String>,
+    ) -> Result<(), Report<CacheError>> {
+        if inserted_entries.is_empty() && removed_keys.is_empty() {
+            return Ok(());
+        }
+
+        let database = self.database.clone();
+        tokio::task::spawn_blocking(move || -> Result<(), CacheError> {
+            let mut transaction = database.begin_write().map_err(disk_error)?;
+            // The cached results can be fetched again from the upstream search engines, so the
+            // write is not waited to be flushed to the disk.
+            transaction.set_durability(Durability::Eventual);
+            {
+                let mut table = transaction
+                    .open_table(SEARCH_RESULTS_TABLE)
+                    .map_err(disk_error)?;
+                for (key, expires_at, bytes) in inserted_entries.iter() {
+                    table
+                        .insert(key.as_str(), (*expires_at, bytes.as_slice()))
+                        .map_err(disk_error)?;
+                }
+                for key in removed_keys.iter() {
+                    table.remove(key.as_str()).map_err(disk_error)?;
+                }
+            }
+            transaction.commit().map_err(disk_error)?;
+            Ok(())
+        })
+        .await
+        .map_err(join_error)??;
+        Ok(())
+    }
+
+    /// A helper function which locks the recency of the cached results.
+    ///
+    /// The lock is never held across an await point, so a panic while it is held cannot leave the
+    /// recency in an inconsistent state and a poisoned lock is recovered.
+    fn recency(&self) -> MutexGuard<'_, Recency> {
+        self.recency.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Recency {
+    /// A helper function which starts tracking an entry as the most recently used one.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - It takes the key of the entry as an argument.
+    /// * `size` - It takes the number of bytes taken by the entry as an argument.
+    /// * `expires_at` - It takes the unix time at which the entry expires as an argument.
+    fn track(&mut self, key: String, size: u64, expires_at: u64) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        self.total_size += size;
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(
+            key,
+            EntryInfo {
+                tick,
+                size,
+                expires_at,
+            },
+        );
+    }
+
+    /// A helper function which stops tracking an entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - It takes the key of the entry as an argument.
+    fn untrack(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.tick);
+            self.total_size -= entry.size;
+        }
+    }
+
+    /// A helper function which marks an entry as the most recently used one.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - It takes the key of the entry as an argument.
+    fn touch(&mut self, key: &str) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.tick);
+            entry.tick = self.next_tick;
+            self.next_tick += 1;
+            self.recency.insert(entry.tick, key.to_owned());
+        }
+    }
+
+    /// A helper function which stops tracking the least recently used entries until the cache
+    /// fits in its max size.
+    ///
+    /// # Returns
+    ///
+    /// Returns the keys of the evicted entries which should be removed from the database.
+    fn evict(&mut self) -> Vec<String> {
+        let mut evicted_keys = Vec::new();
+        while self.total_size > self.max_size {
+            let Some((_, key)) = self.recency.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&key) {
+                self.total_size -= entry.size;
+            }
+            self.evictions += 1;
+            evicted_keys.push(key);
+        }
+        evicted_keys
+    }
+}
+
+/// A helper function which computes the number of bytes taken by an entry of the cache.
+///
+/// # Arguments
+///
+/// * `key` - It takes the key of the entry as an argument.
+/// * `bytes` - It takes the cached bytes of the entry as an argument.
+fn entry_size(key: &str, bytes: &[u8]) -> u64 {
+    (key.len() + bytes.len()) as u64 + ENTRY_OVERHEAD
+}
+
+/// A helper function which returns the current unix time in seconds.
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// A helper function which converts the error of a panicked database task into a `CacheError`.
+///
+/// # Arguments
+///
+/// * `error` - It takes the error of the panicked task as an argument.
+fn join_error(error: tokio::task::JoinError) -> CacheError {
+    disk_error(std::io::Error::other(error))
+}
+
+/// A helper function which converts the errors of the on-disk database into a `CacheError`.
+///
+/// # Arguments
+///
+/// * `error` - It takes the error of the database as an argument.
+fn disk_error(error: impl Into<redb::Error>) -> CacheError {
+    CacheError::DiskCacheError(Box::new(error.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A helper function which opens a disk cache in the given folder.
+    async fn open(folder: &tempfile::TempDir, max_size: u64) -> DiskCache {
+        let path = folder.path().join("cache.redb");
+        DiskCache::new(path.to_str().unwrap(), max_size, 600)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cached_results_survive_restarts() {
+        let folder = tempfile::tempdir().unwrap();
+
+        let cache = open(&folder, 1024).await;
+        cache
+            .cache_bytes([("rust".to_owned(), b"results".to_vec())].into_iter())
+            .await
+            .unwrap();
+        drop(cache);
+
+        let cache = open(&folder, 1024).await;
+        assert_eq!(cache.cached_bytes("rust").await.unwrap(), b"results");
+        assert!(cache.cached_bytes("lua").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_results_are_evicted() {
+        let folder = tempfile::tempdir().unwrap();
+        // Every entry takes 4 + 100 + 8 bytes, so the cache holds two of them.
+        let cache = open(&folder, 250).await;
+
+        for key in ["key1", "key2"] {
+            cache
+                .cache_bytes([(key.to_owned(), vec![0; 100])].into_iter())
+                .await
+                .unwrap();
+        }
+        // Accessing the first entry makes the second one the least recently used.
+        cache.cached_bytes("key1").await.unwrap();
+        cache
+            .cache_bytes([("key3".to_owned(), vec![0; 100])].into_iter())
+            .await
+            .unwrap();
+
+        assert!(cache.cached_bytes("key1").await.is_ok());
+        assert!(cache.cached_bytes("key2").await.is_err());
+        assert!(cache.cached_bytes("key3").await.is_ok());
+        assert!(cache.recency().total_size <= 250);
+        assert_eq!(cache.usage().evictions, 1);
+        drop(cache);
+
+        let cache = open(&folder, 250).await;
+        assert!(cache.cached_bytes("key2").await.is_err());
+        assert!(cache.cached_bytes("key3").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_results_are_removed_by_prefix() {
+        let folder = tempfile::tempdir().unwrap();
+        let cache = open(&folder, 1024).await;
+        cache
+            .cache_bytes(
+                [
+                    ("rust:1".to_owned(), b"results".to_vec()),
+                    ("rust:2".to_owned(), b"results".to_vec()),
+                    ("lua:1".to_owned(), b"results".to_vec()),
+                ]
+                .into_iter(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(cache.remove_keys("rust:").await.unwrap(), 2);
+        assert!(cache.cached_bytes("rust:1").await.is_err());
+        assert!(cache.cached_bytes("lua:1").await.is_ok());
+        assert_eq!(cache.usage().entries, 1);
+        drop(cache);
+
+        // The removed results are also removed from the database.
+        let cache = open(&folder, 1024).await;
+        assert!(cache.cached_bytes("rust:2").await.is_err());
+        assert_eq!(cache.usage().entries, 1);
+    }
+}
@@ -1,25 +1,376 @@
+//! This module provides the functionality to encrypt and decrypt the cached search results with
+//! ChaCha20-Poly1305. Every entry is encrypted with a fresh random nonce which is stored along
+//! with the ciphertext, and the keys are loaded from the key file referenced from the config file
+//! or from the `WEBSURFX_CACHE_ENCRYPTION_KEYS` environment variable, so that the entries stay
+//! readable across restarts and by all the replicas sharing a redis server.
+//!
+//! Every entry is bound to the cache key it is stored under, which is passed as the associated
+//! data of the cipher, so an entry copied under another key fails to decrypt.
+//!
+//! Every key has an id which is stored in the entries it encrypts. The first key is used to
+//! encrypt the new entries while all the keys can decrypt the entries, so a key can be rotated by
+//! adding the new key in front of it and removing the old key once its entries have expired.
+
+use std::{collections::HashMap, fmt, fs, sync::OnceLock};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
 use chacha20poly1305::{
-    consts::{B0, B1},
-    ChaChaPoly1305,
-};
-use std::sync::OnceLock;
-
-use chacha20::{
-    cipher::{
-        generic_array::GenericArray,
-        typenum::{UInt, UTerm},
-        StreamCipherCoreWrapper,
-    },
-    ChaChaCore,
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
 };
 
-/// The ChaCha20 core wrapped in a stream cipher for use in ChaCha20-Poly1305 authenticated encryption.
-type StreamCipherCoreWrapperType =
-    StreamCipherCoreWrapper<ChaChaCore<UInt<UInt<UInt<UInt<UTerm, B1>, B0>, B1>, B0>>>;
-/// Our ChaCha20-Poly1305 cipher instance, lazily initialized.
-pub static CIPHER: OnceLock<ChaChaPoly1305<StreamCipherCoreWrapperType>> = OnceLock::new();
+use super::error::CacheError;
+
+/// The name of the environment variable which holds the encryption keys. It takes precedence
+/// over the key file referenced from the config file.
+pub const ENCRYPTION_KEYS_ENV_VAR: &str = "WEBSURFX_CACHE_ENCRYPTION_KEYS";
+
+/// The version of the format of the encrypted entries, which is prefixed to every entry so that
+/// the format can be changed without misreading the older entries.
+const ENCRYPTED_ENTRY_VERSION: u8 = 2;
+
+/// The length of the nonce of ChaCha20-Poly1305 in bytes.
+const NONCE_LENGTH: usize = 12;
+
+/// A static holding the keyring used to encrypt and decrypt the cached search results.
+static KEYRING: OnceLock<Keyring> = OnceLock::new();
+
+/// A custom error type used for handling the errors which occur while loading the encryption
+/// keys.
+#[derive(Debug)]
+pub enum KeyringError {
+    /// This variant handles the errors which occur while reading the key file.
+    Io(String, std::io::Error),
+    /// This variant handles the malformed keys along with the line number of the key.
+    InvalidKey(usize, &'static str),
+    /// This variant handles the keys which reuse the id of another key.
+    DuplicateKeyId(String),
+}
+
+impl fmt::Display for KeyringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyringError::Io(path, error) => {
+                write!(
+                    f,
+                    "Unable to read the cache encryption key file {path}: {error}"
+                )
+            }
+            KeyringError::InvalidKey(line, reason) => {
+                write!(f, "Invalid cache encryption key on line {line}: {reason}")
+            }
+            KeyringError::DuplicateKeyId(id) => {
+                write!(f, "The cache encryption key id {id} is used more than once")
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeyringError {}
+
+/// A named struct which stores the keys used to encrypt and decrypt the cached search results.
+pub struct Keyring {
+    /// The id of the key used to encrypt the new entries.
+    current_key_id: String,
+    /// The ciphers of all the keys mapped to their ids.
+    ciphers: HashMap<String, ChaCha20Poly1305>,
+}
+
+impl Keyring {
+    /// Parses the encryption keys. Every key is written as `<id>:<base64 encoded 32 byte key>`
+    /// and the keys are separated by new lines or commas. Everything after a `#` on a line is a
+    /// comment, and blank lines are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - It takes the encryption keys as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the keyring on success otherwise returns a `KeyringError` if a key is malformed,
+    /// an id is used more than once or no keys are provided.
+    pub fn parse(source: &str) -> Result<Self, KeyringError> {
+        let mut current_key_id = None;
+        let mut ciphers = HashMap::new();
+
+        for (index, entry) in source.lines().enumerate().flat_map(|(index, line)| {
+            let line = line.split_once('#').map_or(line, |(keys, _comment)| keys);
+            line.split(',').map(move |entry| (index, entry))
+        }) {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+
+            let line = index + 1;
+            let (id, key) = entry
+                .split_once(':')
+                .ok_or(KeyringError::InvalidKey(line, "expected `<id>:<key>`"))?;
+            let id = id.trim();
+            if id.is_empty() || id.len() > u8::MAX as usize {
+                return Err(KeyringError::InvalidKey(
+                    line,
+                    "the key id should be 1 to 255 bytes long",
+                ));
+            }
+            let key: [u8; 32] = STANDARD
+                .decode(key.trim())
+                .ok()
+                .and_then(|key| key.try_into().ok())
+                .ok_or(KeyringError::InvalidKey(
+                    line,
+                    "the key should be 32 bytes encoded in base64",
+                ))?;
+
+            if ciphers
+                .insert(id.to_owned(), ChaCha20Poly1305::new(Key::from_slice(&key)))
+                .is_some()
+            {
+                return Err(KeyringError::DuplicateKeyId(id.to_owned()));
+            }
+            current_key_id.get_or_insert_with(|| id.to_owned());
+        }
+
+        Ok(Self {
+            current_key_id: current_key_id
+                .ok_or(KeyringError::InvalidKey(0, "no keys were provided"))?,
+            ciphers,
+        })
+    }
+
+    /// Generates a keyring with a single random key, which is used when no keys are configured.
+    /// The entries encrypted with it cannot be decrypted after a restart or by other replicas.
+    pub fn ephemeral() -> Self {
+        let current_key_id = "ephemeral".to_owned();
+        let cipher = ChaCha20Poly1305::new(&ChaCha20Poly1305::generate_key(&mut OsRng));
+        Self {
+            ciphers: HashMap::from([(current_key_id.clone(), cipher)]),
+            current_key_id,
+        }
+    }
+
+    /// Encrypts the bytes with the current key and a fresh random nonce. The encrypted entry has
+    /// the form `<version><key id length><key id><nonce><ciphertext>`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - It takes the bytes to be encrypted as an argument.
+    /// * `cache_key` - It takes the key the entry is stored under as an argument, which is
+    ///   authenticated along with the entry.
+    ///
+    /// # Error
+    ///
+    /// Returns the encrypted entry on success otherwise returns a `CacheError::EncryptionError`.
+    pub fn encrypt(&self, bytes: &[u8], cache_key: &str) -> Result<Vec<u8>, CacheError> {
+        let cipher = &self.ciphers[&self.current_key_id];
+        // The nonce must never be reused with the same key, so it is generated for every entry.
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: bytes,
+                    aad: cache_key.as_bytes(),
+                },
+            )
+            .map_err(|_| CacheError::EncryptionError)?;
+
+        let key_id = self.current_key_id.as_bytes();
+        let mut entry = Vec::with_capacity(2 + key_id.len() + NONCE_LENGTH + ciphertext.len());
+        entry.push(ENCRYPTED_ENTRY_VERSION);
+        entry.push(key_id.len() as u8);
+        entry.extend_from_slice(key_id);
+        entry.extend_from_slice(&nonce);
+        entry.extend_from_slice(&ciphertext);
+        Ok(entry)
+    }
+
+    /// Decrypts an entry encrypted by `encrypt` with the key whose id is stored in the entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - It takes the encrypted entry as an argument.
+    /// * `cache_key` - It takes the key the entry is stored under as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the decrypted bytes on success otherwise returns a `CacheError::EncryptionError`
+    /// if the entry is malformed, its key is not in the keyring, it has been tampered with or it
+    /// was encrypted for another cache key.
+    pub fn decrypt(&self, entry: &[u8], cache_key: &str) -> Result<Vec<u8>, CacheError> {
+        let [ENCRYPTED_ENTRY_VERSION, key_id_length, rest @ ..] = entry else {
+            return Err(CacheError::EncryptionError);
+        };
+        let (key_id, rest) = rest
+            .split_at_checked(*key_id_length as usize)
+            .ok_or(CacheError::EncryptionError)?;
+        let (nonce, ciphertext) = rest
+            .split_at_checked(NONCE_LENGTH)
+            .ok_or(CacheError::EncryptionError)?;
+
+        let cipher = std::str::from_utf8(key_id)
+            .ok()
+            .and_then(|key_id| self.ciphers.get(key_id))
+            .ok_or(CacheError::EncryptionError)?;
+        cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: cache_key.as_bytes(),
+                },
+            )
+            .map_err(|_| CacheError::EncryptionError)
+    }
+}
+
+/// Loads the encryption keys from the `WEBSURFX_CACHE_ENCRYPTION_KEYS` environment variable or
+/// otherwise from the key file referenced from the config file. An ephemeral key is generated
+/// with a warning if neither is set. It does nothing if the keys have already been loaded.
+///
+/// # Arguments
+///
+/// * `key_file` - It takes the path of the key file referenced from the config file as an
+///   argument or `None` if no key file is configured.
+///
+/// # Error
+///
+/// Returns an error if the key file cannot be read or the keys are malformed, so that the server
+/// does not start with keys which cannot decrypt the cached results.
+pub fn init_keyring(key_file: Option<&str>) -> Result<(), KeyringError> {
+    if KEYRING.get().is_some() {
+        return Ok(());
+    }
+
+    let keyring = match (std::env::var(ENCRYPTION_KEYS_ENV_VAR), key_file) {
+        (Ok(keys), _) => Keyring::parse(&keys)?,
+        (Err(_), Some(path)) => Keyring::parse(
+            &fs::read_to_string(path).map_err(|error| KeyringError::Io(path.to_owned(), error))?,
+        )?,
+        (Err(_), None) => {
+            log::warn!("No cache encryption keys are configured, the cached results are encrypted with an ephemeral key and cannot be read after a restart");
+            Keyring::ephemeral()
+        }
+    };
+    log::info!(
+        "Loaded {} cache encryption keys, encrypting with the key {}",
+        keyring.ciphers.len(),
+        keyring.current_key_id
+    );
+
+    // Another thread may have loaded the keys in the meantime, in which case they are used.
+    let _ = KEYRING.set(keyring);
+    Ok(())
+}
+
+/// Returns the keyring used to encrypt and decrypt the cached search results. If the keys have
+/// not been loaded with `init_keyring` yet, an ephemeral key is used.
+pub fn keyring() -> &'static Keyring {
+    KEYRING.get_or_init(Keyring::ephemeral)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The keys used in the tests.
+    const OLD_KEY: &str = "old:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+    const NEW_KEY: &str = "new:AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+
+    #[test]
+    fn test_entries_use_fresh_nonces() {
+        let keyring = Keyring::parse(OLD_KEY).unwrap();
+        let first = keyring.encrypt(b"search results", "key").unwrap();
+        let second = keyring.encrypt(b"search results", "key").unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(keyring.decrypt(&first, "key").unwrap(), b"search results");
+        assert_eq!(keyring.decrypt(&second, "key").unwrap(), b"search results");
+    }
+
+    #[test]
+    fn test_entries_survive_restarts_and_rotation() {
+        // Every keyring simulates a restart of the server which reloads the same keys.
+        let entry = Keyring::parse(OLD_KEY)
+            .unwrap()
+            .encrypt(b"search results", "key")
+            .unwrap();
+        assert_eq!(
+            Keyring::parse(OLD_KEY)
+                .unwrap()
+                .decrypt(&entry, "key")
+                .unwrap(),
+            b"search results"
+        );
+
+        // After rotating the key the old entries are still read while the new entries are
+        // encrypted with the new key.
+        let rotated = Keyring::parse(&format!("{NEW_KEY}\n{OLD_KEY}")).unwrap();
+        assert_eq!(rotated.decrypt(&entry, "key").unwrap(), b"search results");
+        let new_entry = rotated.encrypt(b"new results", "key").unwrap();
+        assert_eq!(
+            Keyring::parse(&format!("{NEW_KEY},{OLD_KEY}"))
+                .unwrap()
+                .decrypt(&new_entry, "key")
+                .unwrap(),
+            b"new results"
+        );
+
+        // Once the old key is removed its entries can no longer be read.
+        assert!(Keyring::parse(NEW_KEY)
+            .unwrap()
+            .decrypt(&entry, "key")
+            .is_err());
+        assert!(Keyring::ephemeral().decrypt(&new_entry, "key").is_err());
+    }
+
+    #[test]
+    fn test_tampered_and_malformed_entries_are_rejected() {
+        let keyring = Keyring::parse(OLD_KEY).unwrap();
+        let mut entry = keyring.encrypt(b"search results", "key").unwrap();
+        let last = entry.len() - 1;
+        entry[last] ^= 1;
+
+        assert!(keyring.decrypt(&entry, "key").is_err());
+        assert!(keyring.decrypt(&[], "key").is_err());
+        assert!(keyring
+            .decrypt(&[ENCRYPTED_ENTRY_VERSION, 200, 1, 2], "key")
+            .is_err());
+    }
+
+    #[test]
+    fn test_entries_are_bound_to_their_cache_key() {
+        let keyring = Keyring::parse(OLD_KEY).unwrap();
+        let entry = keyring.encrypt(b"search results", "first").unwrap();
+
+        assert_eq!(keyring.decrypt(&entry, "first").unwrap(), b"search results");
+        assert!(keyring.decrypt(&entry, "second").is_err());
+    }
+
+    #[test]
+    fn test_comments_are_ignored() {
+        let keyring = Keyring::parse(&format!(
+            "# the current key\n{NEW_KEY} # added in october\n{OLD_KEY}, # rotated out # soon\n"
+        ))
+        .unwrap();
+
+        assert_eq!(keyring.current_key_id, "new");
+        assert_eq!(keyring.ciphers.len(), 2);
+    }
 
-/// The type alias for our encryption key, a 32-byte array.
-type GenericArrayType = GenericArray<u8, UInt<UInt<UInt<UInt<UTerm, B1>, B1>, B0>, B0>>;
-/// Our encryption key, lazily initialized.
-pub static ENCRYPTION_KEY: OnceLock<GenericArrayType> = OnceLock::new();
+    #[test]
+    fn test_invalid_keys_are_rejected() {
+        assert!(matches!(
+            Keyring::parse("# no keys\n"),
+            Err(KeyringError::InvalidKey(0, _))
+        ));
+        assert!(matches!(
+            Keyring::parse(&format!("{OLD_KEY}\nshort:AAAA")),
+            Err(KeyringError::InvalidKey(2, _))
+        ));
+        assert!(matches!(
+            Keyring::parse(&format!("{OLD_KEY},{OLD_KEY}")),
+            Err(KeyringError::DuplicateKeyId(_))
+        ));
+    }
+}
@@ -11,6 +11,9 @@ pub enum CacheError {
     /// This variant handles all errors related to `RedisError`,
     #[cfg(feature = "redis-cache")]
     RedisError(RedisError),
+    /// This variant handles all errors related to the on-disk database of the disk cache.
+    #[cfg(feature = "disk-cache")]
+    DiskCacheError(Box<redb::Error>),
     /// This variant handles the errors which occurs when all the connections
     /// in the connection pool return a connection dropped redis error.
     PoolExhaustionWithConnectionDropError,
@@ -37,6 +40,10 @@ impl fmt::Display for CacheError {
                     write!(f, "")
                 }
             }
+            #[cfg(feature = "disk-cache")]
+            CacheError::DiskCacheError(disk_cache_error) => {
+                write!(f, "{}", disk_cache_error)
+            }
             CacheError::PoolExhaustionWithConnectionDropError => {
                 write!(
                     f,
@@ -1,31 +1,109 @@
 //! This module provides the functionality to cache the aggregated results fetched and aggregated
 //! from the upstream search engines in a json format.
 
-use super::error::CacheError;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, PoisonError, RwLock,
+    },
+    time::Duration,
+};
+
+use super::{cacher::CacheStats, error::CacheError};
+use crate::models::parser_models::{RedisConfig, RedisTopology};
 use error_stack::Report;
-use futures::stream::FuturesUnordered;
+use futures::{future::try_join_all, stream::FuturesUnordered};
 use redis::{
-    aio::ConnectionManager, AsyncCommands, Client, ExistenceCheck, RedisError, SetExpiry,
-    SetOptions,
+    aio::{ConnectionLike, ConnectionManager, ConnectionManagerConfig},
+    cluster::ClusterClientBuilder,
+    cluster_async::ClusterConnection,
+    AsyncCommands, AsyncConnectionConfig, Client, Cmd, ErrorKind, IntoConnectionInfo, Pipeline,
+    RedisError, RedisFuture, RedisResult, SetExpiry, SetOptions, TlsCertificates, Value,
 };
 
-/// A constant holding the redis pipeline size.
-const REDIS_PIPELINE_SIZE: usize = 3;
+/// A constant holding the number of times a dropped connection is tried to be reconnected
+/// (with an exponentially increasing delay) before the reconnection is given up until the
+/// connection is used again.
+const REDIS_RECONNECTION_RETRIES: usize = 3;
+
+/// A constant holding the time (secs) after which an attempt to connect to the redis server is
+/// given up.
+const REDIS_CONNECTION_TIMEOUT: u64 = 2;
+
+/// A constant holding the max number of keys removed by a single command when the cache is purged.
+const REDIS_PURGE_BATCH_SIZE: usize = 1000;
+
+/// A connection to the redis deployment, which depends on its topology.
+#[derive(Clone)]
+enum RedisConnection {
+    /// A connection to a single redis server, which reconnects itself when it is dropped.
+    Server(Box<ConnectionManager>),
+    /// A connection to a redis cluster, which routes the commands to the nodes owning their keys
+    /// and reconnects the nodes itself.
+    Cluster(ClusterConnection),
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            RedisConnection::Server(connection) => connection.req_packed_command(cmd),
+            RedisConnection::Cluster(connection) => connection.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            RedisConnection::Server(connection) => {
+                connection.req_packed_commands(cmd, offset, count)
+            }
+            RedisConnection::Cluster(connection) => {
+                connection.req_packed_commands(cmd, offset, count)
+            }
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            RedisConnection::Server(connection) => connection.get_db(),
+            RedisConnection::Cluster(connection) => connection.get_db(),
+        }
+    }
+}
+
+/// A named struct which stores the pool of connections to the redis deployment along with the
+/// url of the primary server they are connected to when it is managed by sentinels.
+struct Connections {
+    /// It stores the connections of the pool.
+    pool: Box<[RedisConnection]>,
+    /// It stores the url of the primary server found by the sentinels.
+    primary: Option<String>,
+}
 
 /// A named struct which stores the redis Connection url address to which the client will
 /// connect to.
 pub struct RedisCache {
-    /// It stores a pool of connections ready to be used.
-    connection_pool: Box<[ConnectionManager]>,
+    /// It stores the options used to connect to the redis deployment.
+    options: RedisConfig,
+    /// It stores a pool of connections ready to be used, which is replaced when the sentinels
+    /// fail the primary server over.
+    connections: RwLock<Arc<Connections>>,
     /// It stores the size of the connection pool (in other words the number of
     /// connections that should be stored in the pool).
     pool_size: u8,
-    /// It stores the index of which connection is being used at the moment.
-    current_connection: u8,
+    /// It stores the index of the connection of the pool which runs the next command, so that
+    /// the commands are spread over all the connections in a round-robin fashion.
+    next_connection: AtomicUsize,
+    /// It stores a lock ensuring that the sentinels are asked for the new primary server by one
+    /// command at a time.
+    failover: tokio::sync::Mutex<()>,
     /// It stores the max TTL for keys.
     cache_ttl: u16,
-    /// It stores the redis pipeline struct of size 3.
-    pipeline: redis::Pipeline,
 }
 
 impl RedisCache {
@@ -33,7 +111,7 @@ impl RedisCache {
     ///
     /// # Arguments
     ///
-    /// * `redis_connection_url` - It takes the redis Connection url address.
+    /// * `options` - It takes the options used to connect to the redis deployment.
     /// * `pool_size` - It takes the size of the connection pool (in other words the number of
     /// connections that should be stored in the pool).
     /// * `cache_ttl` - It takes the the time to live for cached results to live in the redis
@@ -44,31 +122,20 @@ impl RedisCache {
     /// Returns a newly constructed `RedisCache` struct on success otherwise returns a standard
     /// error type.
     pub async fn new(
-        redis_connection_url: &str,
+        options: &RedisConfig,
         pool_size: u8,
         cache_ttl: u16,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let client = Client::open(redis_connection_url)?;
-        let tasks: FuturesUnordered<_> = FuturesUnordered::new();
-
-        for _ in 0..pool_size {
-            let client_partially_cloned = client.clone();
-            tasks.push(tokio::spawn(async move {
-                client_partially_cloned.get_connection_manager().await
-            }));
-        }
-
-        let mut outputs = Vec::with_capacity(tasks.len());
-        for task in tasks {
-            outputs.push(task.await??);
-        }
+        let pool_size = pool_size.max(1);
+        let connections = connect(options, pool_size).await?;
 
         let redis_cache = RedisCache {
-            connection_pool: outputs.into_boxed_slice(),
+            options: options.clone(),
+            connections: RwLock::new(Arc::new(connections)),
             pool_size,
-            current_connection: Default::default(),
+            next_connection: Default::default(),
+            failover: Default::default(),
             cache_ttl,
-            pipeline: redis::Pipeline::with_capacity(REDIS_PIPELINE_SIZE),
         };
 
         Ok(redis_cache)
@@ -84,41 +151,9 @@ impl RedisCache {
     ///
     /// Returns the json as a String from the cache on success otherwise returns a `CacheError`
     /// on a failure.
-    pub async fn cached_json(&mut self, key: &str) -> Result<String, Report<CacheError>> {
-        self.current_connection = Default::default();
-
-        let mut result: Result<String, RedisError> = self.connection_pool
-            [self.current_connection as usize]
-            .get(key)
-            .await;
-
-        // Code to check whether the current connection being used is dropped with connection error
-        // or not. if it drops with the connection error then the current connection is replaced
-        // with a new connection from the pool which is then used to run the redis command then
-        // that connection is also checked whether it is dropped or not if it is not then the
-        // result is passed as a `Result` or else the same process repeats again and if all of the
-        // connections in the pool result in connection drop error then a custom pool error is
-        // returned.
-        loop {
-            match result {
-                Err(error) => match error.is_connection_dropped() {
-                    true => {
-                        self.current_connection += 1;
-                        if self.current_connection == self.pool_size {
-                            return Err(Report::new(
-                                CacheError::PoolExhaustionWithConnectionDropError,
-                            ));
-                        }
-                        result = self.connection_pool[self.current_connection as usize]
-                            .get(key)
-                            .await;
-                        continue;
-                    }
-                    false => return Err(Report::new(CacheError::RedisError(error))),
-                },
-                Ok(res) => return Ok(res),
-            }
-        }
+    pub async fn cached_json(&self, key: &str) -> Result<String, Report<CacheError>> {
+        self.run(|mut connection| async move { connection.get(key).await })
+            .await
     }
 
     /// A function which caches the json by using the key and
@@ -135,57 +170,415 @@ impl RedisCache {
     /// Returns an unit type if the results are cached succesfully otherwise returns a `CacheError`
     /// on a failure.
     pub async fn cache_json(
-        &mut self,
+        &self,
         json_results: impl Iterator<Item = String>,
         keys: impl Iterator<Item = String>,
     ) -> Result<(), Report<CacheError>> {
-        self.current_connection = Default::default();
-
+        // New pipelines are built for every call, so that only the given results are sent. The
+        // keys of a cluster are spread over its nodes, so every result is sent in its own pipeline
+        // which is routed to the node owning its key.
+        let one_pipeline_per_key = self.options.topology == RedisTopology::Cluster;
+        let mut pipelines: Vec<Pipeline> = Vec::new();
         for (key, json_result) in keys.zip(json_results) {
-            self.pipeline.set_options(
-                key,
-                json_result,
-                SetOptions::default()
-                    .conditional_set(ExistenceCheck::NX)
-                    .get(true)
-                    .with_expiration(SetExpiry::EX(self.cache_ttl.into())),
-            );
+            if one_pipeline_per_key || pipelines.is_empty() {
+                pipelines.push(redis::pipe());
+            }
+            if let Some(pipeline) = pipelines.last_mut() {
+                // The results are always overwritten, so that the refreshed, extended and retried
+                // results replace the ones cached before them.
+                pipeline.set_options(
+                    key,
+                    json_result,
+                    SetOptions::default().with_expiration(SetExpiry::EX(self.cache_ttl.into())),
+                );
+            }
         }
 
-        let mut result: Result<(), RedisError> = self
-            .pipeline
-            .query_async(&mut self.connection_pool[self.current_connection as usize])
-            .await;
-
-        // Code to check whether the current connection being used is dropped with connection error
-        // or not. if it drops with the connection error then the current connection is replaced
-        // with a new connection from the pool which is then used to run the redis command then
-        // that connection is also checked whether it is dropped or not if it is not then the
-        // result is passed as a `Result` or else the same process repeats again and if all of the
-        // connections in the pool result in connection drop error then a custom pool error is
-        // returned.
-        loop {
-            match result {
-                Err(error) => match error.is_connection_dropped() {
-                    true => {
-                        self.current_connection += 1;
-                        if self.current_connection == self.pool_size {
-                            return Err(Report::new(
-                                CacheError::PoolExhaustionWithConnectionDropError,
-                            ));
-                        }
-                        result = self
-                            .pipeline
-                            .query_async(
-                                &mut self.connection_pool[self.current_connection as usize],
-                            )
-                            .await;
-                        continue;
+        try_join_all(pipelines.iter().map(|pipeline| {
+            self.run(move |mut connection| async move {
+                pipeline.query_async::<()>(&mut connection).await
+            })
+        }))
+        .await?;
+        Ok(())
+    }
+
+    /// A function which computes the usage of the redis deployment from the number of keys of its
+    /// database and the statistics reported by its servers (summed over the primary nodes of a
+    /// cluster). The database is expected to be dedicated to the cache.
+    ///
+    /// # Error
+    ///
+    /// Returns the number of evictions, entries and bytes of the cache on success otherwise
+    /// returns a `CacheError` on a failure.
+    pub async fn usage(&self) -> Result<CacheStats, Report<CacheError>> {
+        let entries: u64 =
+            self.run(|mut connection| async move {
+                redis::cmd("DBSIZE").query_async(&mut connection).await
+            })
+            .await?;
+        // A cluster sends the info of every primary node mapped to its address.
+        let info: Value =
+            self.run(|mut connection| async move {
+                redis::cmd("INFO").query_async(&mut connection).await
+            })
+            .await?;
+        let servers_info = match info {
+            Value::Map(nodes) => nodes.into_iter().map(|(_, info)| info).collect(),
+            info => vec![info],
+        };
+
+        let mut stats = CacheStats {
+            entries,
+            ..Default::default()
+        };
+        for info in servers_info {
+            let info: String = redis::from_redis_value(&info)
+                .map_err(|error| Report::new(CacheError::RedisError(error)))?;
+            for (field, value) in info.lines().filter_map(|line| line.split_once(':')) {
+                let value: u64 = value.trim().parse().unwrap_or_default();
+                match field {
+                    "used_memory" => stats.size += value,
+                    "evicted_keys" | "expired_keys" => stats.evictions += value,
+                    _ => (),
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    /// A function which removes the keys matching the given glob-style pattern from the redis
+    /// deployment.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - It takes the pattern of the keys to remove as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the number of removed keys on success otherwise returns a `CacheError` on a
+    /// failure.
+    pub async fn remove_keys(&self, pattern: &str) -> Result<u64, Report<CacheError>> {
+        let keys: Vec<String> = match self.options.topology {
+            // The cursors of the nodes of a cluster cannot be combined, so the keys are listed
+            // with `KEYS` which is sent to all the primary nodes.
+            RedisTopology::Cluster => {
+                self.run(|mut connection| async move {
+                    redis::cmd("KEYS")
+                        .arg(pattern)
+                        .query_async(&mut connection)
+                        .await
+                })
+                .await?
+            }
+            _ => {
+                self.run(|mut connection| async move {
+                    let mut keys = Vec::new();
+                    let mut iter = connection.scan_match::<_, String>(pattern).await?;
+                    while let Some(key) = iter.next_item().await {
+                        keys.push(key);
                     }
-                    false => return Err(Report::new(CacheError::RedisError(error))),
-                },
-                Ok(_) => return Ok(()),
+                    Ok(keys)
+                })
+                .await?
             }
+        };
+
+        let mut removed = 0;
+        for keys in keys.chunks(REDIS_PURGE_BATCH_SIZE) {
+            removed += self
+                .run(|mut connection| async move { connection.unlink::<_, u64>(keys).await })
+                .await?;
         }
+        Ok(removed)
     }
+
+    /// A helper function which runs a redis command on the next connection of the pool.
+    ///
+    /// When the connection has been dropped, the command is run again on the following
+    /// connections of the pool while the dropped connection reconnects to the redis server in
+    /// the background, so that it can be used again by the next commands. When the primary
+    /// server managed by sentinels cannot be used, the sentinels are asked for the new primary
+    /// server on which the command is run again.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - It takes a closure which runs the command on the given connection.
+    ///
+    /// # Error
+    ///
+    /// Returns the output of the command on success otherwise returns a `CacheError` if the
+    /// command fails or all the connections in the pool have been dropped.
+    async fn run<T, F, Fut>(&self, command: F) -> Result<T, Report<CacheError>>
+    where
+        F: Fn(RedisConnection) -> Fut,
+        Fut: Future<Output = RedisResult<T>>,
+    {
+        let connections = self.connections();
+        let pool_size = connections.pool.len();
+        let first_connection = self.next_connection.fetch_add(1, Ordering::Relaxed);
+
+        for attempt in 0..pool_size {
+            let connection = connections.pool[(first_connection + attempt) % pool_size].clone();
+            match command(connection).await {
+                Ok(output) => return Ok(output),
+                // The primary server has been demoted to a replica by a failover.
+                Err(error) if error.kind() == ErrorKind::ReadOnly => break,
+                Err(error) if error.is_unrecoverable_error() => {
+                    log::debug!("Redis connection dropped, trying the next one: {error}");
+                }
+                Err(error) => return Err(Report::new(CacheError::RedisError(error))),
+            }
+        }
+
+        if self.fail_over(&connections).await {
+            let connections = self.connections();
+            let connection = connections.pool[first_connection % connections.pool.len()].clone();
+            return command(connection)
+                .await
+                .map_err(|error| Report::new(CacheError::RedisError(error)));
+        }
+
+        Err(Report::new(
+            CacheError::PoolExhaustionWithConnectionDropError,
+        ))
+    }
+
+    /// A helper function which asks the sentinels for the primary server and replaces the pool
+    /// of connections when the primary server has changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `failed_connections` - It takes the connections which failed to run the command.
+    ///
+    /// # Returns
+    ///
+    /// Returns whether the pool of connections has been replaced with the connections to the new
+    /// primary server.
+    async fn fail_over(&self, failed_connections: &Arc<Connections>) -> bool {
+        let RedisTopology::Sentinel { master_name } = &self.options.topology else {
+            return false;
+        };
+
+        let _failover = self.failover.lock().await;
+        // Another command may have already replaced the connections while this one was waiting.
+        if !Arc::ptr_eq(&self.connections(), failed_connections) {
+            return true;
+        }
+
+        let primary = match primary_url(&self.options, master_name).await {
+            Ok(primary) if Some(&primary) != failed_connections.primary.as_ref() => primary,
+            Ok(_) => return false,
+            Err(error) => {
+                log::error!("Unable to find the redis primary server from the sentinels: {error}");
+                return false;
+            }
+        };
+
+        match server_pool(&primary, &self.options, self.pool_size).await {
+            Ok(pool) => {
+                log::info!("Redis primary server failed over to {primary}");
+                *self
+                    .connections
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner) = Arc::new(Connections {
+                    pool,
+                    primary: Some(primary),
+                });
+                true
+            }
+            Err(error) => {
+                log::error!("Unable to connect to the redis primary server {primary}: {error}");
+                false
+            }
+        }
+    }
+
+    /// A helper function which returns the current pool of connections.
+    fn connections(&self) -> Arc<Connections> {
+        self.connections
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+/// A helper function which connects to the redis deployment according to its topology.
+///
+/// # Arguments
+///
+/// * `options` - It takes the options used to connect to the redis deployment.
+/// * `pool_size` - It takes the number of connections to open to a redis server.
+///
+/// # Error
+///
+/// Returns the pool of connections on success otherwise returns a `RedisError` if the redis
+/// deployment cannot be reached.
+async fn connect(options: &RedisConfig, pool_size: u8) -> RedisResult<Connections> {
+    match &options.topology {
+        RedisTopology::Standalone => Ok(Connections {
+            pool: server_pool(&options.urls[0], options, pool_size).await?,
+            primary: None,
+        }),
+        RedisTopology::Sentinel { master_name } => {
+            let primary = primary_url(options, master_name).await?;
+            Ok(Connections {
+                pool: server_pool(&primary, options, pool_size).await?,
+                primary: Some(primary),
+            })
+        }
+        RedisTopology::Cluster => {
+            let mut builder = ClusterClientBuilder::new(options.urls.iter().map(String::as_str))
+                .connection_timeout(Duration::from_secs(REDIS_CONNECTION_TIMEOUT));
+            if let Some(username) = options.username.as_ref() {
+                builder = builder.username(username.clone());
+            }
+            if let Some(password) = options.password.as_ref() {
+                builder = builder.password(password.clone());
+            }
+            if let Some(certificates) = certificates(options) {
+                builder = builder.certs(certificates);
+            }
+
+            // The cluster connection multiplexes the commands over the connections to all the
+            // nodes, so a single connection is shared by all the commands.
+            let connection = builder.build()?.get_async_connection().await?;
+            Ok(Connections {
+                pool: Box::new([RedisConnection::Cluster(connection)]),
+                primary: None,
+            })
+        }
+    }
+}
+
+/// A helper function which opens a pool of connections to a redis server.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the redis server.
+/// * `options` - It takes the options used to connect to the redis deployment.
+/// * `pool_size` - It takes the number of connections to open.
+///
+/// # Error
+///
+/// Returns the pool of connections on success otherwise returns a `RedisError` if the redis
+/// server cannot be reached.
+async fn server_pool(
+    url: &str,
+    options: &RedisConfig,
+    pool_size: u8,
+) -> RedisResult<Box<[RedisConnection]>> {
+    let mut connection_info = url.into_connection_info()?;
+    if options.password.is_some() {
+        connection_info.redis.username = options.username.clone();
+        connection_info.redis.password = options.password.clone();
+    }
+    let client = match certificates(options) {
+        Some(certificates) => Client::build_with_tls(connection_info, certificates)?,
+        None => Client::open(connection_info)?,
+    };
+
+    let tasks: FuturesUnordered<_> = FuturesUnordered::new();
+    for _ in 0..pool_size {
+        let client_partially_cloned = client.clone();
+        tasks.push(tokio::spawn(async move {
+            client_partially_cloned
+                .get_connection_manager_with_config(
+                    ConnectionManagerConfig::new()
+                        .set_number_of_retries(REDIS_RECONNECTION_RETRIES)
+                        .set_connection_timeout(Duration::from_secs(REDIS_CONNECTION_TIMEOUT)),
+                )
+                .await
+        }));
+    }
+
+    let mut outputs = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        let connection = task
+            .await
+            .map_err(|error| RedisError::from(std::io::Error::other(error)))??;
+        outputs.push(RedisConnection::Server(Box::new(connection)));
+    }
+    Ok(outputs.into_boxed_slice())
+}
+
+/// A helper function which asks the sentinels for the url of the primary server they manage.
+///
+/// # Arguments
+///
+/// * `options` - It takes the options used to connect to the redis deployment, whose urls are
+///   the urls of the sentinels.
+/// * `master_name` - It takes the name under which the sentinels monitor the primary server.
+///
+/// # Error
+///
+/// Returns the url of the primary server on success otherwise returns a `RedisError` if none of
+/// the sentinels knows the primary server.
+async fn primary_url(options: &RedisConfig, master_name: &str) -> RedisResult<String> {
+    let mut last_error = RedisError::from((ErrorKind::EmptySentinelList, "No sentinels provided"));
+
+    for sentinel in options.urls.iter() {
+        // The sentinels are authenticated with the credentials of their urls, as they usually
+        // differ from the credentials of the redis servers.
+        let primary = async {
+            let client = match certificates(options) {
+                Some(certificates) => Client::build_with_tls(sentinel.as_str(), certificates)?,
+                None => Client::open(sentinel.as_str())?,
+            };
+            let mut connection = client
+                .get_multiplexed_async_connection_with_config(
+                    &AsyncConnectionConfig::new()
+                        .set_connection_timeout(Duration::from_secs(REDIS_CONNECTION_TIMEOUT))
+                        .set_response_timeout(Duration::from_secs(REDIS_CONNECTION_TIMEOUT)),
+                )
+                .await?;
+            redis::cmd("SENTINEL")
+                .arg("GET-MASTER-ADDR-BY-NAME")
+                .arg(master_name)
+                .query_async::<Option<(String, u16)>>(&mut connection)
+                .await
+        }
+        .await;
+
+        match primary {
+            Ok(Some((host, port))) => {
+                let scheme = if options.uses_tls() {
+                    "rediss"
+                } else {
+                    "redis"
+                };
+                let host = match host.contains(':') {
+                    true => format!("[{host}]"),
+                    false => host,
+                };
+                return Ok(format!("{scheme}://{host}:{port}"));
+            }
+            Ok(None) => {
+                last_error = RedisError::from((
+                    ErrorKind::MasterNameNotFoundBySentinel,
+                    "The primary server is not monitored by the sentinel",
+                ))
+            }
+            Err(error) => last_error = error,
+        }
+        log::debug!("Unable to find the redis primary server from a sentinel: {last_error}");
+    }
+
+    Err(last_error)
+}
+
+/// A helper function which returns the certificates used to verify the TLS connections when a
+/// custom CA is configured.
+///
+/// # Arguments
+///
+/// * `options` - It takes the options used to connect to the redis deployment.
+fn certificates(options: &RedisConfig) -> Option<TlsCertificates> {
+    options
+        .ca_certificate
+        .as_ref()
+        .map(|ca_certificate| TlsCertificates {
+            client_tls: None,
+            root_cert: Some(ca_certificate.clone()),
+        })
 }
@@ -3,11 +3,40 @@
 
 use crate::handler::{file_path, FileType};
 
-use crate::models::parser_models::{AggregatorConfig, RateLimiter, Style};
+#[cfg(feature = "redis-cache")]
+use crate::models::parser_models::RedisConfig;
+use crate::models::parser_models::{
+    AggregatorConfig, PrefetchPolicy, PrivacyRedirects, RateLimiter, Style,
+};
+use crate::results::url_cleaner::DEFAULT_TRACKING_PARAMETERS;
 use log::LevelFilter;
 use mlua::Lua;
 use reqwest::Proxy;
-use std::{collections::HashMap, fs, thread::available_parallelism};
+use std::{collections::HashMap, fs, path::Path, thread::available_parallelism};
+
+/// A constant holding the default number of search results shown on a page.
+pub const DEFAULT_RESULTS_PER_PAGE: u8 = 10;
+
+/// A constant holding the maximum number of search results which can be shown on a page.
+pub const MAX_RESULTS_PER_PAGE: u8 = 50;
+
+/// A constant holding the default time (secs) for which the failures of the upstream search engines
+/// are cached.
+#[cfg(any(
+    feature = "redis-cache",
+    feature = "memory-cache",
+    feature = "disk-cache"
+))]
+pub const DEFAULT_CACHE_FAILURE_TIME: u16 = 30;
+
+/// A constant holding the default path of the database file of the on-disk cache, relative to the
+/// folder of the config file.
+#[cfg(feature = "disk-cache")]
+pub const DEFAULT_DISK_CACHE_PATH: &str = "cache.redb";
+
+/// A constant holding the default max size of the on-disk cache in megabytes.
+#[cfg(feature = "disk-cache")]
+pub const DEFAULT_DISK_CACHE_MAX_SIZE: u64 = 256;
 
 /// A named struct which stores the parsed config file options.
 pub struct Config {
@@ -18,12 +47,42 @@ pub struct Config {
     /// It stores the theming options for the website.
     pub style: Style,
     #[cfg(feature = "redis-cache")]
-    /// It stores the redis connection url address on which the redis
-    /// client should connect.
-    pub redis_url: String,
-    #[cfg(any(feature = "redis-cache", feature = "memory-cache"))]
+    /// It stores the options used to connect to the redis deployment on which the redis client
+    /// should cache the results.
+    pub redis: RedisConfig,
+    #[cfg(any(
+        feature = "redis-cache",
+        feature = "memory-cache",
+        feature = "disk-cache"
+    ))]
     /// It stores the max TTL for search results in cache.
     pub cache_expiry_time: u16,
+    #[cfg(any(
+        feature = "redis-cache",
+        feature = "memory-cache",
+        feature = "disk-cache"
+    ))]
+    /// It stores the time (secs) for which the expired search results are still served while
+    /// they are refreshed in the background.
+    pub cache_stale_time: u16,
+    #[cfg(any(
+        feature = "redis-cache",
+        feature = "memory-cache",
+        feature = "disk-cache"
+    ))]
+    /// It stores the time (secs) for which the failures of the upstream search engines and the
+    /// search results holding them are cached.
+    pub cache_failure_time: u16,
+    #[cfg(feature = "disk-cache")]
+    /// It stores the path of the database file of the on-disk cache.
+    pub disk_cache_path: String,
+    #[cfg(feature = "disk-cache")]
+    /// It stores the max size of the on-disk cache in bytes.
+    pub disk_cache_max_size: u64,
+    #[cfg(any(feature = "encrypt-cache-results", feature = "cec-cache-results"))]
+    /// It stores the path of the file holding the keys used to encrypt the cached search results
+    /// or `None` if no key file is configured.
+    pub cache_encryption_key_file: Option<String>,
     /// It stores the option to whether enable or disable production use.
     pub aggregator: AggregatorConfig,
     /// It stores the option to whether enable or disable logs.
@@ -45,6 +104,8 @@ pub struct Config {
     /// It stores the level of safe search to be used for restricting content in the
     /// search results.
     pub safe_search: u8,
+    /// It stores the number of search results shown on a page by default.
+    pub results_per_page: u8,
     /// It stores the TCP connection keepalive duration in seconds.
     pub tcp_connection_keep_alive: u8,
     /// It stores the pool idle connection timeout in seconds.
@@ -53,6 +114,21 @@ pub struct Config {
     pub proxy: Option<Proxy>,
     /// It stores the number of https connections to keep in the pool.
     pub number_of_https_connections: u8,
+    /// It stores the rules to rewrite the search result urls to privacy respecting front-ends.
+    pub privacy_redirects: PrivacyRedirects,
+    /// It stores the names of the tracking parameters to be removed from the search result urls.
+    pub tracking_parameters: Vec<String>,
+    /// It stores the paths of the domain filter lists used to filter the search results.
+    pub domain_filter_lists: Vec<String>,
+    /// It stores the option to whether allow or disallow the users to temporarily reveal the
+    /// search results removed by the filter lists.
+    pub reveal_filtered_results: bool,
+    /// It stores the secret used to sign the preferences tokens or `None` if the tokens should
+    /// not be signed.
+    pub preferences_token_secret: Option<String>,
+    /// It stores the token authorizing the requests to the admin routes or `None` if the admin
+    /// routes should be disabled.
+    pub admin_token: Option<String>,
 }
 
 impl Config {
@@ -109,9 +185,40 @@ impl Config {
             }
         };
 
-        #[cfg(any(feature = "redis-cache", feature = "memory-cache"))]
+        let prefetch = match globals.get::<_, Option<String>>("prefetch")? {
+            Some(name) => PrefetchPolicy::from_name(&name).unwrap_or_else(|| {
+                log::error!("Config Error: The value of `prefetch` option should be one of `off`, `next` or `both`.");
+                log::error!("Falling back to using the value `next` for the option");
+                PrefetchPolicy::Next
+            }),
+            None => PrefetchPolicy::default(),
+        };
+
+        let parsed_results_per_page: u8 = globals
+            .get::<_, Option<u8>>("results_per_page")?
+            .unwrap_or(DEFAULT_RESULTS_PER_PAGE);
+        let results_per_page: u8 = match parsed_results_per_page {
+            1..=MAX_RESULTS_PER_PAGE => parsed_results_per_page,
+            _ => {
+                log::error!("Config Error: The value of `results_per_page` option should be a non zero positive integer from 1 to {MAX_RESULTS_PER_PAGE}.");
+                log::error!(
+                    "Falling back to using the value `{DEFAULT_RESULTS_PER_PAGE}` for the option"
+                );
+                DEFAULT_RESULTS_PER_PAGE
+            }
+        };
+
+        #[cfg(any(
+            feature = "redis-cache",
+            feature = "memory-cache",
+            feature = "disk-cache"
+        ))]
         let parsed_cet = globals.get::<_, u16>("cache_expiry_time")?;
-        #[cfg(any(feature = "redis-cache", feature = "memory-cache"))]
+        #[cfg(any(
+            feature = "redis-cache",
+            feature = "memory-cache",
+            feature = "disk-cache"
+        ))]
         let cache_expiry_time = match parsed_cet {
             0..=59 => {
                 log::error!(
@@ -123,6 +230,22 @@ impl Config {
             _ => parsed_cet,
         };
 
+        #[cfg(feature = "disk-cache")]
+        let disk_cache_max_size: u64 = match globals
+            .get::<_, Option<u64>>("disk_cache_max_size")?
+            .unwrap_or(DEFAULT_DISK_CACHE_MAX_SIZE)
+        {
+            0 => {
+                log::error!("Config Error: The value of `disk_cache_max_size` option should be a non zero positive integer.");
+                log::error!(
+                    "Falling back to using the value `{DEFAULT_DISK_CACHE_MAX_SIZE}` for the option"
+                );
+                DEFAULT_DISK_CACHE_MAX_SIZE
+            }
+            megabytes => megabytes,
+        } * 1024
+            * 1024;
+
         let proxy_opt = globals.get::<_, Option<String>>("proxy")?;
         let proxy = proxy_opt.and_then(|proxy_str| {
             Proxy::all(proxy_str).ok().and_then(|_| {
@@ -131,6 +254,97 @@ impl Config {
             })
         });
 
+        let privacy_redirects = PrivacyRedirects {
+            enabled: globals
+                .get::<_, Option<bool>>("privacy_redirects")?
+                .unwrap_or(false),
+            rules: globals
+                .get::<_, Option<HashMap<String, String>>>("privacy_redirect_rules")?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(hostname, redirect)| {
+                    (
+                        hostname.trim().trim_start_matches("www.").to_lowercase(),
+                        redirect.trim().to_lowercase(),
+                    )
+                })
+                .collect(),
+        };
+
+        let tracking_parameters: Vec<String> = globals
+            .get::<_, Option<Vec<String>>>("tracking_parameters")?
+            .map(|parameters| {
+                parameters
+                    .into_iter()
+                    .map(|parameter| parameter.trim().to_lowercase())
+                    .filter(|parameter| !parameter.is_empty())
+                    .collect()
+            })
+            .unwrap_or_else(|| {
+                DEFAULT_TRACKING_PARAMETERS
+                    .iter()
+                    .map(|parameter| parameter.to_string())
+                    .collect()
+            });
+
+        // The relative paths of the domain filter lists are resolved against the folder of the
+        // config file.
+        let config_folder = Path::new(file_path(FileType::Config)?)
+            .parent()
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        let domain_filter_lists: Vec<String> = globals
+            .get::<_, Option<Vec<String>>>("domain_filter_lists")?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|path| {
+                config_folder
+                    .join(path.trim())
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+
+        #[cfg(feature = "redis-cache")]
+        let redis = {
+            let urls: Vec<String> = match globals.get::<_, Option<Vec<String>>>("redis_nodes")? {
+                Some(nodes) => nodes,
+                None => globals
+                    .get::<_, Option<String>>("redis_url")?
+                    .into_iter()
+                    .collect(),
+            };
+            let ca_certificate = globals
+                .get::<_, Option<String>>("redis_tls_ca_file")?
+                .map(|path| {
+                    fs::read(config_folder.join(path.trim())).map_err(|error| {
+                        format!(
+                            "Config Error: Unable to read the `redis_tls_ca_file` option: {error}"
+                        )
+                    })
+                })
+                .transpose()?;
+            let password = globals
+                .get::<_, Option<String>>("redis_password_file")?
+                .map(|path| read_secret(&config_folder.join(path.trim())))
+                .transpose()?;
+
+            RedisConfig::new(
+                globals
+                    .get::<_, Option<String>>("redis_mode")?
+                    .as_deref()
+                    .unwrap_or("standalone"),
+                urls,
+                globals.get::<_, Option<String>>("redis_sentinel_master")?,
+                ca_certificate,
+                globals
+                    .get::<_, Option<String>>("redis_username")?
+                    .filter(|username| !username.is_empty()),
+                password,
+            )
+            .map_err(|error| format!("Config Error: {error}"))?
+        };
+
         Ok(Config {
             port: globals.get::<_, u16>("port")?,
             binding_ip: globals.get::<_, String>("binding_ip")?,
@@ -140,9 +354,10 @@ impl Config {
                 globals.get::<_, Option<String>>("animation")?,
             ),
             #[cfg(feature = "redis-cache")]
-            redis_url: globals.get::<_, String>("redis_url")?,
+            redis,
             aggregator: AggregatorConfig {
                 random_delay: globals.get::<_, bool>("production_use")?,
+                prefetch,
             },
             logging,
             debug,
@@ -160,13 +375,96 @@ impl Config {
                 time_limit: rate_limiter["time_limit"],
             },
             safe_search,
-            #[cfg(any(feature = "redis-cache", feature = "memory-cache"))]
+            results_per_page,
+            #[cfg(any(
+                feature = "redis-cache",
+                feature = "memory-cache",
+                feature = "disk-cache"
+            ))]
             cache_expiry_time,
+            #[cfg(any(
+                feature = "redis-cache",
+                feature = "memory-cache",
+                feature = "disk-cache"
+            ))]
+            cache_stale_time: globals
+                .get::<_, Option<u16>>("cache_stale_time")?
+                .unwrap_or_default(),
+            #[cfg(any(
+                feature = "redis-cache",
+                feature = "memory-cache",
+                feature = "disk-cache"
+            ))]
+            cache_failure_time: globals
+                .get::<_, Option<u16>>("cache_failure_time")?
+                .unwrap_or(DEFAULT_CACHE_FAILURE_TIME)
+                .min(cache_expiry_time),
+            #[cfg(feature = "disk-cache")]
+            disk_cache_path: config_folder
+                .join(
+                    globals
+                        .get::<_, Option<String>>("disk_cache_path")?
+                        .as_deref()
+                        .unwrap_or(DEFAULT_DISK_CACHE_PATH)
+                        .trim(),
+                )
+                .to_string_lossy()
+                .into_owned(),
+            #[cfg(feature = "disk-cache")]
+            disk_cache_max_size,
+            #[cfg(any(feature = "encrypt-cache-results", feature = "cec-cache-results"))]
+            cache_encryption_key_file: globals
+                .get::<_, Option<String>>("cache_encryption_key_file")?
+                .map(|path| {
+                    config_folder
+                        .join(path.trim())
+                        .to_string_lossy()
+                        .into_owned()
+                }),
             proxy,
+            privacy_redirects,
+            tracking_parameters,
+            domain_filter_lists,
+            reveal_filtered_results: globals
+                .get::<_, Option<bool>>("reveal_filtered_results")?
+                .unwrap_or(false),
+            preferences_token_secret: globals
+                .get::<_, Option<String>>("preferences_token_secret")?
+                .filter(|secret| !secret.is_empty()),
+            admin_token: globals
+                .get::<_, Option<String>>("admin_token")?
+                .filter(|token| !token.is_empty()),
         })
     }
 }
 
+/// A helper function which reads a secret (like a password) from the first line of a file.
+///
+/// # Arguments
+///
+/// * `path` - It takes the path of the file holding the secret.
+///
+/// # Error
+///
+/// Returns the secret on success otherwise returns an error if the file cannot be read or is
+/// empty.
+#[cfg(feature = "redis-cache")]
+fn read_secret(path: &Path) -> Result<String, String> {
+    let content = fs::read_to_string(path).map_err(|error| {
+        format!(
+            "Config Error: Unable to read the secrets file {}: {error}",
+            path.display()
+        )
+    })?;
+    match content.lines().next().map(str::trim_end) {
+        Some(secret) if !secret.is_empty() => Ok(secret.to_owned()),
+        _ => Err(format!(
+            "Config Error: The secrets file {} is empty",
+            path.display()
+        )),
+    }
+}
+
 /// a helper function that sets the proper logging level
 ///
 /// # Arguments
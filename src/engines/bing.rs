@@ -9,9 +9,9 @@ use reqwest::header::HeaderMap;
 use reqwest::Client;
 use scraper::Html;
 
-use crate::models::aggregation_models::SearchResult;
+use crate::models::aggregation_models::{EngineResults, SearchResult};
 
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::engine_models::{EngineError, SafeSearchMode, SearchEngine};
 
 use error_stack::{Report, Result, ResultExt};
 
@@ -34,9 +34,51 @@ impl Bing {
                 "h2 a",
                 ".tpcn a.tilk",
                 ".b_caption p",
-            )?,
+            )?
+            .with_related_searches(".b_rs ul li a")?,
         })
     }
+
+    /// Parses the search results from the html document returned by the upstream search
+    /// engine.
+    fn parse_results(&self, document: &Html) -> Result<EngineResults, EngineError> {
+        // Bing is very aggressive in finding matches
+        // even with the most absurd of queries. ".b_algo" is the
+        // class for the list item of results
+        if let Some(no_result_msg) = self.parser.parse_for_no_results(document).nth(0) {
+            if no_result_msg
+                .value()
+                .attr("class")
+                .map(|classes| classes.contains("b_algo"))
+                .unwrap_or(false)
+            {
+                return Err(Report::new(EngineError::EmptyResultSet));
+            }
+        }
+
+        let re_span = Regex::new(r#"<span.*?>.*?(?:</span>&nbsp;·|</span>)"#).unwrap();
+        let re_strong = Regex::new(r#"(<strong>|</strong>)"#).unwrap();
+
+        let related_searches = self
+            .parser
+            .parse_for_related_searches(document, |related_search| {
+                Some(related_search.text().collect::<String>())
+            });
+
+        // scrape all the results from the html
+        self.parser
+            .parse_for_results(document, |title, url, desc| {
+                url.value().attr("href").map(|url| {
+                    SearchResult::new(
+                        &re_strong.replace_all(title.inner_html().trim(), ""),
+                        url,
+                        &re_span.replace_all(desc.inner_html().trim(), ""),
+                        &["bing"],
+                    )
+                })
+            })
+            .map(|results| EngineResults::new(results, related_searches))
+    }
 }
 
 #[async_trait::async_trait]
@@ -47,20 +89,28 @@ impl SearchEngine for Bing {
         page: u32,
         user_agent: &str,
         client: &Client,
-        _safe_search: u8,
-    ) -> Result<Vec<(String, SearchResult)>, EngineError> {
+        safe_search: u8,
+    ) -> Result<EngineResults, EngineError> {
         // Bing uses `start results from this number` convention
         // So, for 10 results per page, page 0 starts at 1, page 1
         // starts at 11, and so on.
         let results_per_page = 10;
         let start_result = results_per_page * page + 1;
 
+        let adult_filter = match SafeSearchMode::from_level(safe_search) {
+            SafeSearchMode::Off => "off",
+            SafeSearchMode::Moderate => "moderate",
+            SafeSearchMode::Strict => "strict",
+        };
+
         let url: String = match page {
             0 => {
-                format!("https://www.bing.com/search?q={query}")
+                format!("https://www.bing.com/search?q={query}&adlt={adult_filter}")
             }
             _ => {
-                format!("https://www.bing.com/search?q={query}&first={start_result}")
+                format!(
+                    "https://www.bing.com/search?q={query}&first={start_result}&adlt={adult_filter}"
+                )
             }
         };
 
@@ -93,32 +143,23 @@ impl SearchEngine for Bing {
             &Bing::fetch_html_from_upstream(self, &url, header_map, client).await?,
         );
 
-        // Bing is very aggressive in finding matches
-        // even with the most absurd of queries. ".b_algo" is the
-        // class for the list item of results
-        if let Some(no_result_msg) = self.parser.parse_for_no_results(&document).nth(0) {
-            if no_result_msg
-                .value()
-                .attr("class")
-                .map(|classes| classes.contains("b_algo"))
-                .unwrap_or(false)
-            {
-                return Err(Report::new(EngineError::EmptyResultSet));
-            }
-        }
-
-        let re_span = Regex::new(r#"<span.*?>.*?(?:</span>&nbsp;·|</span>)"#).unwrap();
-        let re_strong = Regex::new(r#"(<strong>|</strong>)"#).unwrap();
+        self.parse_results(&document)
+    }
+}
 
-        // scrape all the results from the html
-        self.parser
-            .parse_for_results(&document, |title, url, desc| {
-                Some(SearchResult::new(
-                    &re_strong.replace_all(title.inner_html().trim(), ""),
-                    url.value().attr("href").unwrap(),
-                    &re_span.replace_all(desc.inner_html().trim(), ""),
-                    &["bing"],
-                ))
-            })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::search_result_parser::tests::{
+        assert_sanitized, MALICIOUS_DESCRIPTION, MALICIOUS_TITLE,
+    };
+
+    #[test]
+    fn test_parse_results_sanitizes_malicious_html() {
+        let document = Html::parse_document(&format!(
+            r#"<html><body><ol id="b_results"><li class="b_algo"><div class="tpcn"><a class="tilk" href="https://www.rust-lang.org/">rust-lang.org</a></div><h2><a href="https://www.rust-lang.org/">{MALICIOUS_TITLE}</a></h2><div class="b_caption"><p>{MALICIOUS_DESCRIPTION}</p></div></li></ol></body></html>"#
+        ));
+        let engine_results = Bing::new().unwrap().parse_results(&document).unwrap();
+        assert_sanitized(&engine_results);
     }
 }
@@ -7,10 +7,10 @@ use std::collections::HashMap;
 use reqwest::{header::HeaderMap, Client};
 use scraper::Html;
 
-use crate::models::aggregation_models::SearchResult;
+use crate::models::aggregation_models::{EngineResults, SearchResult};
 use error_stack::{Report, Result, ResultExt};
 
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::engine_models::{EngineError, SafeSearchMode, SearchEngine};
 
 use super::search_result_parser::SearchResultParser;
 
@@ -30,9 +30,42 @@ impl Brave {
                 "a > .url",
                 "a",
                 ".snippet-description",
-            )?,
+            )?
+            .with_related_searches("#related-queries a")?,
         })
     }
+
+    /// Parses the search results from the html document returned by the upstream search
+    /// engine.
+    fn parse_results(&self, document: &Html) -> Result<EngineResults, EngineError> {
+        if let Some(no_result_msg) = self.parser.parse_for_no_results(document).nth(0) {
+            if no_result_msg
+                .inner_html()
+                .contains("Not many great matches came back for your search")
+            {
+                return Err(Report::new(EngineError::EmptyResultSet));
+            }
+        }
+
+        let related_searches = self
+            .parser
+            .parse_for_related_searches(document, |related_search| {
+                Some(related_search.text().collect::<String>())
+            });
+
+        self.parser
+            .parse_for_results(document, |title, url, desc| {
+                url.value().attr("href").map(|url| {
+                    SearchResult::new(
+                        title.inner_html().trim(),
+                        url.trim(),
+                        desc.inner_html().trim(),
+                        &["brave"],
+                    )
+                })
+            })
+            .map(|results| EngineResults::new(results, related_searches))
+    }
 }
 
 #[async_trait::async_trait]
@@ -44,13 +77,13 @@ impl SearchEngine for Brave {
         user_agent: &str,
         client: &Client,
         safe_search: u8,
-    ) -> Result<Vec<(String, SearchResult)>, EngineError> {
+    ) -> Result<EngineResults, EngineError> {
         let url = format!("https://search.brave.com/search?q={query}&offset={page}");
 
-        let safe_search_level = match safe_search {
-            0 => "off",
-            1 => "moderate",
-            _ => "strict",
+        let safe_search_level = match SafeSearchMode::from_level(safe_search) {
+            SafeSearchMode::Off => "off",
+            SafeSearchMode::Moderate => "moderate",
+            SafeSearchMode::Strict => "strict",
         };
 
         let header_map = HeaderMap::try_from(&HashMap::from([
@@ -71,25 +104,23 @@ impl SearchEngine for Brave {
             &Brave::fetch_html_from_upstream(self, &url, header_map, client).await?,
         );
 
-        if let Some(no_result_msg) = self.parser.parse_for_no_results(&document).nth(0) {
-            if no_result_msg
-                .inner_html()
-                .contains("Not many great matches came back for your search")
-            {
-                return Err(Report::new(EngineError::EmptyResultSet));
-            }
-        }
+        self.parse_results(&document)
+    }
+}
 
-        self.parser
-            .parse_for_results(&document, |title, url, desc| {
-                url.value().attr("href").map(|url| {
-                    SearchResult::new(
-                        title.text().collect::<Vec<_>>().join("").trim(),
-                        url.trim(),
-                        desc.inner_html().trim(),
-                        &["brave"],
-                    )
-                })
-            })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::search_result_parser::tests::{
+        assert_sanitized, MALICIOUS_DESCRIPTION, MALICIOUS_TITLE,
+    };
+
+    #[test]
+    fn test_parse_results_sanitizes_malicious_html() {
+        let document = Html::parse_document(&format!(
+            r#"<html><body><div id="results"><div data-pos="0"><a href="https://www.rust-lang.org/"><div class="url">{MALICIOUS_TITLE}</div></a><div class="snippet-description">{MALICIOUS_DESCRIPTION}</div></div></div></body></html>"#
+        ));
+        let engine_results = Brave::new().unwrap().parse_results(&document).unwrap();
+        assert_sanitized(&engine_results);
     }
 }
@@ -8,9 +8,9 @@ use reqwest::header::HeaderMap;
 use reqwest::Client;
 use scraper::Html;
 
-use crate::models::aggregation_models::SearchResult;
+use crate::models::aggregation_models::{EngineResults, SearchResult};
 
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::engine_models::{EngineError, SafeSearchMode, SearchEngine};
 
 use error_stack::{Report, Result, ResultExt};
 
@@ -36,6 +36,31 @@ impl DuckDuckGo {
             )?,
         })
     }
+
+    /// Parses the search results from the html document returned by the upstream search
+    /// engine.
+    fn parse_results(&self, document: &Html) -> Result<EngineResults, EngineError> {
+        if self.parser.parse_for_no_results(document).next().is_some() {
+            return Err(Report::new(EngineError::EmptyResultSet));
+        }
+
+        // scrape all the results from the html
+        self.parser
+            .parse_for_results(document, |title, url, desc| {
+                Some(SearchResult::new(
+                    title.inner_html().trim(),
+                    // the link points to the `//duckduckgo.com/l/?uddg=` redirect wrapper which
+                    // is unwrapped later by the url cleaner.
+                    &url.value()
+                        .attr("href")
+                        .map(str::to_owned)
+                        .unwrap_or_else(|| format!("https://{}", url.inner_html().trim())),
+                    desc.inner_html().trim(),
+                    &["duckduckgo"],
+                ))
+            })
+            .map(EngineResults::from)
+    }
 }
 
 #[async_trait::async_trait]
@@ -46,17 +71,23 @@ impl SearchEngine for DuckDuckGo {
         page: u32,
         user_agent: &str,
         client: &Client,
-        _safe_search: u8,
-    ) -> Result<Vec<(String, SearchResult)>, EngineError> {
+        safe_search: u8,
+    ) -> Result<EngineResults, EngineError> {
+        let safe_search = match SafeSearchMode::from_level(safe_search) {
+            SafeSearchMode::Off => -2,
+            SafeSearchMode::Moderate => -1,
+            SafeSearchMode::Strict => 1,
+        };
+
         // Page number can be missing or empty string and so appropriate handling is required
         // so that upstream server recieves valid page number.
         let url: String = match page {
             0 => {
-                format!("https://html.duckduckgo.com/html/?q={query}&s=&dc=&v=1&o=json&api=/d.js")
+                format!("https://html.duckduckgo.com/html/?q={query}&s=&dc=&v=1&o=json&api=/d.js&kp={safe_search}")
             }
             _ => {
                 format!(
-                    "https://duckduckgo.com/html/?q={query}&s={}&dc={}&v=1&o=json&api=/d.js",
+                    "https://duckduckgo.com/html/?q={query}&s={}&dc={}&v=1&o=json&api=/d.js&kp={safe_search}",
                     page * 30,
                     page * 30 + 1
                 )
@@ -79,19 +110,23 @@ impl SearchEngine for DuckDuckGo {
             &DuckDuckGo::fetch_html_from_upstream(self, &url, header_map, client).await?,
         );
 
-        if self.parser.parse_for_no_results(&document).next().is_some() {
-            return Err(Report::new(EngineError::EmptyResultSet));
-        }
+        self.parse_results(&document)
+    }
+}
 
-        // scrape all the results from the html
-        self.parser
-            .parse_for_results(&document, |title, url, desc| {
-                Some(SearchResult::new(
-                    title.inner_html().trim(),
-                    &format!("https://{}", url.inner_html().trim()),
-                    desc.inner_html().trim(),
-                    &["duckduckgo"],
-                ))
-            })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::search_result_parser::tests::{
+        assert_sanitized, MALICIOUS_DESCRIPTION, MALICIOUS_TITLE,
+    };
+
+    #[test]
+    fn test_parse_results_sanitizes_malicious_html() {
+        let document = Html::parse_document(&format!(
+            r#"<html><body><div class="results"><div class="result"><h2 class="result__title"><a class="result__a" href="https://www.rust-lang.org/">{MALICIOUS_TITLE}</a></h2><a class="result__url" href="https://www.rust-lang.org/">www.rust-lang.org</a><a class="result__snippet">{MALICIOUS_DESCRIPTION}</a></div></div></body></html>"#
+        ));
+        let engine_results = DuckDuckGo::new().unwrap().parse_results(&document).unwrap();
+        assert_sanitized(&engine_results);
     }
 }
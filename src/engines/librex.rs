@@ -7,8 +7,8 @@ use reqwest::header::HeaderMap;
 use reqwest::Client;
 use scraper::Html;
 
-use crate::models::aggregation_models::SearchResult;
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::aggregation_models::{EngineResults, SearchResult};
+use crate::models::engine_models::{EngineError, SafeSearchMode, SearchEngine};
 
 use error_stack::{Report, Result, ResultExt};
 
@@ -37,6 +37,26 @@ impl LibreX {
             )?,
         })
     }
+
+    /// Parses the search results from the html document returned by the upstream search
+    /// engine.
+    fn parse_results(&self, document: &Html) -> Result<EngineResults, EngineError> {
+        if self.parser.parse_for_no_results(document).next().is_some() {
+            return Err(Report::new(EngineError::EmptyResultSet));
+        }
+
+        // scrape all the results from the html
+        self.parser
+            .parse_for_results(document, |title, url, desc| {
+                Some(SearchResult::new(
+                    title.inner_html().trim(),
+                    url.inner_html().trim(),
+                    desc.inner_html().trim(),
+                    &["librex"],
+                ))
+            })
+            .map(EngineResults::from)
+    }
 }
 
 #[async_trait::async_trait]
@@ -49,7 +69,8 @@ impl SearchEngine for LibreX {
     /// * `page` - The page number for pagination.
     /// * `user_agent` - The user agent string.
     /// * `client` - The reqwest client for making HTTP requests.
-    /// * `_safe_search` - A parameter for safe search (not currently used).
+    /// * `safe_search` - The safe search level, which turns on the safe search of LibreX for
+    ///   every level except 0.
     ///
     /// # Returns
     ///
@@ -61,8 +82,14 @@ impl SearchEngine for LibreX {
         page: u32,
         user_agent: &str,
         client: &Client,
-        _safe_search: u8,
-    ) -> Result<Vec<(String, SearchResult)>, EngineError> {
+        safe_search: u8,
+    ) -> Result<EngineResults, EngineError> {
+        // LibreX only supports turning safe search on, which is done by the cookie below.
+        let safe_search = match SafeSearchMode::from_level(safe_search) {
+            SafeSearchMode::Off => "",
+            SafeSearchMode::Moderate | SafeSearchMode::Strict => " safe_search=on;",
+        };
+
         // Page number can be missing or empty string and so appropriate handling is required
         // so that upstream server recieves valid page number.
         let url: String = format!(
@@ -77,7 +104,7 @@ impl SearchEngine for LibreX {
             ("Content-Type".to_string(), "application/x-www-form-urlencoded".to_string()),
             (
                 "Cookie".to_string(),
-                "theme=amoled; disable_special=on; disable_frontends=on; language=en; number_of_results=10; safe_search=on; save=1".to_string(),
+                format!("theme=amoled; disable_special=on; disable_frontends=on; language=en; number_of_results=10;{safe_search} save=1"),
             ),
         ]))
         .change_context(EngineError::UnexpectedError)?;
@@ -86,19 +113,23 @@ impl SearchEngine for LibreX {
             &LibreX::fetch_html_from_upstream(self, &url, header_map, client).await?,
         );
 
-        if self.parser.parse_for_no_results(&document).next().is_some() {
-            return Err(Report::new(EngineError::EmptyResultSet));
-        }
+        self.parse_results(&document)
+    }
+}
 
-        // scrape all the results from the html
-        self.parser
-            .parse_for_results(&document, |title, url, desc| {
-                Some(SearchResult::new(
-                    title.inner_html().trim(),
-                    url.inner_html().trim(),
-                    desc.inner_html().trim(),
-                    &["librex"],
-                ))
-            })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::search_result_parser::tests::{
+        assert_sanitized, MALICIOUS_DESCRIPTION, MALICIOUS_TITLE,
+    };
+
+    #[test]
+    fn test_parse_results_sanitizes_malicious_html() {
+        let document = Html::parse_document(&format!(
+            r#"<html><body><div class="text-result-container"><div class="text-result-wrapper"><a href="https://www.rust-lang.org/"><h2>{MALICIOUS_TITLE}</h2></a><span>{MALICIOUS_DESCRIPTION}</span></div></div></body></html>"#
+        ));
+        let engine_results = LibreX::new().unwrap().parse_results(&document).unwrap();
+        assert_sanitized(&engine_results);
     }
 }
@@ -8,9 +8,9 @@ use reqwest::header::HeaderMap;
 use reqwest::Client;
 use scraper::Html;
 
-use crate::models::aggregation_models::SearchResult;
+use crate::models::aggregation_models::{EngineResults, SearchResult};
 
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::engine_models::{EngineError, SafeSearchMode, SearchEngine};
 
 use error_stack::{Report, Result, ResultExt};
 
@@ -36,6 +36,31 @@ impl Mojeek {
             )?,
         })
     }
+
+    /// Parses the search results from the html document returned by the upstream search
+    /// engine.
+    fn parse_results(&self, document: &Html) -> Result<EngineResults, EngineError> {
+        if let Some(no_result_msg) = self.parser.parse_for_no_results(document).nth(0) {
+            if no_result_msg
+                .inner_html()
+                .contains("No pages found matching:")
+            {
+                return Err(Report::new(EngineError::EmptyResultSet));
+            }
+        }
+
+        // scrape all the results from the html
+        self.parser
+            .parse_for_results(document, |title, url, desc| {
+                Some(SearchResult::new(
+                    title.inner_html().trim(),
+                    url.inner_html().trim(),
+                    desc.inner_html().trim(),
+                    &["mojeek"],
+                ))
+            })
+            .map(EngineResults::from)
+    }
 }
 
 #[async_trait::async_trait]
@@ -47,7 +72,7 @@ impl SearchEngine for Mojeek {
         user_agent: &str,
         client: &Client,
         safe_search: u8,
-    ) -> Result<Vec<(String, SearchResult)>, EngineError> {
+    ) -> Result<EngineResults, EngineError> {
         // Mojeek uses `start results from this number` convention
         // So, for 10 results per page, page 0 starts at 1, page 1
         // starts at 11, and so on.
@@ -75,20 +100,9 @@ impl SearchEngine for Mojeek {
 
         let qss = search_engines.join("%2C");
 
-        // A branchless condition to check whether the `safe_search` parameter has the
-        // value 0 or not. If it is zero then it sets the value 0 otherwise it sets
-        // the value to 1 for all other values of `safe_search`
-        //
-        // Moreover, the below branchless code is equivalent to the following code below:
-        //
-        // ```rust
-        // let safe = if safe_search == 0 { 0 } else { 1 }.to_string();
-        // ```
-        //
-        // For more information on branchless programming. See:
-        //
-        // * https://piped.video/watch?v=bVJ-mWWL7cE
-        let safe = u8::from(safe_search != 0).to_string();
+        // Mojeek only supports turning its safe search on or off.
+        let safe =
+            u8::from(SafeSearchMode::from_level(safe_search) != SafeSearchMode::Off).to_string();
 
         // Mojeek detects automated requests, these are preferences that are
         // able to circumvent the countermeasure. Some of these are
@@ -143,24 +157,23 @@ impl SearchEngine for Mojeek {
             &Mojeek::fetch_html_from_upstream(self, &url, header_map, client).await?,
         );
 
-        if let Some(no_result_msg) = self.parser.parse_for_no_results(&document).nth(0) {
-            if no_result_msg
-                .inner_html()
-                .contains("No pages found matching:")
-            {
-                return Err(Report::new(EngineError::EmptyResultSet));
-            }
-        }
+        self.parse_results(&document)
+    }
+}
 
-        // scrape all the results from the html
-        self.parser
-            .parse_for_results(&document, |title, url, desc| {
-                Some(SearchResult::new(
-                    title.inner_html().trim(),
-                    url.inner_html().trim(),
-                    desc.inner_html().trim(),
-                    &["mojeek"],
-                ))
-            })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::search_result_parser::tests::{
+        assert_sanitized, MALICIOUS_DESCRIPTION, MALICIOUS_TITLE,
+    };
+
+    #[test]
+    fn test_parse_results_sanitizes_malicious_html() {
+        let document = Html::parse_document(&format!(
+            r#"<html><body><ul class="results-standard"><li><a class="ob" href="https://www.rust-lang.org/"><span class="url">{MALICIOUS_TITLE}</span></a><h2><a class="title" href="https://www.rust-lang.org/">https://www.rust-lang.org/</a></h2><p class="s">{MALICIOUS_DESCRIPTION}</p></li></ul></body></html>"#
+        ));
+        let engine_results = Mojeek::new().unwrap().parse_results(&document).unwrap();
+        assert_sanitized(&engine_results);
     }
 }
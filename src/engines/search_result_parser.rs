@@ -16,6 +16,8 @@ pub struct SearchResultParser {
     result_url: Selector,
     /// selector to locate the description relative to the search result item.
     result_desc: Selector,
+    /// selector to locate the related search items, if the engine provides any.
+    related_searches: Option<Selector>,
 }
 
 impl SearchResultParser {
@@ -33,9 +35,20 @@ impl SearchResultParser {
             result_title: new_selector(result_title_selector)?,
             result_url: new_selector(result_url_selector)?,
             result_desc: new_selector(result_desc_selector)?,
+            related_searches: None,
         })
     }
 
+    /// Sets the selector used to locate the related search items, if the given parameter is a
+    /// valid css expression, otherwise it returns an EngineError.
+    pub fn with_related_searches(
+        mut self,
+        related_searches_selector: &str,
+    ) -> Result<SearchResultParser, EngineError> {
+        self.related_searches = Some(new_selector(related_searches_selector)?);
+        Ok(self)
+    }
+
     /// Parse the html and returns element representing the 'no result found' response.
     pub fn parse_for_no_results<'a>(&'a self, document: &'a Html) -> Select<'a, 'a> {
         document.select(&self.no_result)
@@ -62,6 +75,25 @@ impl SearchResultParser {
             .collect();
         Ok(res)
     }
+
+    /// Parse the html for the related searches, with the help of the builder function which
+    /// extracts the query from each matched element. Returns an empty list if no related
+    /// searches selector was provided for the engine.
+    pub fn parse_for_related_searches(
+        &self,
+        document: &Html,
+        builder: impl Fn(&ElementRef<'_>) -> Option<String>,
+    ) -> Vec<String> {
+        match self.related_searches {
+            Some(ref selector) => document
+                .select(selector)
+                .filter_map(|related_search| builder(&related_search))
+                .map(|related_search| related_search.trim().to_owned())
+                .filter(|related_search| !related_search.is_empty())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
 }
 
 /// Create a Selector struct, if the given parameter is a valid css expression, otherwise convert it into an EngineError.
@@ -73,3 +105,24 @@ fn new_selector(selector: &str) -> Result<Selector, EngineError> {
         ))
     })
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::models::aggregation_models::EngineResults;
+
+    /// A title fixture containing markup which must not end up in the rendered search results.
+    pub const MALICIOUS_TITLE: &str =
+        r#"Rust <b onclick="alert(1)">lang</b><script>alert(document.cookie)</script>"#;
+    /// A description fixture containing markup which must not end up in the rendered search
+    /// results.
+    pub const MALICIOUS_DESCRIPTION: &str = r#"<img src=x onerror="alert(1)">A <em onmouseover="alert(1)">safe</em> language<iframe src="javascript:alert(1)"></iframe><!-- <script>alert(1)</script> -->"#;
+
+    /// Asserts that the engine parsed exactly one search result from the malicious fixtures and
+    /// that only the highlighting tags were kept in its title and description.
+    pub fn assert_sanitized(engine_results: &EngineResults) {
+        assert_eq!(engine_results.results.len(), 1);
+        let (_, result) = &engine_results.results[0];
+        assert_eq!(result.title, "Rust <b>lang</b>");
+        assert_eq!(result.description, "A <em>safe</em> language");
+    }
+}
@@ -8,8 +8,8 @@ use scraper::Html;
 use std::collections::HashMap;
 
 use super::search_result_parser::SearchResultParser;
-use crate::models::aggregation_models::SearchResult;
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::aggregation_models::{EngineResults, SearchResult};
+use crate::models::engine_models::{EngineError, SafeSearchMode, SearchEngine};
 use error_stack::{Report, Result, ResultExt};
 
 /// A new Searx engine type defined in-order to implement the `SearchEngine` trait which allows to
@@ -29,9 +29,47 @@ impl Searx {
                 "h3>a",
                 "h3>a",
                 ".content",
-            )?,
+            )?
+            .with_related_searches("#suggestions .suggestion")?,
         })
     }
+
+    /// Parses the search results from the html document returned by the upstream search
+    /// engine.
+    fn parse_results(&self, document: &Html) -> Result<EngineResults, EngineError> {
+        if let Some(no_result_msg) = self.parser.parse_for_no_results(document).nth(1) {
+            if no_result_msg.inner_html()
+            == "we didn't find any results. Please use another query or search in more categories"
+        {
+            return Err(Report::new(EngineError::EmptyResultSet));
+        }
+        }
+
+        // Searx renders the related searches as submit buttons whose values are prefixed
+        // with a bullet character.
+        let related_searches = self
+            .parser
+            .parse_for_related_searches(document, |related_search| {
+                related_search
+                    .value()
+                    .attr("value")
+                    .map(|value| value.trim_start_matches('•').to_owned())
+            });
+
+        // scrape all the results from the html
+        self.parser
+            .parse_for_results(document, |title, url, desc| {
+                url.value().attr("href").map(|url| {
+                    SearchResult::new(
+                        title.inner_html().trim(),
+                        url,
+                        desc.inner_html().trim(),
+                        &["searx"],
+                    )
+                })
+            })
+            .map(|results| EngineResults::new(results, related_searches))
+    }
 }
 
 #[async_trait::async_trait]
@@ -42,22 +80,13 @@ impl SearchEngine for Searx {
         page: u32,
         user_agent: &str,
         client: &Client,
-        mut safe_search: u8,
-    ) -> Result<Vec<(String, SearchResult)>, EngineError> {
-        // A branchless condition to check whether the `safe_search` parameter has the
-        // value greater than equal to three or not. If it is, then it modifies the
-        // `safesearch` parameters value to 2.
-        //
-        // Moreover, the below branchless code is equivalent to the following code below:
-        //
-        // ```rust
-        // safe_search = u8::from(safe_search == 3) * 2;
-        // ```
-        //
-        // For more information on branchless programming. See:
-        //
-        // * https://piped.video/watch?v=bVJ-mWWL7cE
-        safe_search = u8::from(safe_search >= 3) * 2;
+        safe_search: u8,
+    ) -> Result<EngineResults, EngineError> {
+        let safe_search = match SafeSearchMode::from_level(safe_search) {
+            SafeSearchMode::Off => 0,
+            SafeSearchMode::Moderate => 1,
+            SafeSearchMode::Strict => 2,
+        };
 
         let url: String = format!(
             "https://searx.be/search?q={query}&pageno={}&safesearch={safe_search}",
@@ -69,7 +98,7 @@ impl SearchEngine for Searx {
             ("User-Agent".to_string(), user_agent.to_string()),
             ("Referer".to_string(), "https://google.com/".to_string()),
             ("Content-Type".to_string(), "application/x-www-form-urlencoded".to_string()),
-            ("Cookie".to_string(), "categories=general; language=auto; locale=en; autocomplete=duckduckgo; image_proxy=1; method=POST; safesearch=2; theme=simple; results_on_new_tab=1; doi_resolver=oadoi.org; simple_style=auto; center_alignment=1; query_in_title=1; infinite_scroll=0; disabled_engines=; enabled_engines=\"archive is__general\\054yep__general\\054curlie__general\\054currency__general\\054ddg definitions__general\\054wikidata__general\\054duckduckgo__general\\054tineye__general\\054lingva__general\\054startpage__general\\054yahoo__general\\054wiby__general\\054marginalia__general\\054alexandria__general\\054wikibooks__general\\054wikiquote__general\\054wikisource__general\\054wikiversity__general\\054wikivoyage__general\\054dictzone__general\\054seznam__general\\054mojeek__general\\054naver__general\\054wikimini__general\\054brave__general\\054petalsearch__general\\054goo__general\"; disabled_plugins=; enabled_plugins=\"searx.plugins.hostname_replace\\054searx.plugins.oa_doi_rewrite\\054searx.plugins.vim_hotkeys\"; tokens=; maintab=on; enginetab=on".to_string())
+            ("Cookie".to_string(), format!("categories=general; language=auto; locale=en; autocomplete=duckduckgo; image_proxy=1; method=POST; safesearch={safe_search}; theme=simple; results_on_new_tab=1; doi_resolver=oadoi.org; simple_style=auto; center_alignment=1; query_in_title=1; infinite_scroll=0; disabled_engines=; enabled_engines=\"archive is__general\\054yep__general\\054curlie__general\\054currency__general\\054ddg definitions__general\\054wikidata__general\\054duckduckgo__general\\054tineye__general\\054lingva__general\\054startpage__general\\054yahoo__general\\054wiby__general\\054marginalia__general\\054alexandria__general\\054wikibooks__general\\054wikiquote__general\\054wikisource__general\\054wikiversity__general\\054wikivoyage__general\\054dictzone__general\\054seznam__general\\054mojeek__general\\054naver__general\\054wikimini__general\\054brave__general\\054petalsearch__general\\054goo__general\"; disabled_plugins=; enabled_plugins=\"searx.plugins.hostname_replace\\054searx.plugins.oa_doi_rewrite\\054searx.plugins.vim_hotkeys\"; tokens=; maintab=on; enginetab=on"))
         ]))
         .change_context(EngineError::UnexpectedError)?;
 
@@ -77,25 +106,23 @@ impl SearchEngine for Searx {
             &Searx::fetch_html_from_upstream(self, &url, header_map, client).await?,
         );
 
-        if let Some(no_result_msg) = self.parser.parse_for_no_results(&document).nth(1) {
-            if no_result_msg.inner_html()
-            == "we didn't find any results. Please use another query or search in more categories"
-        {
-            return Err(Report::new(EngineError::EmptyResultSet));
-        }
-        }
+        self.parse_results(&document)
+    }
+}
 
-        // scrape all the results from the html
-        self.parser
-            .parse_for_results(&document, |title, url, desc| {
-                url.value().attr("href").map(|url| {
-                    SearchResult::new(
-                        title.inner_html().trim(),
-                        url,
-                        desc.inner_html().trim(),
-                        &["searx"],
-                    )
-                })
-            })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::search_result_parser::tests::{
+        assert_sanitized, MALICIOUS_DESCRIPTION, MALICIOUS_TITLE,
+    };
+
+    #[test]
+    fn test_parse_results_sanitizes_malicious_html() {
+        let document = Html::parse_document(&format!(
+            r#"<html><body><div id="urls"><div class="result"><h3><a href="https://www.rust-lang.org/">{MALICIOUS_TITLE}</a></h3><p class="content">{MALICIOUS_DESCRIPTION}</p></div></div></body></html>"#
+        ));
+        let engine_results = Searx::new().unwrap().parse_results(&document).unwrap();
+        assert_sanitized(&engine_results);
     }
 }
@@ -8,9 +8,9 @@ use reqwest::header::HeaderMap;
 use reqwest::Client;
 use scraper::Html;
 
-use crate::models::aggregation_models::SearchResult;
+use crate::models::aggregation_models::{EngineResults, SearchResult};
 
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::engine_models::{EngineError, SafeSearchMode, SearchEngine};
 
 use error_stack::{Report, Result, ResultExt};
 
@@ -36,6 +36,26 @@ impl Startpage {
             )?,
         })
     }
+
+    /// Parses the search results from the html document returned by the upstream search
+    /// engine.
+    fn parse_results(&self, document: &Html) -> Result<EngineResults, EngineError> {
+        if self.parser.parse_for_no_results(document).next().is_some() {
+            return Err(Report::new(EngineError::EmptyResultSet));
+        }
+
+        // scrape all the results from the html
+        self.parser
+            .parse_for_results(document, |title, url, desc| {
+                Some(SearchResult::new(
+                    title.inner_html().trim(),
+                    url.inner_html().trim(),
+                    desc.inner_html().trim(),
+                    &["startpage"],
+                ))
+            })
+            .map(EngineResults::from)
+    }
 }
 
 #[async_trait::async_trait]
@@ -46,8 +66,12 @@ impl SearchEngine for Startpage {
         page: u32,
         user_agent: &str,
         client: &Client,
-        _safe_search: u8,
-    ) -> Result<Vec<(String, SearchResult)>, EngineError> {
+        safe_search: u8,
+    ) -> Result<EngineResults, EngineError> {
+        // Startpage only supports turning its family filter on or off.
+        let disable_family_filter =
+            u8::from(SafeSearchMode::from_level(safe_search) == SafeSearchMode::Off);
+
         // Page number can be missing or empty string and so appropriate handling is required
         // so that upstream server recieves valid page number.
         let url: String = format!(
@@ -63,7 +87,7 @@ impl SearchEngine for Startpage {
                 "Content-Type".to_string(),
                 "application/x-www-form-urlencoded".to_string(),
             ),
-            ("Cookie".to_string(), "preferences=connect_to_serverEEE0N1Ndate_timeEEEworldN1Ndisable_family_filterEEE0N1Ndisable_open_in_new_windowEEE0N1Nenable_post_methodEEE1N1Nenable_proxy_safety_suggestEEE1N1Nenable_stay_controlEEE0N1Ninstant_answersEEE1N1Nlang_homepageEEEs%2Fnight%2FenN1NlanguageEEEenglishN1Nlanguage_uiEEEenglishN1Nnum_of_resultsEEE10N1Nsearch_results_regionEEEallN1NsuggestionsEEE1N1Nwt_unitEEEcelsius".to_string()),
+            ("Cookie".to_string(), format!("preferences=connect_to_serverEEE0N1Ndate_timeEEEworldN1Ndisable_family_filterEEE{disable_family_filter}N1Ndisable_open_in_new_windowEEE0N1Nenable_post_methodEEE1N1Nenable_proxy_safety_suggestEEE1N1Nenable_stay_controlEEE0N1Ninstant_answersEEE1N1Nlang_homepageEEEs%2Fnight%2FenN1NlanguageEEEenglishN1Nlanguage_uiEEEenglishN1Nnum_of_resultsEEE10N1Nsearch_results_regionEEEallN1NsuggestionsEEE1N1Nwt_unitEEEcelsius")),
         ]))
         .change_context(EngineError::UnexpectedError)?;
 
@@ -71,19 +95,23 @@ impl SearchEngine for Startpage {
             &Startpage::fetch_html_from_upstream(self, &url, header_map, client).await?,
         );
 
-        if self.parser.parse_for_no_results(&document).next().is_some() {
-            return Err(Report::new(EngineError::EmptyResultSet));
-        }
+        self.parse_results(&document)
+    }
+}
 
-        // scrape all the results from the html
-        self.parser
-            .parse_for_results(&document, |title, url, desc| {
-                Some(SearchResult::new(
-                    title.inner_html().trim(),
-                    url.inner_html().trim(),
-                    desc.inner_html().trim(),
-                    &["startpage"],
-                ))
-            })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engines::search_result_parser::tests::{
+        assert_sanitized, MALICIOUS_DESCRIPTION, MALICIOUS_TITLE,
+    };
+
+    #[test]
+    fn test_parse_results_sanitizes_malicious_html() {
+        let document = Html::parse_document(&format!(
+            r#"<html><body><div class="w-gl__result__main"><div class="w-gl__result-second-line-container"><a class="w-gl__result-title" href="https://www.rust-lang.org/"><h3>{MALICIOUS_TITLE}</h3></a></div><a class="w-gl__result-url" href="https://www.rust-lang.org/">https://www.rust-lang.org/</a><p class="w-gl__description">{MALICIOUS_DESCRIPTION}</p></div></body></html>"#
+        ));
+        let engine_results = Startpage::new().unwrap().parse_results(&document).unwrap();
+        assert_sanitized(&engine_results);
     }
 }
@@ -17,6 +17,10 @@ const CONFIG_FILE_NAME: &str = "config.lua";
 const ALLOWLIST_FILE_NAME: &str = "allowlist.txt";
 /// The constant holding the name of the BlockList text file.
 const BLOCKLIST_FILE_NAME: &str = "blocklist.txt";
+/// The constant holding the name of the plugins folder.
+const PLUGINS_DIRECTORY_NAME: &str = "plugins";
+/// The constant holding the name of the folder of the safe search classifier lists.
+const SAFE_SEARCH_DIRECTORY_NAME: &str = "safe_search";
 
 /// An enum type which provides different variants to handle paths for various files/folders.
 #[derive(Hash, PartialEq, Eq, Debug)]
@@ -29,6 +33,11 @@ pub enum FileType {
     BlockList,
     /// This variant handles all the paths associated with the public folder (Theme folder).
     Theme,
+    /// This variant handles all the paths associated with the lua plugins folder.
+    Plugins,
+    /// This variant handles all the paths associated with the folder of the safe search
+    /// classifier lists.
+    SafeSearch,
 }
 
 /// A static variable which stores the different filesystem paths for various file/folder types.
@@ -96,6 +105,37 @@ pub fn file_path(file_type: FileType) -> Result<&'static str, Error> {
                         format!("./{}/{}", COMMON_DIRECTORY_NAME, BLOCKLIST_FILE_NAME),
                     ],
                 ),
+                (
+                    FileType::Plugins,
+                    vec![
+                        format!(
+                            "{}/.config/{}/{}/",
+                            home, COMMON_DIRECTORY_NAME, PLUGINS_DIRECTORY_NAME
+                        ),
+                        format!(
+                            "/etc/xdg/{}/{}/",
+                            COMMON_DIRECTORY_NAME, PLUGINS_DIRECTORY_NAME
+                        ),
+                        format!("./{}/{}/", COMMON_DIRECTORY_NAME, PLUGINS_DIRECTORY_NAME),
+                    ],
+                ),
+                (
+                    FileType::SafeSearch,
+                    vec![
+                        format!(
+                            "{}/.config/{}/{}/",
+                            home, COMMON_DIRECTORY_NAME, SAFE_SEARCH_DIRECTORY_NAME
+                        ),
+                        format!(
+                            "/etc/xdg/{}/{}/",
+                            COMMON_DIRECTORY_NAME, SAFE_SEARCH_DIRECTORY_NAME
+                        ),
+                        format!(
+                            "./{}/{}/",
+                            COMMON_DIRECTORY_NAME, SAFE_SEARCH_DIRECTORY_NAME
+                        ),
+                    ],
+                ),
             ])
         })
         .get(&file_type)
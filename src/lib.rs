@@ -30,6 +30,8 @@ use actix_web::{
 use cache::cacher::{Cacher, SharedCache};
 use config::parser::Config;
 use handler::{file_path, FileType};
+use models::server_models::init_available_styles;
+use results::filter_lists::init_filter_lists;
 
 /// A static constant for holding the cache struct.
 static SHARED_CACHE: OnceLock<SharedCache> = OnceLock::new();
@@ -69,7 +71,18 @@ pub fn run(
 ) -> std::io::Result<Server> {
     let public_folder_path: &str = file_path(FileType::Theme)?;
 
-    let cache = SHARED_CACHE.get_or_init(|| SharedCache::new(cache));
+    // Load the filter lists before the server starts and watch them for changes.
+    init_filter_lists(&config.domain_filter_lists);
+
+    // Scan the stylesheets which the preferences of the users are checked against.
+    init_available_styles();
+
+    // Load the cache encryption keys before any results are cached.
+    #[cfg(any(feature = "encrypt-cache-results", feature = "cec-cache-results"))]
+    cache::encryption::init_keyring(config.cache_encryption_key_file.as_deref())
+        .map_err(|error| std::io::Error::other(error.to_string()))?;
+
+    let cache = SHARED_CACHE.get_or_init(|| SharedCache::new(cache, config));
 
     let server = HttpServer::new(move || {
         let cors: Cors = Cors::default()
@@ -110,6 +123,10 @@ pub fn run(
             .service(server::routes::search::search) // search page
             .service(router::about) // about page
             .service(router::settings) // settings page
+            .service(router::save_settings) // settings form submission
+            .service(router::block_site) // hide a site from the search results
+            .service(server::routes::admin::cache_stats) // cache statistics
+            .service(server::routes::admin::purge_cache) // cache purge
             .default_service(web::route().to(router::not_found)) // error page
     })
     .workers(config.threads as usize)
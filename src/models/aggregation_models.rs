@@ -2,6 +2,7 @@
 //! data scraped from the upstream search engines.
 
 use super::engine_models::EngineError;
+use crate::results::sanitizer::sanitize_html;
 use serde::{Deserialize, Serialize};
 #[cfg(any(
     feature = "use-synonyms-search",
@@ -34,16 +35,17 @@ impl SearchResult {
     ///
     /// # Arguments
     ///
-    /// * `title` - The title of the search result.
+    /// * `title` - The title of the search result as a html fragment, which gets sanitized.
     /// * `url` - The url which is accessed when clicked on it
     ///   (href url in html in simple words).
-    /// * `description` - The description of the search result.
+    /// * `description` - The description of the search result as a html fragment, which gets
+    ///   sanitized.
     /// * `engine` - The names of the upstream engines from which this results were provided.
     pub fn new(title: &str, url: &str, description: &str, engine: &[&str]) -> Self {
         SearchResult {
-            title: title.to_owned(),
+            title: sanitize_html(title),
             url: url.to_owned(),
-            description: description.to_owned(),
+            description: sanitize_html(description),
             relevance_score: 0.0,
             engine: engine.iter().map(|name| name.to_string()).collect(),
         }
@@ -107,6 +109,67 @@ impl SearchResult {
     }
 }
 
+/// A named struct to store the raw scraped search results from an upstream search engine along
+/// with the related searches it suggested for the query (if the engine provides any).
+#[derive(Default)]
+pub struct EngineResults {
+    /// The scraped search results keyed by their urls.
+    pub results: Vec<(String, SearchResult)>,
+    /// The related search queries scraped from the upstream search engine.
+    pub related_searches: Vec<String>,
+}
+
+impl EngineResults {
+    /// Constructs a new `EngineResults` with the given arguments needed for the struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `results` - The scraped search results keyed by their urls.
+    /// * `related_searches` - The related search queries scraped from the upstream search engine.
+    pub fn new(results: Vec<(String, SearchResult)>, related_searches: Vec<String>) -> Self {
+        Self {
+            results,
+            related_searches,
+        }
+    }
+}
+
+impl From<Vec<(String, SearchResult)>> for EngineResults {
+    fn from(results: Vec<(String, SearchResult)>) -> Self {
+        Self::new(results, Vec::new())
+    }
+}
+
+/// The raw results of an upstream search engine are cached as `SearchResults`, so that they can be
+/// stored by all the cache backends.
+impl From<&EngineResults> for SearchResults {
+    fn from(engine_results: &EngineResults) -> Self {
+        SearchResults::new(
+            engine_results
+                .results
+                .iter()
+                .map(|(_, result)| result.clone())
+                .collect(),
+            Box::default(),
+            engine_results.related_searches.clone().into_boxed_slice(),
+        )
+    }
+}
+
+impl From<SearchResults> for EngineResults {
+    fn from(search_results: SearchResults) -> Self {
+        Self::new(
+            search_results
+                .results
+                .into_vec()
+                .into_iter()
+                .map(|result| (result.url.clone(), result))
+                .collect(),
+            search_results.related_searches.into_vec(),
+        )
+    }
+}
+
 /// A named struct that stores the error info related to the upstream search engines.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct EngineErrorInfo {
@@ -145,6 +208,21 @@ impl EngineErrorInfo {
             },
         }
     }
+
+    /// A function which converts the error info back into the error of the upstream search
+    /// engine, which is used to serve the cached failures of the engines.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `EngineError` described by the error info.
+    pub fn engine_error(&self) -> EngineError {
+        match self.error.as_str() {
+            "EngineNotFound" => EngineError::NoSuchEngineFound(self.engine.clone()),
+            "RequestError" => EngineError::RequestError,
+            "EmptyResultSet" => EngineError::EmptyResultSet,
+            _ => EngineError::UnexpectedError,
+        }
+    }
 }
 
 /// A named struct to store, serialize, deserialize the all the search results scraped and
@@ -158,6 +236,9 @@ pub struct SearchResults {
     /// Stores the information on which engines failed with their engine name
     /// and the type of error that caused it.
     pub engine_errors_info: Box<[EngineErrorInfo]>,
+    /// Stores the deduplicated related searches collected from the upstream search engines.
+    #[serde(default)]
+    pub related_searches: Box<[String]>,
     /// Stores the flag option which holds the check value that the following
     /// search query was disallowed when the safe search level set to 4 and it
     /// was present in the `Blocklist` file.
@@ -171,6 +252,75 @@ pub struct SearchResults {
     /// Stores the flag option which holds the check value that whether any search engines were
     /// selected or not.
     pub no_engines_selected: bool,
+    /// Stores the flag option which holds the check value that whether there are more results
+    /// after the current page.
+    pub has_next_page: bool,
+    /// Stores how far the upstream search engines have been paged through when the struct holds
+    /// the pagination buffer of a search query.
+    pub pagination: PaginationState,
+    /// Stores how many search results were removed by every rule of the filter lists.
+    pub filtered_results: Vec<FilteredResultsInfo>,
+    /// Stores the flag option which holds the check value that whether the search results
+    /// removed by the filter lists have been revealed by the user.
+    pub revealed: bool,
+    /// Stores the unix time (in seconds) at which the struct was cached or zero if it has not
+    /// been cached yet, which tells whether the cached results are fresh or stale.
+    pub cached_at: u64,
+}
+
+/// A named struct to store how many search results a rule of a filter list removed, so that the
+/// users can be told why they see fewer results.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FilteredResultsInfo {
+    /// Stores the name of the filter list the rule was read from.
+    pub list: String,
+    /// Stores the rule which removed the search results.
+    pub rule: String,
+    /// Stores the number of search results removed by the rule.
+    pub count: u32,
+}
+
+impl FilteredResultsInfo {
+    /// Adds the removed search results to the count of their rule, adding the rule to the list
+    /// when it has not removed any search results yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `filtered_results` - It takes the counts of the removed search results of every rule as
+    ///   an argument.
+    /// * `list` - It takes the name of the filter list the rule was read from as an argument.
+    /// * `rule` - It takes the rule which removed the search results as an argument.
+    /// * `count` - It takes the number of removed search results as an argument.
+    pub fn record(filtered_results: &mut Vec<Self>, list: &str, rule: &str, count: u32) {
+        match filtered_results
+            .iter_mut()
+            .find(|info| info.list == list && info.rule == rule)
+        {
+            Some(info) => info.count += count,
+            None => filtered_results.push(Self {
+                list: list.to_owned(),
+                rule: rule.to_owned(),
+                count,
+            }),
+        }
+    }
+}
+
+/// A named struct to store how far the upstream search engines have been paged through for a
+/// search query, so that the pagination buffer can be extended with the next engine pages.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PaginationState {
+    /// Stores the number of pages fetched so far from the upstream search engines.
+    pub fetched_engine_pages: u32,
+    /// Stores the names of the upstream search engines which have run out of results or failed
+    /// and are no longer paged through.
+    pub exhausted_engines: Vec<String>,
+    /// Stores the counts of the search results removed by the filter lists from every engine
+    /// page along with the index in the buffer from which the engine page added its results, so
+    /// that every page only reports the results filtered from the engine pages it shows.
+    pub filtered_engine_pages: Vec<(usize, Vec<FilteredResultsInfo>)>,
 }
 
 impl SearchResults {
@@ -184,14 +334,26 @@ impl SearchResults {
     ///   the search url.
     /// * `engine_errors_info` - Takes an array of structs which contains information regarding
     ///   which engines failed with their names, reason and their severity color name.
-    pub fn new(results: Box<[SearchResult]>, engine_errors_info: Box<[EngineErrorInfo]>) -> Self {
+    /// * `related_searches` - Takes the deduplicated related searches collected from the
+    ///   upstream search engines.
+    pub fn new(
+        results: Box<[SearchResult]>,
+        engine_errors_info: Box<[EngineErrorInfo]>,
+        related_searches: Box<[String]>,
+    ) -> Self {
         Self {
             results,
             engine_errors_info,
+            related_searches,
             disallowed: Default::default(),
             filtered: Default::default(),
             safe_search_level: Default::default(),
             no_engines_selected: Default::default(),
+            has_next_page: Default::default(),
+            pagination: Default::default(),
+            filtered_results: Default::default(),
+            revealed: Default::default(),
+            cached_at: Default::default(),
         }
     }
 
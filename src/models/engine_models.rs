@@ -1,7 +1,7 @@
 //! This module provides the error enum to handle different errors associated while requesting data from
 //! the upstream search engines with the search query provided by the user.
 
-use super::aggregation_models::SearchResult;
+use super::aggregation_models::EngineResults;
 use error_stack::{Report, Result, ResultExt};
 use reqwest::Client;
 use std::fmt;
@@ -48,6 +48,36 @@ impl fmt::Display for EngineError {
 
 impl error_stack::Context for EngineError {}
 
+/// An enum type which provides the safe search modes natively supported by the upstream search
+/// engines, so that every safe search level is forwarded to all the engines in the same way.
+/// The engines which only support turning safe search on or off enable it for every mode except
+/// `Off`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SafeSearchMode {
+    /// The native safe search of the engine is turned off (level 0).
+    Off,
+    /// The native safe search of the engine only removes the explicit content (level 1).
+    Moderate,
+    /// The native safe search of the engine is as strict as possible (levels 2 to 4).
+    Strict,
+}
+
+impl SafeSearchMode {
+    /// Maps the safe search level selected by the user or the config file to the native safe
+    /// search mode of the upstream search engines.
+    ///
+    /// # Arguments
+    ///
+    /// * `safe_search` - It takes the safe search level (from 0 to 4) as an argument.
+    pub fn from_level(safe_search: u8) -> Self {
+        match safe_search {
+            0 => Self::Off,
+            1 => Self::Moderate,
+            _ => Self::Strict,
+        }
+    }
+}
+
 /// A trait to define common behavior for all search engines.
 #[async_trait::async_trait]
 pub trait SearchEngine: Sync + Send {
@@ -125,7 +155,8 @@ pub trait SearchEngine: Sync + Send {
     /// This function scrapes results from the upstream engine and puts all the scraped results like
     /// title, visiting_url (href in html),engine (from which engine it was fetched from) and description
     /// in a RawSearchResult and then adds that to HashMap whose keys are url and values are RawSearchResult
-    /// struct and then returns it within a Result enum.
+    /// struct and then returns it within a Result enum along with the related searches provided by the
+    /// upstream engine (if any).
     ///
     /// # Arguments
     ///
@@ -147,7 +178,7 @@ pub trait SearchEngine: Sync + Send {
         user_agent: &str,
         client: &Client,
         safe_search: u8,
-    ) -> Result<Vec<(String, SearchResult)>, EngineError>;
+    ) -> Result<EngineResults, EngineError>;
 }
 
 /// A named struct which stores the engine struct with the name of the associated engine.
@@ -219,6 +250,11 @@ impl EngineHandler {
         })
     }
 
+    /// A getter function that gets the name of the engine to which the struct is associated to.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
     /// This function converts the EngineHandler type into a tuple containing the engine name and
     /// the associated engine struct.
     pub fn into_name_engine(self) -> (&'static str, Box<dyn SearchEngine>) {
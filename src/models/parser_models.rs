@@ -1,6 +1,8 @@
 //! This module provides public models for handling, storing and serializing parsed config file
 //! options from config.lua by grouping them together.
 
+use std::collections::HashMap;
+
 /// A named struct which stores,deserializes, serializes and groups the parsed config file options
 /// of theme and colorscheme names into the Style struct which derives the `Clone`, `Serialize`
 /// and Deserialize traits where the `Clone` trait is derived for allowing the struct to be
@@ -44,6 +46,53 @@ pub struct AggregatorConfig {
     /// It stores the option to whether enable or disable random delays between
     /// requests.
     pub random_delay: bool,
+    /// It stores which pages around the requested page are prefetched in the background.
+    pub prefetch: PrefetchPolicy,
+}
+
+/// Configuration option for which pages around the requested page of the search results are
+/// prefetched in the background after the response has been sent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PrefetchPolicy {
+    /// No pages are prefetched.
+    Off,
+    /// Only the next page is prefetched.
+    #[default]
+    Next,
+    /// Both the previous and the next pages are prefetched.
+    Both,
+}
+
+impl PrefetchPolicy {
+    /// Parses the prefetch policy from its name in the config file.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - It takes the name of the policy (`off`, `next` or `both`) as an argument.
+    ///
+    /// # Returns
+    ///
+    /// Returns the parsed policy or `None` if the name is unknown.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "off" => Some(Self::Off),
+            "next" => Some(Self::Next),
+            "both" => Some(Self::Both),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration options for rewriting the search result urls to privacy respecting alternative
+/// front-ends.
+#[derive(Default)]
+pub struct PrivacyRedirects {
+    /// It stores the option to whether enable or disable the redirects by default (users can
+    /// still toggle them from the settings page).
+    pub enabled: bool,
+    /// It stores the hostnames (without subdomains) mapped to the hostname of the alternative
+    /// front-end they should be redirected to.
+    pub rules: HashMap<String, String>,
 }
 
 /// Configuration options for the rate limiter middleware.
@@ -53,3 +102,230 @@ pub struct RateLimiter {
     /// The time limit in which the quantity of requests that should be accepted.
     pub time_limit: u8,
 }
+
+/// The topology of the redis deployment used by the redis cache.
+#[cfg(feature = "redis-cache")]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum RedisTopology {
+    /// A single redis server.
+    #[default]
+    Standalone,
+    /// A primary server managed by sentinels, which is looked up again after a failover.
+    Sentinel {
+        /// The name under which the sentinels monitor the primary server.
+        master_name: String,
+    },
+    /// A redis cluster, whose nodes are discovered from the initial nodes.
+    Cluster,
+}
+
+/// Configuration options for connecting to the redis deployment used by the redis cache.
+#[cfg(feature = "redis-cache")]
+#[derive(Clone, Debug, Default)]
+pub struct RedisConfig {
+    /// The topology of the redis deployment.
+    pub topology: RedisTopology,
+    /// The urls of the redis server, of the sentinels or of the initial nodes of the cluster
+    /// depending on the topology.
+    pub urls: Vec<String>,
+    /// The PEM encoded certificate of the custom CA used to verify the TLS connections or `None`
+    /// if the system root certificates should be used.
+    pub ca_certificate: Option<Vec<u8>>,
+    /// The username used to authenticate to the redis servers.
+    pub username: Option<String>,
+    /// The password used to authenticate to the redis servers.
+    pub password: Option<String>,
+}
+
+#[cfg(feature = "redis-cache")]
+impl RedisConfig {
+    /// Constructs a new `RedisConfig` after validating the given options.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - It takes the name of the topology (`standalone`, `sentinel` or `cluster`) as an
+    ///   argument.
+    /// * `urls` - It takes the urls of the redis server, of the sentinels or of the initial nodes
+    ///   of the cluster as an argument.
+    /// * `sentinel_master` - It takes the name of the primary server monitored by the sentinels
+    ///   as an argument.
+    /// * `ca_certificate` - It takes the PEM encoded certificate of the custom CA as an argument.
+    /// * `username` - It takes the username used to authenticate as an argument.
+    /// * `password` - It takes the password used to authenticate as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the validated options on success otherwise returns an error message describing
+    /// the invalid option.
+    pub fn new(
+        mode: &str,
+        urls: Vec<String>,
+        sentinel_master: Option<String>,
+        ca_certificate: Option<Vec<u8>>,
+        username: Option<String>,
+        password: Option<String>,
+    ) -> Result<Self, String> {
+        let topology = match mode.trim().to_lowercase().as_str() {
+            "standalone" => RedisTopology::Standalone,
+            "sentinel" => RedisTopology::Sentinel {
+                master_name: sentinel_master
+                    .map(|name| name.trim().to_owned())
+                    .filter(|name| !name.is_empty())
+                    .ok_or("The `redis_sentinel_master` option is required in the `sentinel` mode")?,
+            },
+            "cluster" => RedisTopology::Cluster,
+            _ => {
+                return Err(
+                    "The value of `redis_mode` option should be one of `standalone`, `sentinel` or `cluster`".to_owned(),
+                )
+            }
+        };
+
+        let urls: Vec<String> = urls
+            .into_iter()
+            .map(|url| url.trim().to_owned())
+            .filter(|url| !url.is_empty())
+            .collect();
+        match (&topology, urls.len()) {
+            (_, 0) => return Err("At least one redis url should be provided".to_owned()),
+            (RedisTopology::Standalone, 2..) => {
+                return Err(
+                    "Only one redis url can be provided in the `standalone` mode".to_owned(),
+                )
+            }
+            _ => (),
+        }
+
+        let mut uses_tls = None;
+        for url in urls.iter() {
+            let tls = match redis::parse_redis_url(url).as_ref().map(|url| url.scheme()) {
+                Some("redis") => false,
+                Some("rediss") => true,
+                _ => return Err(format!("The redis url `{url}` is not a valid redis url")),
+            };
+            if uses_tls
+                .replace(tls)
+                .is_some_and(|previous| previous != tls)
+            {
+                return Err(
+                    "The redis urls should either all use TLS (`rediss://`) or none of them"
+                        .to_owned(),
+                );
+            }
+        }
+
+        if let Some(certificate) = ca_certificate.as_ref() {
+            if uses_tls != Some(true) {
+                return Err(
+                    "The `redis_tls_ca_file` option requires the redis urls to use TLS (`rediss://`)"
+                        .to_owned(),
+                );
+            }
+            if !String::from_utf8_lossy(certificate).contains("-----BEGIN CERTIFICATE-----") {
+                return Err(
+                    "The `redis_tls_ca_file` option should point to a PEM encoded certificate"
+                        .to_owned(),
+                );
+            }
+        }
+
+        if username.is_some() && password.is_none() {
+            return Err("The `redis_username` option requires a `redis_password_file`".to_owned());
+        }
+
+        Ok(RedisConfig {
+            topology,
+            urls,
+            ca_certificate,
+            username,
+            password,
+        })
+    }
+
+    /// Checks whether the connections to the redis servers are secured with TLS.
+    pub fn uses_tls(&self) -> bool {
+        self.urls.iter().any(|url| url.starts_with("rediss://"))
+    }
+}
+
+#[cfg(all(test, feature = "redis-cache"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redis_config_is_validated() {
+        let urls = |urls: &[&str]| urls.iter().map(|url| url.to_string()).collect::<Vec<_>>();
+
+        let config = RedisConfig::new(
+            "sentinel",
+            urls(&["redis://10.0.0.1:26379", "redis://10.0.0.2:26379"]),
+            Some("primary".to_owned()),
+            None,
+            None,
+            Some("secret".to_owned()),
+        )
+        .unwrap();
+        assert_eq!(
+            config.topology,
+            RedisTopology::Sentinel {
+                master_name: "primary".to_owned()
+            }
+        );
+
+        let certificate = b"-----BEGIN CERTIFICATE-----\n...\n-----END CERTIFICATE-----\n";
+        assert!(RedisConfig::new(
+            "cluster",
+            urls(&["rediss://10.0.0.1:6379"]),
+            None,
+            Some(certificate.to_vec()),
+            None,
+            None
+        )
+        .unwrap()
+        .uses_tls());
+
+        for (mode, nodes, sentinel_master, ca_certificate) in [
+            ("replicated", vec!["redis://10.0.0.1"], None, None),
+            ("sentinel", vec!["redis://10.0.0.1"], None, None),
+            (
+                "standalone",
+                vec!["redis://10.0.0.1", "redis://10.0.0.2"],
+                None,
+                None,
+            ),
+            ("cluster", vec![], None, None),
+            ("cluster", vec!["http://10.0.0.1"], None, None),
+            (
+                "cluster",
+                vec!["redis://10.0.0.1", "rediss://10.0.0.2"],
+                None,
+                None,
+            ),
+            (
+                "standalone",
+                vec!["redis://10.0.0.1"],
+                None,
+                Some(certificate.to_vec()),
+            ),
+            (
+                "standalone",
+                vec!["rediss://10.0.0.1"],
+                None,
+                Some(b"not a certificate".to_vec()),
+            ),
+        ] {
+            assert!(
+                RedisConfig::new(
+                    mode,
+                    urls(&nodes),
+                    sentinel_master,
+                    ca_certificate,
+                    None,
+                    None
+                )
+                .is_err(),
+                "the `{mode}` mode with {nodes:?} should be rejected"
+            );
+        }
+    }
+}
@@ -1,10 +1,67 @@
 //! This module provides the models to parse cookies and search parameters from the search
 //! engine website.
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs,
+    sync::OnceLock,
+};
 
-use serde::Deserialize;
+use actix_web::{web, HttpRequest};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 use super::parser_models::Style;
+use crate::{
+    config::parser::{Config, MAX_RESULTS_PER_PAGE},
+    handler::{file_path, FileType},
+    results::domain_filter::is_supported_rule,
+};
+
+/// The name of the cookie which stores the user's preferences.
+pub const COOKIE_NAME: &str = "appCookie";
+
+/// The current version of the schema of the preferences cookie. It must be incremented whenever
+/// the schema changes in a way that needs the older cookies to be migrated in `migrate_cookie`.
+pub const COOKIE_VERSION: u8 = 1;
+
+/// The maximum number of sites a user can hide from the search results, which keeps the cookie
+/// within the size limits of the browsers.
+pub const MAX_BLOCKED_DOMAINS: usize = 64;
+
+/// The name of the url parameter which carries the preferences token.
+pub const PREFERENCES_TOKEN_PARAM: &str = "preferences";
+
+/// The version of the preferences token format, which is prefixed to every token so that the
+/// format can be changed without misreading older tokens.
+const PREFERENCES_TOKEN_VERSION: &str = "1";
+
+/// The style types whose stylesheets are provided by the server.
+const STYLE_TYPES: [&str; 3] = ["themes", "colorschemes", "animations"];
+
+/// A static holding the names of the stylesheets provided by the server for every style type, or
+/// `None` if the public folder could not be found.
+static AVAILABLE_STYLES: OnceLock<Option<HashMap<&'static str, HashSet<String>>>> = OnceLock::new();
+
+/// The context string used to derive the key which signs the preferences tokens from the
+/// configured secret.
+const PREFERENCES_TOKEN_KEY_CONTEXT: &str = "websurfx 2024 preferences token signing key";
+
+/// The compact representation of the preferences stored in the preferences token, which omits the
+/// field names of the cookie. The fields added later are defaulted, so that the tokens created
+/// before they were added can still be read.
+#[derive(Deserialize)]
+struct PreferencesTokenPayload<'a>(
+    Cow<'a, str>,
+    Cow<'a, str>,
+    Option<Cow<'a, str>>,
+    Cow<'a, [Cow<'a, str>]>,
+    u8,
+    Option<bool>,
+    #[serde(default)] Option<u8>,
+    #[serde(default)] Cow<'a, [Cow<'a, str>]>,
+);
 
 /// A named struct which deserializes all the user provided search parameters and stores them.
 #[derive(Deserialize)]
@@ -18,31 +75,744 @@ pub struct SearchParams {
     /// It stores the search parameter `safesearch` (or safe search level in simple words) of the
     /// search url.
     pub safesearch: Option<u8>,
+    /// It stores the search parameter `reveal` which reveals the search results removed by the
+    /// filter lists when the server allows it.
+    pub reveal: Option<bool>,
+}
+
+/// A named struct which deserializes the fields submitted by the "hide this site" button of a
+/// search result and stores them.
+#[derive(Deserialize)]
+pub struct BlockSiteParams {
+    /// It stores the domain of the site to hide from the search results.
+    pub domain: String,
+    /// It stores the search query of the page the site was hidden from.
+    pub q: String,
+    /// It stores the page number of the page the site was hidden from.
+    pub page: Option<u32>,
+    /// It stores the safe search level of the page the site was hidden from.
+    pub safesearch: Option<u8>,
+    /// It stores the preferences token of the page the site was hidden from or `None` if the
+    /// preferences are read from the cookie.
+    pub preferences: Option<String>,
 }
 
-/// A named struct which is used to deserialize the cookies fetched from the client side.
-#[allow(dead_code)]
+/// A named struct which deserializes the parameters of the request purging the cache and stores
+/// them.
 #[derive(Deserialize)]
+pub struct PurgeParams {
+    /// It stores the search query whose cached results should be removed or `None` if all the
+    /// cached results should be removed.
+    pub q: Option<String>,
+}
+
+/// A named struct which stores the user's preferences read from the cookies fetched from the
+/// client side and which is serialized to save the preferences from the settings page.
+#[derive(Serialize, Clone)]
 pub struct Cookie<'a> {
+    /// It stores the version of the schema of the cookie. Cookies saved before the schema was
+    /// versioned are treated as version 0.
+    pub version: u8,
     /// It stores the theme name used in the website.
     pub theme: Cow<'a, str>,
     /// It stores the colorscheme name used for the website theme.
     pub colorscheme: Cow<'a, str>,
+    /// It stores the animation name used for the website theme or `None` if no animation should
+    /// be used.
+    pub animation: Option<Cow<'a, str>>,
     /// It stores the user selected upstream search engines selected from the UI.
     pub engines: Cow<'a, [Cow<'a, str>]>,
     /// It stores the user selected safe search level from the UI.
     pub safe_search_level: u8,
+    /// It stores whether the user enabled the privacy redirects of the result urls from the UI
+    /// or `None` if the server default should be used.
+    pub privacy_redirects: Option<bool>,
+    /// It stores the number of search results shown on a page selected from the UI or `None` if
+    /// the server default should be used.
+    pub results_per_page: Option<u8>,
+    /// It stores the domain filter rules of the sites the user has hidden from the search
+    /// results.
+    pub blocked_domains: Cow<'a, [Cow<'a, str>]>,
+    /// It stores the names of the settings which were rejected while reading the cookie and
+    /// replaced with the server defaults, so that a notice can be shown to the user.
+    #[serde(skip)]
+    pub rejected_fields: Vec<&'static str>,
 }
 
 impl<'a> Cookie<'a> {
     /// server_models::Cookie contructor function
-    pub fn build(style: &'a Style, mut engines: Vec<Cow<'a, str>>, safe_search_level: u8) -> Self {
+    pub fn build(
+        style: &'a Style,
+        mut engines: Vec<Cow<'a, str>>,
+        safe_search_level: u8,
+        privacy_redirects: bool,
+        results_per_page: u8,
+    ) -> Self {
         engines.sort();
         Self {
+            version: COOKIE_VERSION,
             theme: Cow::Borrowed(&style.theme),
             colorscheme: Cow::Borrowed(&style.colorscheme),
+            animation: style.animation.as_deref().map(Cow::Borrowed),
             engines: Cow::Owned(engines),
             safe_search_level,
+            privacy_redirects: Some(privacy_redirects),
+            results_per_page: Some(results_per_page),
+            blocked_domains: Cow::Owned(Vec::new()),
+            rejected_fields: Vec::new(),
+        }
+    }
+
+    /// Builds the preferences from the server's config, which are used when the user has not
+    /// saved any preferences.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config as an argument.
+    pub fn from_config(config: &'a Config) -> Self {
+        Self::build(
+            &config.style,
+            config
+                .upstream_search_engines
+                .iter()
+                .filter_map(|(engine, enabled)| enabled.then_some(Cow::Borrowed(engine.as_str())))
+                .collect(),
+            config.safe_search,
+            config.privacy_redirects.enabled,
+            config.results_per_page,
+        )
+    }
+
+    /// Reads the user's preferences from the preferences token in the url of the request or
+    /// otherwise from the cookie sent with the request, so that they can be honored server-side
+    /// without any javascript. The preferences from the server's config are used if neither is
+    /// present.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - It takes the request sent by the user as an argument.
+    /// * `config` - It takes the parsed config as an argument.
+    pub fn from_request(req: &HttpRequest, config: &'a Config) -> Self {
+        if let Some(preferences) = preferences_token_param(req)
+            .and_then(|token| Self::from_token(&token, config.preferences_token_secret.as_deref()))
+        {
+            return preferences.validate(config);
+        }
+
+        match req.cookie(COOKIE_NAME) {
+            Some(cookie) => Self::from_cookie(cookie.value(), config),
+            None => Self::from_config(config),
         }
     }
+
+    /// Reads the preferences from the value of the preferences cookie. The cookies saved with an
+    /// older version of the schema are migrated forward and every field is read on its own, so
+    /// that a missing or invalid field only falls back to the server's config for that field
+    /// instead of resetting all the preferences. The rejected fields are recorded in
+    /// `rejected_fields`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - It takes the json value of the cookie as an argument.
+    /// * `config` - It takes the parsed config as an argument.
+    pub fn from_cookie(value: &str, config: &'a Config) -> Self {
+        let mut defaults = Self::from_config(config);
+
+        let Ok(Value::Object(mut fields)) = serde_json::from_str::<Value>(value) else {
+            defaults.rejected_fields.push("all settings");
+            return defaults;
+        };
+
+        migrate_cookie(&mut fields);
+
+        let mut rejected_fields = Vec::new();
+
+        let theme: Option<String> = required_field(&mut fields, "theme", &mut rejected_fields);
+        let colorscheme: Option<String> =
+            required_field(&mut fields, "colorscheme", &mut rejected_fields);
+        let engines: Option<Vec<String>> =
+            required_field(&mut fields, "engines", &mut rejected_fields);
+        let safe_search_level: Option<u8> =
+            required_field(&mut fields, "safe_search_level", &mut rejected_fields);
+        let animation: Result<Option<String>, ()> =
+            optional_field(&mut fields, "animation", &mut rejected_fields);
+        let privacy_redirects: Result<Option<bool>, ()> =
+            optional_field(&mut fields, "privacy_redirects", &mut rejected_fields);
+        let results_per_page: Result<Option<u8>, ()> =
+            optional_field(&mut fields, "results_per_page", &mut rejected_fields);
+        let blocked_domains: Result<Option<Vec<String>>, ()> =
+            optional_field(&mut fields, "blocked_domains", &mut rejected_fields);
+
+        Self {
+            version: COOKIE_VERSION,
+            theme: theme.map_or(defaults.theme, Cow::Owned),
+            colorscheme: colorscheme.map_or(defaults.colorscheme, Cow::Owned),
+            animation: animation.map_or(defaults.animation, |animation| animation.map(Cow::Owned)),
+            engines: engines.map_or(defaults.engines, |engines| {
+                Cow::Owned(engines.into_iter().map(Cow::Owned).collect())
+            }),
+            safe_search_level: safe_search_level.unwrap_or(defaults.safe_search_level),
+            privacy_redirects: privacy_redirects.unwrap_or(defaults.privacy_redirects),
+            results_per_page: results_per_page.unwrap_or(defaults.results_per_page),
+            blocked_domains: match blocked_domains {
+                Ok(Some(blocked_domains)) => {
+                    Cow::Owned(blocked_domains.into_iter().map(Cow::Owned).collect())
+                }
+                _ => defaults.blocked_domains,
+            },
+            rejected_fields,
+        }
+        .validate(config)
+    }
+
+    /// Validates the preferences against the styles and engines the server actually provides.
+    /// The theme, colorscheme and animation without a matching stylesheet, the unknown engines
+    /// and an out of range safe search level or number of results per page are replaced with the
+    /// server defaults and recorded in `rejected_fields`. The engine names are normalized to the
+    /// names used in the config and the hidden sites are normalized to lowercase, dropping the
+    /// unsupported rules and the ones beyond `MAX_BLOCKED_DOMAINS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config as an argument.
+    pub fn validate(mut self, config: &'a Config) -> Self {
+        if !is_available_style("themes", &self.theme) {
+            self.theme = Cow::Borrowed(&config.style.theme);
+            self.rejected_fields.push("theme");
+        }
+        if !is_available_style("colorschemes", &self.colorscheme) {
+            self.colorscheme = Cow::Borrowed(&config.style.colorscheme);
+            self.rejected_fields.push("colorscheme");
+        }
+        if self
+            .animation
+            .as_deref()
+            .is_some_and(|animation| !is_available_style("animations", animation))
+        {
+            self.animation = config.style.animation.as_deref().map(Cow::Borrowed);
+            self.rejected_fields.push("animation");
+        }
+
+        let mut engines: Vec<Cow<'a, str>> = Vec::with_capacity(self.engines.len());
+        for engine in self.engines.iter() {
+            match config
+                .upstream_search_engines
+                .keys()
+                .find(|name| name.eq_ignore_ascii_case(engine.trim()))
+            {
+                Some(name) => engines.push(Cow::Borrowed(name)),
+                None if !self.rejected_fields.contains(&"engines") => {
+                    self.rejected_fields.push("engines")
+                }
+                None => (),
+            }
+        }
+        engines.sort();
+        engines.dedup();
+        self.engines = Cow::Owned(engines);
+
+        if self.safe_search_level > 4 {
+            self.safe_search_level = config.safe_search;
+            self.rejected_fields.push("safe_search_level");
+        }
+
+        if self
+            .results_per_page
+            .is_some_and(|results_per_page| !(1..=MAX_RESULTS_PER_PAGE).contains(&results_per_page))
+        {
+            self.results_per_page = Some(config.results_per_page);
+            self.rejected_fields.push("results_per_page");
+        }
+
+        let mut blocked_domains: Vec<Cow<'a, str>> = Vec::with_capacity(self.blocked_domains.len());
+        for rule in self.blocked_domains.iter() {
+            let rule = rule.trim().to_lowercase();
+            if !is_supported_rule(&rule) || blocked_domains.len() == MAX_BLOCKED_DOMAINS {
+                if !self.rejected_fields.contains(&"blocked_domains") {
+                    self.rejected_fields.push("blocked_domains");
+                }
+            } else if !blocked_domains.iter().any(|blocked| *blocked == rule) {
+                blocked_domains.push(Cow::Owned(rule));
+            }
+        }
+        self.blocked_domains = Cow::Owned(blocked_domains);
+
+        self
+    }
+
+    /// Builds the preferences from the fields submitted with the settings page form. Unknown
+    /// engines and unavailable styles are ignored in favour of the server's config.
+    ///
+    /// # Arguments
+    ///
+    /// * `form` - It takes the submitted form fields as a list of name and value pairs as an
+    ///   argument.
+    /// * `config` - It takes the parsed config as an argument.
+    pub fn from_settings_form(form: &'a [(String, String)], config: &'a Config) -> Self {
+        let field = |name: &str| {
+            form.iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.trim())
+        };
+
+        let mut preferences = Self {
+            version: COOKIE_VERSION,
+            theme: Cow::Borrowed(field("themes").unwrap_or(&config.style.theme)),
+            colorscheme: Cow::Borrowed(field("colorschemes").unwrap_or(&config.style.colorscheme)),
+            animation: match field("animations") {
+                Some("") => None,
+                Some(animation) => Some(Cow::Borrowed(animation)),
+                None => config.style.animation.as_deref().map(Cow::Borrowed),
+            },
+            engines: Cow::Owned(
+                form.iter()
+                    .filter(|(key, _)| key == "engines")
+                    .map(|(_, engine)| Cow::Borrowed(engine.as_str()))
+                    .collect(),
+            ),
+            safe_search_level: field("safe_search_levels")
+                .and_then(|level| level.parse::<u8>().ok())
+                .filter(|level| *level <= 2)
+                .unwrap_or(config.safe_search),
+            privacy_redirects: Some(field("privacy_redirects").is_some()),
+            results_per_page: field("results_per_page")
+                .and_then(|results_per_page| results_per_page.parse::<u8>().ok())
+                .or(Some(config.results_per_page)),
+            // The hidden sites which are unchecked in the table are left out of the form.
+            blocked_domains: Cow::Owned(
+                form.iter()
+                    .filter(|(key, _)| key == "blocked_domains")
+                    .map(|(_, rule)| rule.as_str())
+                    .chain(field("blocked_domains_add").into_iter().flat_map(|rules| {
+                        rules.split(|character: char| character == ',' || character.is_whitespace())
+                    }))
+                    .filter(|rule| !rule.trim().is_empty())
+                    .map(Cow::Borrowed)
+                    .collect(),
+            ),
+            rejected_fields: Vec::new(),
+        }
+        .validate(config);
+
+        // The form only submits the fields which are shown, so nothing needs to be reported.
+        preferences.rejected_fields.clear();
+        preferences
+    }
+
+    /// Encodes the preferences into a compact token which can be bookmarked or put in the search
+    /// url. The token has the form `<version>.<payload>[.<signature>]`, where the payload is the
+    /// url safe base64 encoded json of the preferences and the signature is only added when a
+    /// secret is configured.
+    ///
+    /// # Arguments
+    ///
+    /// * `secret` - It takes the secret used to sign the token as an argument or `None` if the
+    ///   token should not be signed.
+    ///
+    /// # Error
+    ///
+    /// Returns the encoded token on success otherwise returns a serialization error.
+    pub fn to_token(&self, secret: Option<&str>) -> Result<String, serde_json::Error> {
+        let payload = serde_json::to_vec(&(
+            &self.theme,
+            &self.colorscheme,
+            &self.animation,
+            &self.engines,
+            self.safe_search_level,
+            self.privacy_redirects,
+            self.results_per_page,
+            &self.blocked_domains,
+        ))?;
+
+        let mut token = format!(
+            "{PREFERENCES_TOKEN_VERSION}.{}",
+            URL_SAFE_NO_PAD.encode(payload)
+        );
+        if let Some(secret) = secret {
+            let signature = sign_preferences_token(&token, secret);
+            token = format!("{token}.{}", URL_SAFE_NO_PAD.encode(signature.as_bytes()));
+        }
+
+        Ok(token)
+    }
+
+    /// Decodes the preferences from a token created by `to_token`.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - It takes the preferences token as an argument.
+    /// * `secret` - It takes the secret used to sign the tokens as an argument or `None` if the
+    ///   tokens are not signed. When a secret is provided, tokens without a valid signature are
+    ///   rejected.
+    ///
+    /// # Returns
+    ///
+    /// Returns the decoded preferences or `None` if the token is malformed, has an unknown
+    /// version or an invalid signature.
+    pub fn from_token(token: &str, secret: Option<&str>) -> Option<Self> {
+        let mut parts = token.trim().splitn(3, '.');
+        let (version, payload, signature) = (parts.next()?, parts.next()?, parts.next());
+
+        if version != PREFERENCES_TOKEN_VERSION {
+            return None;
+        }
+
+        if let Some(secret) = secret {
+            let signature: [u8; blake3::OUT_LEN] =
+                URL_SAFE_NO_PAD.decode(signature?).ok()?.try_into().ok()?;
+            let signed_part = &token.trim()[..version.len() + 1 + payload.len()];
+            // `blake3::Hash` compares in constant time.
+            if blake3::Hash::from(signature) != sign_preferences_token(signed_part, secret) {
+                return None;
+            }
+        }
+
+        let PreferencesTokenPayload(
+            theme,
+            colorscheme,
+            animation,
+            engines,
+            safe_search_level,
+            privacy_redirects,
+            results_per_page,
+            blocked_domains,
+        ) = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+
+        Some(Self {
+            version: COOKIE_VERSION,
+            theme,
+            colorscheme,
+            animation,
+            engines,
+            safe_search_level,
+            privacy_redirects,
+            results_per_page,
+            blocked_domains,
+            rejected_fields: Vec::new(),
+        })
+    }
+}
+
+/// Returns the preferences token from the url of the request if it is present and can be decoded.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request sent by the user as an argument.
+/// * `config` - It takes the parsed config as an argument.
+pub fn preferences_token(req: &HttpRequest, config: &Config) -> Option<String> {
+    preferences_token_param(req).filter(|token| {
+        Cookie::from_token(token, config.preferences_token_secret.as_deref()).is_some()
+    })
+}
+
+/// Returns the raw value of the preferences token url parameter of the request.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request sent by the user as an argument.
+fn preferences_token_param(req: &HttpRequest) -> Option<String> {
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()?
+        .into_inner()
+        .remove(PREFERENCES_TOKEN_PARAM)
+}
+
+/// Signs the preferences token with a keyed hash derived from the configured secret.
+///
+/// # Arguments
+///
+/// * `token` - It takes the unsigned part of the preferences token as an argument.
+/// * `secret` - It takes the configured secret as an argument.
+///
+/// # Returns
+///
+/// Returns the keyed hash of the token.
+fn sign_preferences_token(token: &str, secret: &str) -> blake3::Hash {
+    let key = blake3::derive_key(PREFERENCES_TOKEN_KEY_CONTEXT, secret.as_bytes());
+    blake3::keyed_hash(&key, token.as_bytes())
+}
+
+/// Migrates the fields of a preferences cookie saved with an older version of the schema to the
+/// current version.
+///
+/// # Arguments
+///
+/// * `fields` - It takes the fields of the cookie as an argument.
+fn migrate_cookie(fields: &mut Map<String, Value>) {
+    let version = fields.get("version").and_then(Value::as_u64).unwrap_or(0);
+
+    if version < 1 {
+        // The version 0 cookies were written by the javascript of the settings page, which saved
+        // the engine names as displayed on the page (these are normalized by `Cookie::validate`)
+        // and `""` when no animation was selected.
+        if fields.get("animation").and_then(Value::as_str) == Some("") {
+            fields.insert("animation".to_owned(), Value::Null);
+        }
+    }
+
+    fields.insert("version".to_owned(), Value::from(COOKIE_VERSION));
+}
+
+/// Removes the field from the fields of the cookie and deserializes it.
+///
+/// # Arguments
+///
+/// * `fields` - It takes the fields of the cookie as an argument.
+/// * `name` - It takes the name of the field as an argument.
+///
+/// # Error
+///
+/// Returns the deserialized field or `None` if it is missing or `null` on success otherwise
+/// returns a deserialization error if the field has an unexpected type.
+fn take_field<T: DeserializeOwned>(
+    fields: &mut Map<String, Value>,
+    name: &str,
+) -> Result<Option<T>, serde_json::Error> {
+    match fields.remove(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value).map(Some),
+    }
+}
+
+/// Reads a required field of the cookie and records it as rejected if it is missing or invalid.
+///
+/// # Arguments
+///
+/// * `fields` - It takes the fields of the cookie as an argument.
+/// * `name` - It takes the name of the field as an argument.
+/// * `rejected_fields` - It takes the list of the rejected fields as an argument.
+fn required_field<T: DeserializeOwned>(
+    fields: &mut Map<String, Value>,
+    name: &'static str,
+    rejected_fields: &mut Vec<&'static str>,
+) -> Option<T> {
+    let value = take_field(fields, name).ok().flatten();
+    if value.is_none() {
+        rejected_fields.push(name);
+    }
+    value
+}
+
+/// Reads an optional field of the cookie and records it as rejected if it is invalid.
+///
+/// # Arguments
+///
+/// * `fields` - It takes the fields of the cookie as an argument.
+/// * `name` - It takes the name of the field as an argument.
+/// * `rejected_fields` - It takes the list of the rejected fields as an argument.
+///
+/// # Error
+///
+/// Returns the field or `None` if it is missing on success otherwise returns an error if the
+/// field is invalid.
+fn optional_field<T: DeserializeOwned>(
+    fields: &mut Map<String, Value>,
+    name: &'static str,
+    rejected_fields: &mut Vec<&'static str>,
+) -> Result<Option<T>, ()> {
+    take_field(fields, name).map_err(|_| rejected_fields.push(name))
+}
+
+/// Scans the stylesheets provided by the server, so that the preferences of the users are checked
+/// against them without touching the file system on every request. It does nothing if the
+/// stylesheets have already been scanned.
+pub fn init_available_styles() {
+    AVAILABLE_STYLES.get_or_init(scan_available_styles);
+}
+
+/// A helper function which lists the names of the stylesheets in the folder of every style type.
+///
+/// # Returns
+///
+/// Returns the names of the stylesheets mapped to their style type or `None` if the public folder
+/// could not be found.
+fn scan_available_styles() -> Option<HashMap<&'static str, HashSet<String>>> {
+    let public_folder_path = file_path(FileType::Theme).ok()?;
+
+    Some(
+        STYLE_TYPES
+            .into_iter()
+            .map(|style_type| {
+                let names = fs::read_dir(format!("{public_folder_path}static/{style_type}/"))
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|file| {
+                        let file_name = file.ok()?.file_name().into_string().ok()?;
+                        file_name.strip_suffix(".css").map(str::to_owned)
+                    })
+                    .collect();
+                (style_type, names)
+            })
+            .collect(),
+    )
+}
+
+/// Checks whether the server provides the stylesheet of the theme, colorscheme or animation. The
+/// name must look like a valid file name, so that arbitrary values from the cookie are never used
+/// to build the stylesheet links.
+///
+/// # Arguments
+///
+/// * `style_type` - It takes the style type of the values `themes`, `colorschemes` and
+///   `animations` as an argument.
+/// * `name` - It takes the style name as an argument.
+fn is_available_style(style_type: &str, name: &str) -> bool {
+    let is_valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || matches!(character, '-' | '_'));
+
+    is_valid_name
+        && AVAILABLE_STYLES
+            .get_or_init(scan_available_styles)
+            .as_ref()
+            .is_none_or(|available_styles| {
+                available_styles
+                    .get(style_type)
+                    .is_some_and(|names| names.contains(name))
+            })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+
+    /// A helper function which returns the parsed config shared by the tests.
+    fn config() -> &'static Config {
+        static CONFIG: OnceLock<Config> = OnceLock::new();
+        CONFIG.get_or_init(|| Config::parse(true).unwrap())
+    }
+
+    /// A helper function which returns the preferences used in the tests.
+    fn preferences() -> Cookie<'static> {
+        Cookie {
+            version: COOKIE_VERSION,
+            theme: Cow::Borrowed("simple"),
+            colorscheme: Cow::Borrowed("monokai"),
+            animation: None,
+            engines: Cow::Owned(vec![Cow::Borrowed("bing"), Cow::Borrowed("brave")]),
+            safe_search_level: 2,
+            privacy_redirects: Some(true),
+            results_per_page: Some(20),
+            blocked_domains: Cow::Owned(vec![Cow::Borrowed("spam.example")]),
+            rejected_fields: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_preferences_token_round_trip() {
+        let token = preferences().to_token(None).unwrap();
+        assert!(token.starts_with("1."));
+
+        let decoded = Cookie::from_token(&token, None).unwrap();
+        assert_eq!(decoded.theme, "simple");
+        assert_eq!(decoded.colorscheme, "monokai");
+        assert_eq!(decoded.animation, None);
+        assert_eq!(decoded.engines.as_ref(), ["bing", "brave"]);
+        assert_eq!(decoded.safe_search_level, 2);
+        assert_eq!(decoded.privacy_redirects, Some(true));
+        assert_eq!(decoded.results_per_page, Some(20));
+        assert_eq!(decoded.blocked_domains.as_ref(), ["spam.example"]);
+
+        // The tokens created before the number of results per page was added are still read.
+        let older_token = format!(
+            "1.{}",
+            URL_SAFE_NO_PAD.encode(r#"["simple","monokai",null,["bing"],2,true]"#)
+        );
+        let decoded = Cookie::from_token(&older_token, None).unwrap();
+        assert_eq!(decoded.safe_search_level, 2);
+        assert_eq!(decoded.results_per_page, None);
+        assert!(decoded.blocked_domains.is_empty());
+
+        assert!(Cookie::from_token(&token.replacen('1', "2", 1), None).is_none());
+        assert!(Cookie::from_token("1.not-base64!", None).is_none());
+    }
+
+    #[test]
+    fn test_signed_preferences_token() {
+        let token = preferences().to_token(Some("secret")).unwrap();
+        assert!(Cookie::from_token(&token, Some("secret")).is_some());
+        assert!(Cookie::from_token(&token, Some("another secret")).is_none());
+
+        // Unsigned or tampered tokens are rejected once a secret is configured.
+        let unsigned_token = preferences().to_token(None).unwrap();
+        assert!(Cookie::from_token(&unsigned_token, Some("secret")).is_none());
+        let mut tampered_preferences = preferences();
+        tampered_preferences.safe_search_level = 0;
+        let tampered_payload = tampered_preferences.to_token(None).unwrap();
+        let signature = token.rsplit('.').next().unwrap();
+        assert!(
+            Cookie::from_token(&format!("{tampered_payload}.{signature}"), Some("secret"))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_legacy_cookie_is_migrated() {
+        let cookie = Cookie::from_cookie(
+            r#"{"theme":"simple","colorscheme":"monokai","animation":"","engines":["Duckduckgo","Bing"],"safe_search_level":1}"#,
+            config(),
+        );
+
+        assert_eq!(cookie.version, COOKIE_VERSION);
+        assert!(cookie.rejected_fields.is_empty());
+        assert_eq!(cookie.theme, "simple");
+        assert_eq!(cookie.colorscheme, "monokai");
+        assert_eq!(cookie.animation, None);
+        assert_eq!(cookie.engines.as_ref(), ["Bing", "DuckDuckGo"]);
+        assert_eq!(cookie.safe_search_level, 1);
+        assert_eq!(cookie.privacy_redirects, None);
+    }
+
+    #[test]
+    fn test_invalid_cookie_fields_are_rejected() {
+        let cookie = Cookie::from_cookie(
+            r#"{"version":1,"theme":"../../../etc/passwd","colorscheme":"no-such-colorscheme","engines":["bing","google"],"safe_search_level":"high","privacy_redirects":true}"#,
+            config(),
+        );
+
+        assert_eq!(
+            cookie.rejected_fields,
+            ["safe_search_level", "theme", "colorscheme", "engines"]
+        );
+        assert_eq!(cookie.theme, config().style.theme);
+        assert_eq!(cookie.colorscheme, config().style.colorscheme);
+        assert_eq!(cookie.engines.as_ref(), ["Bing"]);
+        assert_eq!(cookie.safe_search_level, config().safe_search);
+        // The valid fields are kept.
+        assert_eq!(cookie.privacy_redirects, Some(true));
+
+        let cookie = Cookie::from_cookie("not json", config());
+        assert_eq!(cookie.rejected_fields, ["all settings"]);
+    }
+
+    #[test]
+    fn test_blocked_domains_are_normalized() {
+        let cookie = Cookie::from_cookie(
+            r#"{"version":1,"theme":"simple","colorscheme":"monokai","engines":["bing"],"safe_search_level":1,"blocked_domains":["Spam.Example"," spam.example","not a domain","*.ads.example"]}"#,
+            config(),
+        );
+        assert_eq!(cookie.rejected_fields, ["blocked_domains"]);
+        assert_eq!(
+            cookie.blocked_domains.as_ref(),
+            ["spam.example", "*.ads.example"]
+        );
+
+        let form: Vec<(String, String)> = [
+            ("blocked_domains", "spam.example"),
+            (
+                "blocked_domains_add",
+                "ads.example, tracker.example\nspam.example",
+            ),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        .collect();
+        let preferences = Cookie::from_settings_form(&form, config());
+        assert_eq!(
+            preferences.blocked_domains.as_ref(),
+            ["spam.example", "ads.example", "tracker.example"]
+        );
+    }
 }
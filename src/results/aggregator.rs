@@ -1,79 +1,65 @@
 //! This module provides the functionality to scrape and gathers all the results from the upstream
 //! search engines and then removes duplicate results.
 
-use super::user_agent::random_user_agent;
+use super::{
+    engine_health::record_engine_result,
+    filter_lists::{FilterList, FilterLists},
+    url_cleaner::clean_url,
+    user_agent::random_user_agent,
+};
 use crate::config::parser::Config;
-use crate::handler::{file_path, FileType};
 use crate::models::{
-    aggregation_models::{EngineErrorInfo, SearchResult, SearchResults},
+    aggregation_models::{
+        EngineErrorInfo, EngineResults, FilteredResultsInfo, SearchResult, SearchResults,
+    },
     engine_models::{EngineError, EngineHandler},
 };
 
 use error_stack::Report;
 use futures::stream::FuturesUnordered;
-use regex::Regex;
-use reqwest::{Client, ClientBuilder};
-use std::sync::Arc;
-use tokio::{
-    fs::File,
-    io::{AsyncBufReadExt, BufReader},
-    task::JoinHandle,
-    time::Duration,
-};
+use reqwest::{Client, ClientBuilder, Url};
+use std::{collections::HashMap, sync::Arc};
+use tokio::{task::JoinHandle, time::Duration};
 
 /// A constant for holding the prebuilt Client globally in the app.
 static CLIENT: std::sync::OnceLock<Client> = std::sync::OnceLock::new();
 
+/// A constant holding the maximum number of related searches shown under the search results.
+const MAX_RELATED_SEARCHES: usize = 8;
+
 /// Aliases for long type annotations
 
-type FutureVec =
-    FuturesUnordered<JoinHandle<Result<Vec<(String, SearchResult)>, Report<EngineError>>>>;
-
-/// The function aggregates the scraped results from the user-selected upstream search engines.
-/// These engines can be chosen either from the user interface (UI) or from the configuration file.
-/// The code handles this process by matching the selected search engines and adding them to a vector.
-/// This vector is then used to create an asynchronous task vector using `tokio::spawn`, which returns
-/// a future. This future is awaited in another loop. Once the results are collected, they are filtered
-/// to remove any errors and ensure only proper results are included. If an error is encountered, it is
-/// sent to the UI along with the name of the engine and the type of error. This information is finally
-/// placed in the returned `SearchResults` struct.
-///
-/// Additionally, the function eliminates duplicate results. If two results are identified as coming from
-/// multiple engines, their names are combined to indicate that the results were fetched from these upstream
-/// engines. After this, all the data in the `Vec` is removed and placed into a struct that contains all
-/// the aggregated results in a vector. Furthermore, the query used is also added to the struct. This step is
-/// necessary to ensure that the search bar in the search remains populated even when searched from the query URL.
-///
-/// Overall, this function serves to aggregate scraped results from user-selected search engines, handling errors,
-/// removing duplicates, and organizing the data for display in the UI.
-///
-/// # Example:
-///
-/// If you search from the url like `https://127.0.0.1/search?q=huston` then the search bar should
-/// contain the word huston and not remain empty.
+type FutureVec = FuturesUnordered<JoinHandle<Result<EngineResults, Report<EngineError>>>>;
+
+/// The raw results (or the error) of every requested upstream search engine along with the name
+/// of the engine, in the order in which the engines were selected.
+pub type EngineResponses = Vec<(&'static str, Result<EngineResults, Report<EngineError>>)>;
+
+/// The function fetches the raw results of the given page from the user-selected upstream search
+/// engines. These engines can be chosen either from the user interface (UI) or from the
+/// configuration file. An asynchronous task is spawned with `tokio::spawn` for every engine and
+/// the tasks are awaited in another loop, so that all the engines are requested concurrently. The
+/// outcome of every request is recorded to keep track of the health of the engines.
 ///
 /// # Arguments
 ///
 /// * `query` - Accepts a string to query with the above upstream search engines.
 /// * `page` - Accepts an u32 page number.
-/// * `random_delay` - Accepts a boolean value to add a random delay before making the request.
-/// * `debug` - Accepts a boolean value to enable or disable debug mode option.
+/// * `config` - Accepts the parsed config to build the shared http client.
 /// * `upstream_search_engines` - Accepts a vector of search engine names which was selected by the
-/// * `request_timeout` - Accepts a time (secs) as a value which controls the server request timeout.
 ///   user through the UI or the config file.
+/// * `safe_search` - Accepts the safe search level which is forwarded to the engines.
 ///
-/// # Error
+/// # Returns
 ///
-/// Returns an error a reqwest and scraping selector errors if any error occurs in the results
-/// function in either `searx` or `duckduckgo` or both otherwise returns a `SearchResults struct`
-/// containing appropriate values.
-pub async fn aggregate(
+/// Returns the raw results or the error of every engine whose task did not panic.
+pub async fn fetch_engine_results(
     query: &str,
     page: u32,
     config: &Config,
     upstream_search_engines: &[EngineHandler],
     safe_search: u8,
-) -> Result<SearchResults, Box<dyn std::error::Error>> {
+) -> EngineResponses {
     let client = CLIENT.get_or_init(|| {
         let mut cb = ClientBuilder::new()
             .timeout(Duration::from_secs(config.request_timeout as u64)) // Add timeout to request to avoid DDOSing the server
@@ -121,16 +107,81 @@ pub async fn aggregate(
     }
 
     // get upstream responses
-    let mut responses = Vec::with_capacity(tasks.len());
-
-    for task in tasks {
-        if let Ok(result) = task.await {
-            responses.push(result)
+    let mut responses: EngineResponses = Vec::with_capacity(tasks.len());
+
+    for (engine, task) in names.into_iter().zip(tasks) {
+        if let Ok(response) = task.await {
+            record_engine_result(
+                engine,
+                response
+                    .as_ref()
+                    .err()
+                    .and_then(|error| error.downcast_ref::<EngineError>()),
+            );
+            responses.push((engine, response))
         }
     }
 
+    responses
+}
+
+/// The function aggregates the raw results fetched from the user-selected upstream search
+/// engines. The results are filtered to remove any errors and ensure only proper results are
+/// included. If an error is encountered, it is sent to the UI along with the name of the engine
+/// and the type of error. This information is finally placed in the returned `SearchResults`
+/// struct.
+///
+/// Additionally, the function eliminates duplicate results. If two results are identified as coming from
+/// multiple engines, their names are combined to indicate that the results were fetched from these upstream
+/// engines. After this, all the data in the `Vec` is removed and placed into a struct that contains all
+/// the aggregated results in a vector. Furthermore, the query used is also added to the struct. This step is
+/// necessary to ensure that the search bar in the search remains populated even when searched from the query URL.
+///
+/// When privacy redirects are enabled, the urls of the deduplicated results are rewritten to the
+/// alternative front-ends configured in the config file, so both the displayed links and the cached
+/// results reflect the rewrite.
+///
+/// The related searches suggested by the upstream engines are merged in the same pass, with duplicates
+/// (compared case insensitively) and the user's own query removed.
+///
+/// Overall, this function serves to aggregate scraped results from user-selected search engines, handling errors,
+/// removing duplicates, and organizing the data for display in the UI.
+///
+/// # Example:
+///
+/// If you search from the url like `https://127.0.0.1/search?q=huston` then the search bar should
+/// contain the word huston and not remain empty.
+///
+/// # Arguments
+///
+/// * `query` - Accepts the string queried with the upstream search engines.
+/// * `config` - Accepts the parsed config holding the tracking parameters and the privacy
+///   redirect rules.
+/// * `responses` - Accepts the raw results or the errors of the upstream search engines, which
+///   are fetched with `fetch_engine_results` or taken from the cache.
+/// * `filter_lists` - Accepts the loaded filter lists used to filter the results.
+/// * `safe_search` - Accepts the safe search level used to filter the results.
+/// * `privacy_redirects` - Accepts a boolean value to enable or disable rewriting the result urls
+///   to the configured privacy respecting front-ends.
+/// * `reveal_filtered` - Accepts a boolean value to keep the results which would be removed by
+///   the filter lists, which are still counted.
+///
+/// # Error
+///
+/// Returns a `SearchResults struct` containing appropriate values, the errors of the engines are
+/// reported in it rather than returned.
+pub async fn aggregate(
+    query: &str,
+    config: &Config,
+    mut responses: EngineResponses,
+    filter_lists: &FilterLists,
+    safe_search: u8,
+    privacy_redirects: bool,
+    reveal_filtered: bool,
+) -> Result<SearchResults, Box<dyn std::error::Error>> {
     // aggregate search results, removing duplicates and handling errors the upstream engines returned
     let mut result_map: Vec<(String, SearchResult)> = Vec::new();
+    let mut related_searches: Vec<String> = Vec::new();
     let mut engine_errors_info: Vec<EngineErrorInfo> = Vec::new();
 
     let mut handle_error = |error: &Report<EngineError>, engine_name: &'static str| {
@@ -142,20 +193,32 @@ pub async fn aggregate(
     };
 
     for _ in 0..responses.len() {
-        let response = responses.pop().unwrap();
-        let engine = names.pop().unwrap();
+        // clean the result urls before deduplicating so that results which only differ by their
+        // redirect wrappers or tracking parameters are merged.
+        let (engine, response) = responses.pop().unwrap();
+        let response = response.map(|mut engine_results| {
+            engine_results.results.iter_mut().for_each(|(key, result)| {
+                result.url = clean_url(&result.url, &config.tracking_parameters);
+                key.clone_from(&result.url);
+            });
+            engine_results
+        });
 
         if result_map.is_empty() {
             match response {
-                Ok(results) => result_map = results,
+                Ok(engine_results) => {
+                    result_map = engine_results.results;
+                    related_searches.extend(engine_results.related_searches);
+                }
                 Err(error) => handle_error(&error, engine),
             };
             continue;
         }
 
         match response {
-            Ok(result) => {
-                result.into_iter().for_each(|(key, value)| {
+            Ok(engine_results) => {
+                related_searches.extend(engine_results.related_searches);
+                engine_results.results.into_iter().for_each(|(key, value)| {
                     match result_map.iter().find(|(key_s, _)| key_s == &key) {
                         Some(value) => value.1.to_owned().add_engines(engine),
                         None => result_map.push((key, value)),
@@ -166,85 +229,213 @@ pub async fn aggregate(
         };
     }
 
-    if safe_search >= 3 {
-        let mut blacklist_map: Vec<(String, SearchResult)> = Vec::new();
-        filter_with_lists(
-            &mut result_map,
-            &mut blacklist_map,
-            file_path(FileType::BlockList)?,
-        )
-        .await?;
-
-        filter_with_lists(
-            &mut blacklist_map,
-            &mut result_map,
-            file_path(FileType::AllowList)?,
-        )
-        .await?;
+    let filtered_results =
+        filter_results(&mut result_map, filter_lists, safe_search, reveal_filtered);
 
-        drop(blacklist_map);
+    if privacy_redirects {
+        result_map.iter_mut().for_each(|(_, result)| {
+            if let Some(url) = redirect_url(&result.url, &config.privacy_redirects.rules) {
+                result.url = url;
+            }
+        });
     }
 
     let mut results: Box<[SearchResult]> = result_map
         .into_iter()
         .map(|(_, mut value)| {
             if !value.url.contains("temu.com") {
-                value.calculate_relevance(query.as_str())
+                value.calculate_relevance(query)
             }
             value
         })
         .collect();
     sort_search_results(&mut results);
 
-    Ok(SearchResults::new(
+    let mut results = SearchResults::new(
         results,
         engine_errors_info.into_boxed_slice(),
-    ))
+        dedup_related_searches(query, related_searches),
+    );
+    results.filtered_results = filtered_results;
+    results.revealed = reveal_filtered;
+    Ok(results)
 }
 
-/// Filters a map of search results using a list of regex patterns.
+/// Removes the search results matched by the filter lists and counts the results removed by
+/// every rule. When the filtered results are revealed (which the server has to permit), the
+/// results matched by the filter lists are kept while they are still counted, so that the notice
+/// tells which of the shown results would have been removed.
 ///
 /// # Arguments
 ///
-/// * `map_to_be_filtered` - A mutable reference to a `Vec` of search results to filter, where the filtered results will be removed from.
-/// * `resultant_map` - A mutable reference to a `Vec` to hold the filtered results.
-/// * `file_path` - A `&str` representing the path to a file containing regex patterns to use for filtering.
+/// * `result_map` - It takes the aggregated search results as an argument.
+/// * `filter_lists` - It takes the loaded filter lists as an argument.
+/// * `safe_search` - It takes the safe search level used to filter the results as an argument.
+/// * `reveal_filtered` - It takes whether the results matched by the filter lists should be kept
+///   as an argument.
 ///
-/// # Errors
+/// # Returns
 ///
-/// Returns an error if the file at `file_path` cannot be opened or read, or if a regex pattern is invalid.
-pub async fn filter_with_lists(
-    map_to_be_filtered: &mut Vec<(String, SearchResult)>,
-    resultant_map: &mut Vec<(String, SearchResult)>,
-    file_path: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let reader = BufReader::new(File::open(file_path).await?);
-    let mut lines = reader.lines();
-
-    while let Some(line) = lines.next_line().await? {
-        let re = Regex::new(line.trim())?;
-
-        let mut length = map_to_be_filtered.len();
-        let mut idx: usize = Default::default();
-        // Iterate over each search result in the map and check if it matches the regex pattern
-        while idx < length {
-            let ele = &map_to_be_filtered[idx];
-            let ele_inner = &ele.1;
-            match re.is_match(&ele.0.to_lowercase())
-                || re.is_match(&ele_inner.title.to_lowercase())
-                || re.is_match(&ele_inner.description.to_lowercase())
+/// Returns the number of search results removed by every rule.
+fn filter_results(
+    result_map: &mut Vec<(String, SearchResult)>,
+    filter_lists: &FilterLists,
+    safe_search: u8,
+    reveal_filtered: bool,
+) -> Vec<FilteredResultsInfo> {
+    let mut filtered_results: Vec<FilteredResultsInfo> = Vec::new();
+
+    // The domain filter lists are used to remove spam, so they are applied at every safe search
+    // level.
+    result_map.retain(
+        |(_, result)| match filter_lists.domain_filter.blocking_rule(&result.url) {
+            Some(blocking_rule) => {
+                FilteredResultsInfo::record(
+                    &mut filtered_results,
+                    blocking_rule.list,
+                    &blocking_rule.rule,
+                    1,
+                );
+                reveal_filtered
+            }
+            None => true,
+        },
+    );
+
+    if safe_search >= 3 {
+        let mut blacklist_map: Vec<(String, SearchResult)> = Vec::new();
+        filter_with_lists(result_map, &mut blacklist_map, &filter_lists.blocklist);
+        filter_with_lists(&mut blacklist_map, result_map, &filter_lists.allowlist);
+
+        for (key, result) in &blacklist_map {
+            if let Some(rule) = [key, &result.title, &result.description]
+                .into_iter()
+                .find_map(|text| filter_lists.blocklist.matching_rule(&text.to_lowercase()))
             {
-                true => {
-                    // If the search result matches the regex pattern, move it from the original map to the resultant map
-                    resultant_map.push(map_to_be_filtered.swap_remove(idx));
-                    length -= 1;
-                }
-                false => idx += 1,
-            };
+                FilteredResultsInfo::record(&mut filtered_results, "blocklist", rule, 1);
+            }
         }
+        // The revealed results are sorted back in by their relevance with the other results.
+        if reveal_filtered {
+            result_map.extend(blacklist_map);
+        }
+    }
+
+    // The local classifier makes every safe search level remove comparable results, as the
+    // upstream search engines implement their own safe search differently (or not at all).
+    if !filter_lists.safe_search.is_empty() {
+        result_map.retain(|(_, result)| {
+            let classification = filter_lists.safe_search.classify(result, safe_search);
+            if classification.is_unsafe() {
+                FilteredResultsInfo::record(
+                    &mut filtered_results,
+                    "safe search",
+                    &classification.rules.join(" + "),
+                    1,
+                );
+            }
+            reveal_filtered || !classification.is_unsafe()
+        });
     }
 
-    Ok(())
+    filtered_results
+}
+
+/// Removes the duplicate related searches (compared case insensitively and ignoring surrounding
+/// whitespace) and the ones matching the user's query while keeping the order in which they
+/// were provided by the upstream engines.
+///
+/// # Arguments
+///
+/// * `query` - The search query provided by the user.
+/// * `related_searches` - The related searches collected from all the upstream engines.
+///
+/// # Returns
+///
+/// Returns at most `MAX_RELATED_SEARCHES` unique related searches.
+fn dedup_related_searches(query: &str, related_searches: Vec<String>) -> Box<[String]> {
+    let mut seen: Vec<String> = vec![query.trim().to_lowercase()];
+
+    related_searches
+        .into_iter()
+        .filter(|related_search| {
+            let normalized = related_search.trim().to_lowercase();
+            match seen.contains(&normalized) {
+                true => false,
+                false => {
+                    seen.push(normalized);
+                    true
+                }
+            }
+        })
+        .take(MAX_RELATED_SEARCHES)
+        .collect()
+}
+
+/// Filters a map of search results using a compiled list of regex patterns.
+///
+/// # Arguments
+///
+/// * `map_to_be_filtered` - A mutable reference to a `Vec` of search results to filter, where the filtered results will be removed from.
+/// * `resultant_map` - A mutable reference to a `Vec` to hold the filtered results.
+/// * `filter_list` - A reference to the compiled `FilterList` to use for filtering.
+pub fn filter_with_lists(
+    map_to_be_filtered: &mut Vec<(String, SearchResult)>,
+    resultant_map: &mut Vec<(String, SearchResult)>,
+    filter_list: &FilterList,
+) {
+    if filter_list.is_empty() {
+        return;
+    }
+
+    let mut length = map_to_be_filtered.len();
+    let mut idx: usize = Default::default();
+    // Iterate over each search result in the map and check if it matches any of the regex patterns
+    while idx < length {
+        let ele = &map_to_be_filtered[idx];
+        let ele_inner = &ele.1;
+        match filter_list.is_match(&ele.0.to_lowercase())
+            || filter_list.is_match(&ele_inner.title.to_lowercase())
+            || filter_list.is_match(&ele_inner.description.to_lowercase())
+        {
+            true => {
+                // If the search result matches the regex pattern, move it from the original map to the resultant map
+                resultant_map.push(map_to_be_filtered.swap_remove(idx));
+                length -= 1;
+            }
+            false => idx += 1,
+        };
+    }
+}
+
+/// Rewrites the hostname of the url to the alternative front-end configured for it. A rule matches
+/// the hostname itself and all of its subdomains, and the most specific matching rule is used.
+///
+/// # Arguments
+///
+/// * `url` - The url of the search result to be rewritten.
+/// * `rules` - The hostnames mapped to the hostname of the alternative front-end they should be
+///   redirected to.
+///
+/// # Returns
+///
+/// Returns the rewritten url or `None` if no rule matches the url.
+fn redirect_url(url: &str, rules: &HashMap<String, String>) -> Option<String> {
+    let mut url = Url::parse(url).ok()?;
+    let host = url.host_str()?.to_lowercase();
+
+    let (_, redirect) = rules
+        .iter()
+        .filter(|(hostname, _)| {
+            host == **hostname
+                || host
+                    .strip_suffix(hostname.as_str())
+                    .is_some_and(|subdomain| subdomain.ends_with('.'))
+        })
+        .max_by_key(|(hostname, _)| hostname.len())?;
+
+    url.set_host(Some(redirect)).ok()?;
+    Some(url.into())
 }
 
 /// Sorts  SearchResults by relevance score.
@@ -253,7 +444,7 @@ pub async fn filter_with_lists(
 ///  # Arguments
 ///  * `results` - A mutable slice or Vec of SearchResults
 ///  
-fn sort_search_results(results: &mut [SearchResult]) {
+pub fn sort_search_results(results: &mut [SearchResult]) {
     results.sort_unstable_by(|a, b| {
         use std::cmp::Ordering;
 
@@ -266,11 +457,12 @@ fn sort_search_results(results: &mut [SearchResult]) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::results::{domain_filter::DomainFilter, safe_search::SafeSearchClassifier};
     use std::io::Write;
     use tempfile::NamedTempFile;
 
-    #[tokio::test]
-    async fn test_filter_with_lists() -> Result<(), Box<dyn std::error::Error>> {
+    #[test]
+    fn test_filter_with_lists() -> Result<(), Box<dyn std::error::Error>> {
         // Create a map of search results to filter
         let mut map_to_be_filtered = Vec::new();
         map_to_be_filtered.push((
@@ -302,12 +494,8 @@ mod tests {
         file.flush()?;
 
         let mut resultant_map = Vec::new();
-        filter_with_lists(
-            &mut map_to_be_filtered,
-            &mut resultant_map,
-            file.path().to_str().unwrap(),
-        )
-        .await?;
+        let filter_list = FilterList::load(file.path().to_str().unwrap())?;
+        filter_with_lists(&mut map_to_be_filtered, &mut resultant_map, &filter_list);
 
         assert_eq!(resultant_map.len(), 2);
         assert!(resultant_map
@@ -321,8 +509,8 @@ mod tests {
         Ok(())
     }
 
-    #[tokio::test]
-    async fn test_filter_with_lists_wildcard() -> Result<(), Box<dyn std::error::Error>> {
+    #[test]
+    fn test_filter_with_lists_wildcard() -> Result<(), Box<dyn std::error::Error>> {
         let mut map_to_be_filtered = Vec::new();
         map_to_be_filtered.push((
             "https://www.example.com".to_owned(),
@@ -353,12 +541,8 @@ mod tests {
 
         let mut resultant_map = Vec::new();
 
-        filter_with_lists(
-            &mut map_to_be_filtered,
-            &mut resultant_map,
-            file.path().to_str().unwrap(),
-        )
-        .await?;
+        let filter_list = FilterList::load(file.path().to_str().unwrap())?;
+        filter_with_lists(&mut map_to_be_filtered, &mut resultant_map, &filter_list);
 
         assert_eq!(resultant_map.len(), 1);
         assert!(resultant_map
@@ -372,24 +556,193 @@ mod tests {
         Ok(())
     }
 
-    #[tokio::test]
-    async fn test_filter_with_lists_file_not_found() {
-        let mut map_to_be_filtered = Vec::new();
+    #[test]
+    fn test_reveal_keeps_the_filtered_results() {
+        let mut domain_filter = DomainFilter::default();
+        domain_filter.extend("spam.txt", "||spam.example^");
+        let filter_lists = FilterLists {
+            blocklist: FilterList::parse("casino", "blocklist.txt").unwrap(),
+            domain_filter,
+            safe_search: SafeSearchClassifier::parse(&["explicit phrase"]).0,
+            ..Default::default()
+        };
+        let results = || -> Vec<(String, SearchResult)> {
+            [
+                ("Spam", "https://spam.example/"),
+                ("Online casino", "https://casino.example/"),
+                ("An explicit phrase", "https://explicit.example/"),
+                ("Rust", "https://www.rust-lang.org/"),
+            ]
+            .into_iter()
+            .map(|(title, url)| (url.to_owned(), SearchResult::new(title, url, "", &["bing"])))
+            .collect()
+        };
+        let urls = |result_map: &[(String, SearchResult)]| -> Vec<String> {
+            result_map.iter().map(|(url, _)| url.clone()).collect()
+        };
 
-        let mut resultant_map = Vec::new();
+        let mut result_map = results();
+        let filtered_results = filter_results(&mut result_map, &filter_lists, 3, false);
+        assert_eq!(urls(&result_map), ["https://www.rust-lang.org/"]);
+        assert_eq!(filtered_results.len(), 3);
+
+        // Revealing the filtered results keeps all of them while they are still counted.
+        let mut result_map = results();
+        let revealed_results = filter_results(&mut result_map, &filter_lists, 3, true);
+        let mut revealed_urls = urls(&result_map);
+        revealed_urls.sort();
+        let mut all_urls = urls(&results());
+        all_urls.sort();
+        assert_eq!(revealed_urls, all_urls);
+        assert_eq!(
+            revealed_results
+                .iter()
+                .map(|info| info.list.as_str())
+                .collect::<Vec<&str>>(),
+            filtered_results
+                .iter()
+                .map(|info| info.list.as_str())
+                .collect::<Vec<&str>>()
+        );
+    }
 
-        // Call the `filter_with_lists` function with a non-existent file path
-        let result = filter_with_lists(
-            &mut map_to_be_filtered,
-            &mut resultant_map,
-            "non-existent-file.txt",
+    #[test]
+    fn test_filtered_results_are_counted_per_rule() {
+        let mut filtered_results = Vec::new();
+        FilteredResultsInfo::record(&mut filtered_results, "blocklist", "casino", 1);
+        FilteredResultsInfo::record(&mut filtered_results, "spam.txt", "casino", 1);
+        FilteredResultsInfo::record(&mut filtered_results, "blocklist", "casino", 2);
+
+        assert_eq!(
+            filtered_results
+                .iter()
+                .map(|info| (info.list.as_str(), info.rule.as_str(), info.count))
+                .collect::<Vec<_>>(),
+            [("blocklist", "casino", 3), ("spam.txt", "casino", 1)]
         );
+    }
 
-        assert!(result.await.is_err());
+    #[tokio::test]
+    async fn test_aggregate_sanitizes_the_upstream_html() {
+        let config = Config::parse(true).unwrap();
+        let result = SearchResult::new(
+            r#"Rust <b onclick="alert(1)">lang</b><script>alert(document.cookie)</script>"#,
+            "https://www.rust-lang.org/",
+            r#"<img src=x onerror="alert(1)">A <em>safe</em> language<iframe src="javascript:alert(1)"></iframe>"#,
+            &["bing"],
+        );
+        let responses: EngineResponses = vec![(
+            "bing",
+            Ok(EngineResults::from(vec![(result.url.clone(), result)])),
+        )];
+
+        let search_results = aggregate(
+            "rust",
+            &config,
+            responses,
+            &FilterLists::default(),
+            0,
+            false,
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(search_results.results.len(), 1);
+        assert_eq!(search_results.results[0].title, "Rust <b>lang</b>");
+        assert_eq!(
+            search_results.results[0].description,
+            "A <em>safe</em> language"
+        );
     }
 
     #[tokio::test]
-    async fn test_filter_with_lists_invalid_regex() {
+    async fn test_aggregate_reveals_the_blocklisted_results() {
+        let config = Config::parse(true).unwrap();
+        let filter_lists = FilterLists {
+            blocklist: FilterList::parse("casino", "blocklist.txt").unwrap(),
+            ..Default::default()
+        };
+        let responses = || -> EngineResponses {
+            let results = ["https://casino.example/", "https://www.rust-lang.org/"]
+                .map(|url| {
+                    let result = SearchResult::new("Rust", url, "", &["bing"]);
+                    (url.to_owned(), result)
+                })
+                .to_vec();
+            vec![("bing", Ok(EngineResults::from(results)))]
+        };
+
+        let search_results =
+            aggregate("rust", &config, responses(), &filter_lists, 3, false, false)
+                .await
+                .unwrap();
+        assert_eq!(search_results.results.len(), 1);
+        assert_eq!(search_results.filtered_results[0].count, 1);
+
+        // The blocklisted result is kept when the filtered results are revealed.
+        let search_results = aggregate("rust", &config, responses(), &filter_lists, 3, false, true)
+            .await
+            .unwrap();
+        assert!(search_results
+            .results
+            .iter()
+            .any(|result| result.url == "https://casino.example/"));
+        assert_eq!(search_results.results.len(), 2);
+        assert_eq!(search_results.filtered_results[0].list, "blocklist");
+        assert!(search_results.revealed);
+    }
+
+    #[test]
+    fn test_dedup_related_searches() {
+        let related_searches = vec![
+            "rust programming".to_owned(),
+            "Rust Programming ".to_owned(),
+            "Rust".to_owned(),
+            "rust book".to_owned(),
+        ];
+
+        let deduped = dedup_related_searches("rust", related_searches);
+
+        assert_eq!(
+            deduped.as_ref(),
+            ["rust programming".to_owned(), "rust book".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_redirect_url() {
+        let rules = HashMap::from([
+            ("youtube.com".to_owned(), "yewtu.be".to_owned()),
+            ("medium.com".to_owned(), "scribe.rip".to_owned()),
+            ("blog.medium.com".to_owned(), "blog.example.org".to_owned()),
+        ]);
+
+        assert_eq!(
+            redirect_url("https://www.youtube.com/watch?v=dQw4w9WgXcQ", &rules).as_deref(),
+            Some("https://yewtu.be/watch?v=dQw4w9WgXcQ")
+        );
+        assert_eq!(
+            redirect_url("https://medium.com/@user/post", &rules).as_deref(),
+            Some("https://scribe.rip/@user/post")
+        );
+        assert_eq!(
+            redirect_url("https://blog.medium.com/post", &rules).as_deref(),
+            Some("https://blog.example.org/post")
+        );
+        assert_eq!(redirect_url("https://notyoutube.com/", &rules), None);
+        assert_eq!(redirect_url("https://www.rust-lang.org/", &rules), None);
+    }
+
+    #[test]
+    fn test_filter_with_lists_file_not_found() {
+        // Load the filter list from a non-existent file path
+        let result = FilterList::load("non-existent-file.txt");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_filter_with_lists_invalid_regex() {
         let mut map_to_be_filtered = Vec::new();
         map_to_be_filtered.push((
             "https://www.example.com".to_owned(),
@@ -410,12 +763,15 @@ mod tests {
         writeln!(file, "example(").unwrap();
         file.flush().unwrap();
 
-        let result = filter_with_lists(
+        let result = FilterList::load(file.path().to_str().unwrap());
+
+        assert!(result.is_err());
+        // Nothing is filtered as the list could not be loaded.
+        filter_with_lists(
             &mut map_to_be_filtered,
             &mut resultant_map,
-            file.path().to_str().unwrap(),
+            &FilterList::default(),
         );
-
-        assert!(result.await.is_err());
+        assert!(resultant_map.is_empty());
     }
 }
@@ -0,0 +1,320 @@
+//! This module provides the functionality to filter the search results by their domain using
+//! filter lists written in a subset of the adblock filter syntax, so that community maintained
+//! lists (like the lists of search spam domains) can be used without translating them to regexes.
+//!
+//! The supported rules are:
+//!
+//! * `||example.com^` and `example.com` - block the domain and all of its subdomains.
+//! * `*.example.com` and `||*.example.com^` - block only the subdomains of the domain.
+//! * `*://*.example.com/*` - block the domain and all of its subdomains (the match pattern syntax
+//!   used by the uBlacklist lists).
+//! * `@@` followed by any of the rules above - an exception which keeps the matching results even
+//!   when they are blocked by another rule.
+//! * Lines starting with `!`, `#` or `[` - comments and list headers.
+//!
+//! The rule options (after a `$`) are ignored and the rules which only block some paths of a
+//! domain or use any other syntax are not supported and are skipped.
+//!
+//! The same rules are used for the sites the users hide from their own search results.
+
+use std::{collections::HashMap, sync::Arc};
+
+use reqwest::Url;
+
+/// A named struct which stores the parsed rules of the domain filter lists. Every domain is mapped
+/// to the name of the list its rule was read from.
+#[derive(Default, Clone)]
+pub struct DomainFilter {
+    /// The domains which are blocked along with all of their subdomains.
+    blocked_domains: HashMap<String, Arc<str>>,
+    /// The domains whose subdomains are blocked.
+    blocked_subdomains: HashMap<String, Arc<str>>,
+    /// The domains which are excluded from blocking along with all of their subdomains.
+    allowed_domains: HashMap<String, Arc<str>>,
+    /// The domains whose subdomains are excluded from blocking.
+    allowed_subdomains: HashMap<String, Arc<str>>,
+}
+
+/// A named struct which stores the rule which blocked a search result along with the name of the
+/// list the rule was read from.
+#[derive(Debug, PartialEq)]
+pub struct BlockingRule<'a> {
+    /// The name of the list the rule was read from.
+    pub list: &'a str,
+    /// The blocking rule written in the normalized `example.com` or `*.example.com` form.
+    pub rule: String,
+}
+
+/// A named struct which stores a parsed domain rule.
+struct DomainRule<'a> {
+    /// The domain the rule applies to.
+    domain: &'a str,
+    /// Whether the rule only applies to the subdomains of the domain.
+    subdomains_only: bool,
+    /// Whether the rule is an exception.
+    exception: bool,
+}
+
+impl DomainFilter {
+    /// Parses the rules of a domain filter list and adds them to the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `list` - It takes the name of the list as an argument, which is reported along with the
+    ///   rules blocking a search result.
+    /// * `source` - It takes the contents of the list as an argument.
+    ///
+    /// # Returns
+    ///
+    /// Returns the line numbers of the rules which are not supported and were skipped.
+    pub fn extend(&mut self, list: &str, source: &str) -> Vec<usize> {
+        let list: Arc<str> = Arc::from(list);
+        let mut unsupported_lines = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['!', '#', '[']) {
+                continue;
+            }
+
+            match parse_rule(line) {
+                Some(rule) => {
+                    let domains = match (rule.exception, rule.subdomains_only) {
+                        (false, false) => &mut self.blocked_domains,
+                        (false, true) => &mut self.blocked_subdomains,
+                        (true, false) => &mut self.allowed_domains,
+                        (true, true) => &mut self.allowed_subdomains,
+                    };
+                    domains.insert(rule.domain.to_lowercase(), list.clone());
+                }
+                None => unsupported_lines.push(index + 1),
+            }
+        }
+
+        unsupported_lines
+    }
+
+    /// Checks whether the url of a search result is blocked by the rules of the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the url of the search result as an argument.
+    pub fn is_blocked(&self, url: &str) -> bool {
+        self.blocking_rule(url).is_some()
+    }
+
+    /// Returns the rule which blocks the url of a search result, so that the users can be told
+    /// which lists removed their results.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the url of the search result as an argument.
+    ///
+    /// # Returns
+    ///
+    /// Returns the blocking rule or `None` if the url is not blocked.
+    pub fn blocking_rule(&self, url: &str) -> Option<BlockingRule<'_>> {
+        if self.blocked_domains.is_empty() && self.blocked_subdomains.is_empty() {
+            return None;
+        }
+
+        let host = Url::parse(url).ok()?.host_str()?.to_lowercase();
+        if matching_rule(&host, &self.allowed_domains, &self.allowed_subdomains).is_some() {
+            return None;
+        }
+        matching_rule(&host, &self.blocked_domains, &self.blocked_subdomains)
+    }
+
+    /// Returns the number of rules in the filter.
+    pub fn len(&self) -> usize {
+        self.blocked_domains.len()
+            + self.blocked_subdomains.len()
+            + self.allowed_domains.len()
+            + self.allowed_subdomains.len()
+    }
+
+    /// Returns whether the filter has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Checks whether the rule is supported by the domain filter.
+///
+/// # Arguments
+///
+/// * `rule` - It takes the rule as an argument.
+pub fn is_supported_rule(rule: &str) -> bool {
+    let rule = rule.trim();
+    !rule.starts_with(['!', '#', '[']) && parse_rule(rule).is_some()
+}
+
+/// Returns the domain of the url of a search result without the `www.` prefix, which is used as
+/// the rule to hide all the results of that site.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the search result as an argument.
+pub fn site_domain(url: &str) -> Option<String> {
+    let host = Url::parse(url).ok()?.host_str()?.to_lowercase();
+    let domain = host.strip_prefix("www.").unwrap_or(&host);
+    is_supported_rule(domain).then(|| domain.to_owned())
+}
+
+/// Parses a single rule of a domain filter list.
+///
+/// # Arguments
+///
+/// * `line` - It takes the trimmed line of the list holding the rule as an argument.
+///
+/// # Returns
+///
+/// Returns the parsed rule or `None` if the rule is not supported.
+fn parse_rule(line: &str) -> Option<DomainRule<'_>> {
+    let (exception, rule) = match line.strip_prefix("@@") {
+        Some(rule) => (true, rule),
+        None => (false, line),
+    };
+    // The options only restrict the type of requests a rule applies to, which does not matter
+    // for the search results.
+    let rule = rule.split_once('$').map_or(rule, |(rule, _)| rule);
+
+    let (domain, subdomains_only) = if let Some(rule) = rule.strip_prefix("||") {
+        let domain = rule.trim_end_matches(['^', '|', '/']);
+        match domain.strip_prefix("*.") {
+            Some(domain) => (domain, true),
+            None => (domain, false),
+        }
+    } else if let Some((_, rule)) = rule.split_once("://") {
+        // The match patterns always match the domain itself, even with a `*.` prefix.
+        let domain = rule.strip_suffix("/*").unwrap_or(rule);
+        (domain.strip_prefix("*.").unwrap_or(domain), false)
+    } else if let Some(domain) = rule.strip_prefix("*.") {
+        (domain, true)
+    } else {
+        (rule, false)
+    };
+
+    let is_valid_domain = domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && domain
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || matches!(character, '-' | '.'));
+
+    is_valid_domain.then_some(DomainRule {
+        domain,
+        subdomains_only,
+        exception,
+    })
+}
+
+/// Finds the rule matching the host, that is the rule of the host itself or of one of its parent
+/// domains.
+///
+/// # Arguments
+///
+/// * `host` - It takes the lowercased host of the url as an argument.
+/// * `domains` - It takes the domains which match along with their subdomains as an argument.
+/// * `subdomains` - It takes the domains whose subdomains match as an argument.
+///
+/// # Returns
+///
+/// Returns the matching rule or `None` if no rule matches the host.
+fn matching_rule<'a>(
+    host: &str,
+    domains: &'a HashMap<String, Arc<str>>,
+    subdomains: &'a HashMap<String, Arc<str>>,
+) -> Option<BlockingRule<'a>> {
+    let mut suffix = host;
+    let mut is_subdomain = false;
+
+    loop {
+        if let Some(list) = domains.get(suffix) {
+            return Some(BlockingRule {
+                list,
+                rule: suffix.to_owned(),
+            });
+        }
+        if let Some(list) = subdomains.get(suffix).filter(|_| is_subdomain) {
+            return Some(BlockingRule {
+                list,
+                rule: format!("*.{suffix}"),
+            });
+        }
+        let (_, parent) = suffix.split_once('.')?;
+        suffix = parent;
+        is_subdomain = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_filter_rules() {
+        let mut filter = DomainFilter::default();
+        let unsupported_lines = filter.extend(
+            "spam.txt",
+            "[Adblock Plus 2.0]\n\
+             ! A comment\n\
+             ||spam.example^\n\
+             ||ads.example^$third-party\n\
+             *.wildcard.example\n\
+             *://*.match-pattern.example/*\n\
+             plain.example\n\
+             @@||good.spam.example^\n\
+             ||paths.example/only/this/path\n\
+             ##.cosmetic-rule\n",
+        );
+
+        assert_eq!(unsupported_lines, [9]);
+        assert_eq!(filter.len(), 6);
+
+        assert!(filter.is_blocked("https://spam.example/page"));
+        assert!(filter.is_blocked("https://www.spam.example/"));
+        assert!(filter.is_blocked("https://cdn.ads.example/"));
+        assert!(filter.is_blocked("https://a.b.wildcard.example/"));
+        assert!(!filter.is_blocked("https://wildcard.example/"));
+        assert!(filter.is_blocked("https://match-pattern.example/"));
+        assert!(filter.is_blocked("https://sub.plain.example/"));
+        // The exceptions take precedence over the blocking rules.
+        assert!(!filter.is_blocked("https://good.spam.example/"));
+        assert!(!filter.is_blocked("https://www.good.spam.example/"));
+        // Only whole labels of the domain are matched.
+        assert!(!filter.is_blocked("https://notspam.example/"));
+        assert!(!filter.is_blocked("https://www.rust-lang.org/"));
+        assert!(!filter.is_blocked("not a url"));
+
+        assert_eq!(
+            filter.blocking_rule("https://a.b.wildcard.example/"),
+            Some(BlockingRule {
+                list: "spam.txt",
+                rule: "*.wildcard.example".to_owned()
+            })
+        );
+        assert_eq!(
+            filter
+                .blocking_rule("https://www.spam.example/")
+                .map(|blocking_rule| blocking_rule.rule),
+            Some("spam.example".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_site_domain() {
+        assert_eq!(
+            site_domain("https://www.spam.example/page?q=1").as_deref(),
+            Some("spam.example")
+        );
+        assert_eq!(
+            site_domain("https://blog.spam.example/").as_deref(),
+            Some("blog.spam.example")
+        );
+        assert_eq!(site_domain("https://localhost/"), None);
+        assert!(is_supported_rule("*.spam.example"));
+        assert!(!is_supported_rule("spam example"));
+        assert!(!is_supported_rule("! a comment"));
+    }
+}
@@ -0,0 +1,83 @@
+//! This module provides the functionality to track the health of the upstream search engines, so
+//! that optional requests like prefetching the next page can be skipped while an engine keeps
+//! failing (for example because it has started rate limiting the server).
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use crate::models::engine_models::EngineError;
+
+/// A constant holding the number of consecutive failed requests after which an upstream search
+/// engine is considered to be struggling.
+const STRUGGLING_ENGINE_FAILURES: u32 = 3;
+
+/// A static holding the number of consecutive failed requests of every upstream search engine.
+static ENGINE_FAILURES: OnceLock<Mutex<HashMap<String, u32>>> = OnceLock::new();
+
+/// Records the outcome of a request to an upstream search engine. A successful request resets
+/// the failures of the engine, while a failed request adds to them. An empty result set is
+/// counted as a successful request, as the engine did respond to it.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the upstream search engine as an argument.
+/// * `error` - It takes the error returned by the upstream search engine as an argument or `None`
+///   if the request succeeded.
+pub fn record_engine_result(engine: &str, error: Option<&EngineError>) {
+    let Ok(mut engine_failures) = ENGINE_FAILURES.get_or_init(Default::default).lock() else {
+        return;
+    };
+
+    match error {
+        None | Some(EngineError::EmptyResultSet) => {
+            engine_failures.remove(engine);
+        }
+        Some(_) => *engine_failures.entry(engine.to_owned()).or_default() += 1,
+    }
+}
+
+/// Checks whether the upstream search engine is struggling, that is whether its last
+/// `STRUGGLING_ENGINE_FAILURES` requests have all failed.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the upstream search engine as an argument.
+pub fn is_struggling(engine: &str) -> bool {
+    ENGINE_FAILURES
+        .get_or_init(Default::default)
+        .lock()
+        .is_ok_and(|engine_failures| {
+            engine_failures
+                .get(engine)
+                .is_some_and(|failures| *failures >= STRUGGLING_ENGINE_FAILURES)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_struggles_after_consecutive_failures() {
+        let engine = "engine_health_test_engine";
+
+        for _ in 0..STRUGGLING_ENGINE_FAILURES - 1 {
+            record_engine_result(engine, Some(&EngineError::RequestError));
+        }
+        assert!(!is_struggling(engine));
+
+        // Empty result sets are not counted as failures.
+        record_engine_result(engine, Some(&EngineError::EmptyResultSet));
+        assert!(!is_struggling(engine));
+
+        for _ in 0..STRUGGLING_ENGINE_FAILURES {
+            record_engine_result(engine, Some(&EngineError::UnexpectedError));
+        }
+        assert!(is_struggling(engine));
+
+        record_engine_result(engine, None);
+        assert!(!is_struggling(engine));
+    }
+}
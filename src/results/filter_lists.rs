@@ -0,0 +1,396 @@
+//! This module provides the functionality to load the regex based blocklist and allowlist into
+//! compiled `RegexSet`s once, instead of re-reading and re-compiling them on every search, along
+//! with the domain filter lists referenced from the config file and the lists of the safe search
+//! classifier. The lists are loaded when the server starts and are watched for changes, so that
+//! edited lists are reloaded and swapped in atomically without restarting the server. A list which
+//! cannot be loaded is reported and replaced by its last good version (or an empty list), so that
+//! a broken list never stops the server or the other lists.
+
+use std::{
+    fmt, fs,
+    path::Path,
+    sync::{Arc, OnceLock, RwLock},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use regex::{Regex, RegexSet};
+
+use super::{
+    domain_filter::DomainFilter,
+    safe_search::{list_paths, SafeSearchClassifier},
+};
+use crate::handler::{file_path, FileType};
+
+/// A constant holding the interval at which the list files are checked for changes.
+const FILTER_LISTS_WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A static holding the currently loaded blocklist and allowlist.
+static FILTER_LISTS: OnceLock<RwLock<Arc<FilterLists>>> = OnceLock::new();
+
+/// A static holding the paths of the domain filter lists referenced from the config file.
+static DOMAIN_FILTER_LIST_PATHS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// A custom error type used for handling the errors which occur while loading a filter list.
+#[derive(Debug)]
+pub enum FilterListError {
+    /// This variant handles the errors which occur while reading the list file.
+    Io(String, std::io::Error),
+    /// This variant handles the rules of the list which are not valid regexes, along with the
+    /// line number of the rule.
+    InvalidRule(String, usize, regex::Error),
+}
+
+impl fmt::Display for FilterListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterListError::Io(path, error) => {
+                write!(f, "Unable to read the filter list {path}: {error}")
+            }
+            FilterListError::InvalidRule(path, line, error) => {
+                write!(
+                    f,
+                    "Invalid rule on line {line} of the filter list {path}: {error}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for FilterListError {}
+
+/// A named struct which stores the rules of a filter list compiled into a single `RegexSet`.
+#[derive(Clone)]
+pub struct FilterList {
+    /// The compiled rules of the list.
+    rules: RegexSet,
+}
+
+impl FilterList {
+    /// Parses the rules of a filter list, one regex per line. Blank lines are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - It takes the contents of the list as an argument.
+    /// * `path` - It takes the path of the list as an argument, which is used in the errors.
+    ///
+    /// # Error
+    ///
+    /// Returns the compiled list on success otherwise returns a `FilterListError` with the line
+    /// number of the first invalid rule.
+    pub fn parse(source: &str, path: &str) -> Result<Self, FilterListError> {
+        let mut rules = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let rule = line.trim();
+            if rule.is_empty() {
+                continue;
+            }
+            Regex::new(rule)
+                .map_err(|error| FilterListError::InvalidRule(path.to_owned(), index + 1, error))?;
+            rules.push(rule);
+        }
+
+        Ok(Self {
+            rules: RegexSet::new(rules)
+                .map_err(|error| FilterListError::InvalidRule(path.to_owned(), 0, error))?,
+        })
+    }
+
+    /// Reads and parses the filter list from the provided file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - It takes the path of the list file as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the compiled list on success otherwise returns a `FilterListError` if the file
+    /// cannot be read or contains an invalid rule.
+    pub fn load(path: &str) -> Result<Self, FilterListError> {
+        let source = fs::read_to_string(path)
+            .map_err(|error| FilterListError::Io(path.to_owned(), error))?;
+        Self::parse(&source, path)
+    }
+
+    /// Checks whether any rule of the list matches the provided text.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - It takes the text to be checked as an argument.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.rules.is_match(text)
+    }
+
+    /// Returns the first rule of the list which matches the provided text.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - It takes the text to be checked as an argument.
+    pub fn matching_rule(&self, text: &str) -> Option<&str> {
+        let index = self.rules.matches(text).into_iter().next()?;
+        Some(self.rules.patterns()[index].as_str())
+    }
+
+    /// Returns the number of rules in the list.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Returns whether the list has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl Default for FilterList {
+    fn default() -> Self {
+        Self {
+            rules: RegexSet::empty(),
+        }
+    }
+}
+
+/// A named struct which stores the compiled blocklist and allowlist along with the rules of all
+/// the domain filter lists and the safe search classifier.
+#[derive(Default)]
+pub struct FilterLists {
+    /// The compiled rules of the blocklist.
+    pub blocklist: FilterList,
+    /// The compiled rules of the allowlist.
+    pub allowlist: FilterList,
+    /// The merged rules of the domain filter lists.
+    pub domain_filter: DomainFilter,
+    /// The lists of the safe search levels used to classify the search results.
+    pub safe_search: SafeSearchClassifier,
+}
+
+impl FilterLists {
+    /// Loads the blocklist, the allowlist, the domain filter lists and the safe search lists from
+    /// their files. The safe search lists are optional. The error of a list file which cannot be
+    /// found or read or which contains an invalid rule is logged and the previous version of the
+    /// list is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous` - It takes the previously loaded lists as an argument, which are empty when
+    ///   the lists are loaded for the first time.
+    ///
+    /// # Returns
+    ///
+    /// Returns the loaded lists.
+    fn load(previous: &FilterLists) -> Self {
+        let filter_lists = Self {
+            blocklist: loaded_or_previous(load_list(FileType::BlockList), &previous.blocklist),
+            allowlist: loaded_or_previous(load_list(FileType::AllowList), &previous.allowlist),
+            domain_filter: loaded_or_previous(load_domain_filter(), &previous.domain_filter),
+            safe_search: loaded_or_previous(
+                match file_path(FileType::SafeSearch) {
+                    Ok(folder) => SafeSearchClassifier::load(folder).map_err(Into::into),
+                    Err(_) => Ok(SafeSearchClassifier::default()),
+                },
+                &previous.safe_search,
+            ),
+        };
+        log::info!(
+            "Loaded {} blocklist rules, {} allowlist rules, {} domain filter rules and {} safe search rules",
+            filter_lists.blocklist.len(),
+            filter_lists.allowlist.len(),
+            filter_lists.domain_filter.len(),
+            filter_lists.safe_search.len()
+        );
+        filter_lists
+    }
+}
+
+/// A helper function which returns the loaded list or, when it could not be loaded, logs the
+/// error and returns a copy of the previous version of the list.
+///
+/// # Arguments
+///
+/// * `loaded` - It takes the result of loading the list as an argument.
+/// * `previous` - It takes the previous version of the list as an argument.
+fn loaded_or_previous<T: Clone>(loaded: Result<T, Box<dyn std::error::Error>>, previous: &T) -> T {
+    loaded.unwrap_or_else(|error| {
+        log::error!("Filter List Error: {error}, keeping the previous version of the list");
+        previous.clone()
+    })
+}
+
+/// A helper function which loads the regex based filter list of the provided type.
+///
+/// # Arguments
+///
+/// * `file_type` - It takes the type of the filter list as an argument.
+///
+/// # Error
+///
+/// Returns the compiled list on success otherwise returns an error if the list file cannot be
+/// found or read or contains an invalid rule.
+fn load_list(file_type: FileType) -> Result<FilterList, Box<dyn std::error::Error>> {
+    Ok(FilterList::load(file_path(file_type)?)?)
+}
+
+/// A helper function which loads and merges the domain filter lists referenced from the config
+/// file.
+///
+/// # Error
+///
+/// Returns the merged rules of the lists on success otherwise returns a `FilterListError` if a
+/// list file cannot be read.
+fn load_domain_filter() -> Result<DomainFilter, Box<dyn std::error::Error>> {
+    let mut domain_filter = DomainFilter::default();
+    for path in DOMAIN_FILTER_LIST_PATHS.get().into_iter().flatten() {
+        let source = fs::read_to_string(path)
+            .map_err(|error| FilterListError::Io(path.to_owned(), error))?;
+        let list = Path::new(path)
+            .file_name()
+            .map_or(path.as_str(), |file_name| {
+                file_name.to_str().unwrap_or(path)
+            });
+        let unsupported_lines = domain_filter.extend(list, &source);
+        if let Some(line) = unsupported_lines.first() {
+            log::warn!(
+                "Skipped {} unsupported rules of the domain filter list {path} (the first one is on line {line})",
+                unsupported_lines.len()
+            );
+        }
+    }
+    Ok(domain_filter)
+}
+
+/// Loads the blocklist, the allowlist and the domain filter lists when the server starts and
+/// starts watching the list files for changes. The lists which cannot be loaded are reported and
+/// left empty until they are fixed. It does nothing if the lists have already been loaded.
+///
+/// # Arguments
+///
+/// * `domain_filter_lists` - It takes the paths of the domain filter lists referenced from the
+///   config file as an argument.
+pub fn init_filter_lists(domain_filter_lists: &[String]) {
+    if FILTER_LISTS.get().is_some() {
+        return;
+    }
+
+    DOMAIN_FILTER_LIST_PATHS.get_or_init(|| domain_filter_lists.to_vec());
+
+    let filter_lists = FilterLists::load(&FilterLists::default());
+    if FILTER_LISTS
+        .set(RwLock::new(Arc::new(filter_lists)))
+        .is_ok()
+    {
+        watch_filter_lists();
+    }
+}
+
+/// Returns the currently loaded blocklist and allowlist. If the lists have not been loaded with
+/// `init_filter_lists` yet, they are loaded now and empty lists are used for the lists which fail
+/// to load.
+pub fn filter_lists() -> Arc<FilterLists> {
+    let filter_lists = FILTER_LISTS
+        .get_or_init(|| RwLock::new(Arc::new(FilterLists::load(&FilterLists::default()))));
+
+    match filter_lists.read() {
+        Ok(filter_lists) => filter_lists.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
+/// Spawns a background thread which checks the list files for changes and reloads them. The
+/// reloaded lists are swapped in atomically, while the previous version of a list is kept if the
+/// edited list contains an invalid rule.
+fn watch_filter_lists() {
+    let modified_times = || {
+        [FileType::BlockList, FileType::AllowList]
+            .into_iter()
+            .filter_map(|file_type| file_path(file_type).ok())
+            .map(str::to_owned)
+            .chain(
+                DOMAIN_FILTER_LIST_PATHS
+                    .get()
+                    .into_iter()
+                    .flatten()
+                    .cloned(),
+            )
+            .chain(
+                file_path(FileType::SafeSearch)
+                    .map(list_paths)
+                    .unwrap_or_default(),
+            )
+            .map(|path| {
+                Path::new(&path)
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+            .collect::<Vec<Option<SystemTime>>>()
+    };
+
+    let spawned = thread::Builder::new()
+        .name("filter-lists-watcher".to_owned())
+        .spawn(move || {
+            let mut last_modified_times = modified_times();
+            loop {
+                thread::sleep(FILTER_LISTS_WATCH_INTERVAL);
+
+                let current_modified_times = modified_times();
+                if current_modified_times == last_modified_times {
+                    continue;
+                }
+                last_modified_times = current_modified_times;
+
+                if let Some(loaded_lists) = FILTER_LISTS.get() {
+                    let reloaded_lists = FilterLists::load(&filter_lists());
+                    let mut filter_lists = match loaded_lists.write() {
+                        Ok(filter_lists) => filter_lists,
+                        Err(poisoned) => poisoned.into_inner(),
+                    };
+                    *filter_lists = Arc::new(reloaded_lists);
+                }
+            }
+        });
+
+    if let Err(error) = spawned {
+        log::error!("Unable to watch the filter lists for changes: {error}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_filter_list() {
+        let list = FilterList::parse("example\n\n  ex.*le  \nrust\n", "list.txt").unwrap();
+        assert_eq!(list.len(), 3);
+        assert!(list.is_match("an example domain"));
+        assert!(!list.is_match("nothing to see here"));
+        assert_eq!(list.matching_rule("an example domain"), Some("example"));
+        assert_eq!(list.matching_rule("rust"), Some("rust"));
+        assert_eq!(list.matching_rule("nothing to see here"), None);
+
+        assert!(FilterList::parse("", "list.txt").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_filter_list_reports_line_number() {
+        let error = FilterList::parse("example\n\nexample(\n", "list.txt")
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error,
+            FilterListError::InvalidRule(ref path, 3, _) if path == "list.txt"
+        ));
+        assert!(error.to_string().starts_with("Invalid rule on line 3"));
+    }
+
+    #[test]
+    fn test_broken_lists_keep_their_previous_version() {
+        let previous = FilterList::parse("casino", "blocklist.txt").unwrap();
+        let edited = FilterList::parse("casino\npoker(", "blocklist.txt").map_err(Into::into);
+
+        let blocklist = loaded_or_previous(edited, &previous);
+        assert!(blocklist.is_match("online casino"));
+        assert!(!blocklist.is_match("poker"));
+        assert!(loaded_or_previous(Err("missing".into()), &FilterList::default()).is_empty());
+    }
+}
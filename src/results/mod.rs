@@ -1,6 +1,19 @@
 //! This module provides modules that handle the functionality to aggregate the fetched search
 //! results from the upstream search engines and filters it if safe search is set to 3 or 4. Also,
-//! provides various models to aggregate search results into a standardized form.
+//! provides various models to aggregate search results into a standardized form and runs the lua
+//! plugin hooks on them. It also cleans the result urls of tracking parameters and redirect wrappers
+//! and sanitizes the upstream html in the result titles and descriptions, paginates the aggregated
+//! search results and tracks the health of the upstream search engines. The regex and domain filter
+//! lists along with the safe search classifier used to filter the search results are loaded and
+//! reloaded here as well.
 
 pub mod aggregator;
+pub mod domain_filter;
+pub mod engine_health;
+pub mod filter_lists;
+pub mod paginator;
+pub mod plugins;
+pub mod safe_search;
+pub mod sanitizer;
+pub mod url_cleaner;
 mod user_agent;
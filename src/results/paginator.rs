@@ -0,0 +1,421 @@
+//! This module provides the functionality to serve consistent pages of the aggregated search
+//! results. Every upstream search engine pages its results differently (DuckDuckGo pages by 30
+//! while Bing and Mojeek page by 10), so instead of asking all the engines for the same page
+//! number, the engine pages are fetched as needed and merged into a pagination buffer from which
+//! the pages of the requested size are sliced.
+
+use std::future::Future;
+
+use crate::models::{
+    aggregation_models::{FilteredResultsInfo, SearchResult, SearchResults},
+    engine_models::EngineHandler,
+};
+
+/// A constant holding the maximum number of pages fetched from every upstream search engine for
+/// a search query.
+pub const MAX_ENGINE_PAGES: u32 = 10;
+
+/// Serves the requested page of the search results from the pagination buffer of the search
+/// query. When the buffer does not hold enough results, the next pages of the upstream search
+/// engines which still have results are fetched and merged into it. The results of every engine
+/// page are appended after the results already in the buffer, so the pages which have been served
+/// once never change and no result is skipped or repeated across the pages. The results hidden by
+/// the user are skipped when the pages are sliced, so that the buffer can be shared by the users
+/// hiding different results while their pages are still filled up to their size.
+///
+/// # Arguments
+///
+/// * `buffer` - It takes the pagination buffer of the search query as an argument, which is empty
+///   when the search query has not been paginated yet.
+/// * `page` - It takes the zero based page number requested by the user as an argument.
+/// * `results_per_page` - It takes the number of results shown on a page as an argument.
+/// * `upstream_search_engines` - It takes the upstream search engines selected by the user as an
+///   argument.
+/// * `is_hidden` - It takes a function which tells whether a search result is hidden by the user
+///   as an argument.
+/// * `fetch_engine_page` - It takes a function which aggregates the results of the given page of
+///   the given upstream search engines as an argument.
+///
+/// # Error
+///
+/// Returns the requested page along with a flag indicating whether the buffer has changed and
+/// needs to be cached again on success otherwise returns the error of `fetch_engine_page`.
+pub async fn paginate<H, F, Fut>(
+    buffer: &mut SearchResults,
+    page: u32,
+    results_per_page: usize,
+    upstream_search_engines: &[EngineHandler],
+    is_hidden: H,
+    fetch_engine_page: F,
+) -> Result<(SearchResults, bool), Box<dyn std::error::Error>>
+where
+    H: Fn(&SearchResult) -> bool,
+    F: Fn(u32, Vec<EngineHandler>) -> Fut,
+    Fut: Future<Output = Result<SearchResults, Box<dyn std::error::Error>>>,
+{
+    let start = page as usize * results_per_page;
+    let mut changed = false;
+
+    loop {
+        let engines = pending_engines(
+            buffer,
+            page,
+            results_per_page,
+            upstream_search_engines,
+            &is_hidden,
+        );
+        if engines.is_empty() {
+            break;
+        }
+
+        let engine_page = buffer.pagination.fetched_engine_pages;
+        let engine_results = fetch_engine_page(engine_page, engines.clone()).await?;
+        merge_engine_page(buffer, engine_results, &engines, engine_page == 0);
+        buffer.pagination.fetched_engine_pages += 1;
+        changed = true;
+    }
+
+    let visible_results: Vec<(usize, &SearchResult)> = buffer
+        .results
+        .iter()
+        .enumerate()
+        .filter(|(_, result)| !is_hidden(result))
+        .collect();
+    let results = visible_results.get(start..).unwrap_or_default();
+    let has_next_page = results.len() > results_per_page;
+
+    // The page spans the buffer from its first result up to the first result of the next page.
+    // The results filtered from an engine page are reported on the page spanning its first
+    // result, or on the last page when the engine page has not added any result after it.
+    let page_start = match page {
+        0 => 0,
+        _ => results
+            .first()
+            .map_or(buffer.results.len(), |(index, _)| *index),
+    };
+    let page_end = results
+        .get(results_per_page)
+        .map_or(buffer.results.len(), |(index, _)| *index);
+    let mut filtered_results: Vec<FilteredResultsInfo> = Vec::new();
+    for (first_index, engine_page_filtered_results) in &buffer.pagination.filtered_engine_pages {
+        if (page_start..page_end).contains(first_index)
+            || (!has_next_page && *first_index >= page_end)
+        {
+            for info in engine_page_filtered_results {
+                FilteredResultsInfo::record(
+                    &mut filtered_results,
+                    &info.list,
+                    &info.rule,
+                    info.count,
+                );
+            }
+        }
+    }
+
+    Ok((
+        SearchResults {
+            results: results
+                .iter()
+                .take(results_per_page)
+                .map(|(_, result)| (*result).clone())
+                .collect(),
+            engine_errors_info: buffer.engine_errors_info.clone(),
+            related_searches: buffer.related_searches.clone(),
+            disallowed: buffer.disallowed,
+            filtered: buffer.filtered,
+            safe_search_level: buffer.safe_search_level,
+            no_engines_selected: buffer.no_engines_selected,
+            has_next_page,
+            pagination: Default::default(),
+            filtered_results,
+            revealed: buffer.revealed,
+            cached_at: Default::default(),
+        },
+        changed,
+    ))
+}
+
+/// A function which returns the upstream search engines the next engine page should be fetched
+/// from to serve the requested page from the pagination buffer.
+///
+/// # Arguments
+///
+/// * `buffer` - It takes the pagination buffer of the search query as an argument.
+/// * `page` - It takes the zero based page number requested by the user as an argument.
+/// * `results_per_page` - It takes the number of results shown on a page as an argument.
+/// * `upstream_search_engines` - It takes the upstream search engines selected by the user as an
+///   argument.
+/// * `is_hidden` - It takes a function which tells whether a search result is hidden by the user
+///   as an argument.
+///
+/// # Returns
+///
+/// Returns the upstream search engines which still have results, or none if the buffer already
+/// holds the requested page or no engine page can be fetched anymore.
+pub fn pending_engines(
+    buffer: &SearchResults,
+    page: u32,
+    results_per_page: usize,
+    upstream_search_engines: &[EngineHandler],
+    is_hidden: impl Fn(&SearchResult) -> bool,
+) -> Vec<EngineHandler> {
+    // One more result than the page holds is buffered to know whether there is a next page.
+    let end = (page as usize + 1) * results_per_page;
+    let visible_results = buffer
+        .results
+        .iter()
+        .filter(|result| !is_hidden(result))
+        .count();
+    if visible_results > end || buffer.pagination.fetched_engine_pages >= MAX_ENGINE_PAGES {
+        return Vec::new();
+    }
+
+    upstream_search_engines
+        .iter()
+        .filter(|engine| {
+            !buffer
+                .pagination
+                .exhausted_engines
+                .iter()
+                .any(|name| name == engine.name())
+        })
+        .cloned()
+        .collect()
+}
+
+/// Merges the aggregated results of an engine page into the pagination buffer. The results which
+/// are already in the buffer only get the names of the new engines added, while the new results
+/// are appended in their aggregated order. The engines which failed or did not provide any new
+/// results are marked as exhausted and the counts of the filtered results are added up.
+///
+/// # Arguments
+///
+/// * `buffer` - It takes the pagination buffer of the search query as an argument.
+/// * `engine_results` - It takes the aggregated results of the engine page as an argument.
+/// * `engines` - It takes the upstream search engines the engine page was fetched from as an
+///   argument.
+/// * `first_page` - It takes whether the engine page is the first page as an argument. The engine
+///   errors and the related searches are only kept from the first page, so that the errors of
+///   the engines running out of results on the later pages are not shown to the user.
+fn merge_engine_page(
+    buffer: &mut SearchResults,
+    engine_results: SearchResults,
+    engines: &[EngineHandler],
+    first_page: bool,
+) {
+    let mut results = std::mem::take(&mut buffer.results).into_vec();
+    let mut contributing_engines: Vec<&str> = Vec::new();
+    let first_index = results.len();
+
+    for result in engine_results.results.into_vec() {
+        match results
+            .iter_mut()
+            .find(|buffered| buffered.url == result.url)
+        {
+            Some(buffered) => {
+                for engine in result.engine {
+                    if !buffered.engine.contains(&engine) {
+                        buffered.add_engines(&engine);
+                    }
+                }
+            }
+            None => {
+                contributing_engines.extend(engines.iter().map(EngineHandler::name).filter(
+                    |name| {
+                        result
+                            .engine
+                            .iter()
+                            .any(|engine| engine.eq_ignore_ascii_case(name))
+                    },
+                ));
+                results.push(result);
+            }
+        }
+    }
+    buffer.results = results.into_boxed_slice();
+    buffer.revealed = engine_results.revealed;
+
+    for info in &engine_results.filtered_results {
+        FilteredResultsInfo::record(
+            &mut buffer.filtered_results,
+            &info.list,
+            &info.rule,
+            info.count,
+        );
+    }
+    buffer
+        .pagination
+        .filtered_engine_pages
+        .push((first_index, engine_results.filtered_results));
+
+    for engine in engines {
+        let failed = engine_results
+            .engine_errors_info
+            .iter()
+            .any(|error| error.engine == engine.name());
+        if failed || !contributing_engines.contains(&engine.name()) {
+            buffer
+                .pagination
+                .exhausted_engines
+                .push(engine.name().to_owned());
+        }
+    }
+
+    if first_page {
+        buffer.engine_errors_info = engine_results.engine_errors_info;
+        buffer.related_searches = engine_results.related_searches;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        aggregation_models::{EngineErrorInfo, SearchResult},
+        engine_models::EngineError,
+    };
+
+    /// A helper function which mocks an engine page of the upstream search engines. DuckDuckGo
+    /// provides two pages of 30 results and Bing provides three pages of 10 results, where the
+    /// first page of Bing repeats the first results of DuckDuckGo.
+    fn mock_engine_page(page: u32, engines: &[EngineHandler]) -> SearchResults {
+        let mut results = Vec::new();
+        let mut engine_errors_info = Vec::new();
+
+        for engine in engines {
+            let (name, page_size, pages) = match engine.name() {
+                "duckduckgo" => ("duckduckgo", 30, 2),
+                _ => ("bing", 10, 3),
+            };
+            if page >= pages {
+                engine_errors_info.push(EngineErrorInfo::new(&EngineError::EmptyResultSet, name));
+                continue;
+            }
+            for index in page * page_size..(page + 1) * page_size {
+                let url = match (name, page) {
+                    ("bing", 0) if index < 5 => format!("https://duckduckgo.example/{index}"),
+                    _ => format!("https://{name}.example/{index}"),
+                };
+                results.push(SearchResult::new("title", &url, "description", &[name]));
+            }
+        }
+
+        let mut results = SearchResults::new(
+            results.into_boxed_slice(),
+            engine_errors_info.into_boxed_slice(),
+            Box::default(),
+        );
+        // Every engine page has a result removed by the blocklist.
+        FilteredResultsInfo::record(&mut results.filtered_results, "blocklist", "spam", 1);
+        results
+    }
+
+    /// A helper function which returns the number of results removed by the filter lists.
+    fn results_filtered_count(results: &SearchResults) -> u32 {
+        results.filtered_results.iter().map(|info| info.count).sum()
+    }
+
+    #[tokio::test]
+    async fn test_paginate_serves_consistent_pages() {
+        let engines = [
+            EngineHandler::new("duckduckgo").unwrap(),
+            EngineHandler::new("bing").unwrap(),
+        ];
+        let mut buffer = SearchResults::default();
+        let mut served_urls = Vec::new();
+        let mut served_filtered_count = 0;
+        assert_eq!(
+            pending_engines(&buffer, 0, 20, &engines, |_| false).len(),
+            engines.len()
+        );
+
+        for page in 0.. {
+            let (results, _) = paginate(
+                &mut buffer,
+                page,
+                20,
+                &engines,
+                |_| false,
+                |page, engines| async move { Ok(mock_engine_page(page, &engines)) },
+            )
+            .await
+            .unwrap();
+
+            assert!(results.results.len() <= 20);
+            served_urls.extend(results.results.iter().map(|result| result.url.clone()));
+            served_filtered_count += results_filtered_count(&results);
+            if !results.has_next_page {
+                break;
+            }
+        }
+
+        // 60 results of DuckDuckGo and 30 results of Bing of which 5 are duplicates.
+        assert_eq!(served_urls.len(), 85);
+        let mut unique_urls = served_urls.clone();
+        unique_urls.sort();
+        unique_urls.dedup();
+        assert_eq!(unique_urls.len(), 85);
+
+        let duplicate = buffer
+            .results
+            .iter()
+            .find(|result| result.url == "https://duckduckgo.example/0")
+            .unwrap();
+        assert_eq!(duplicate.engine, ["duckduckgo", "bing"]);
+        assert_eq!(buffer.pagination.exhausted_engines, ["duckduckgo", "bing"]);
+        assert_eq!(
+            results_filtered_count(&buffer),
+            buffer.pagination.fetched_engine_pages
+        );
+        // Every page only reports the results filtered from the engine pages it shows.
+        assert_eq!(served_filtered_count, results_filtered_count(&buffer));
+
+        // Serving a page again from the buffer neither changes it nor fetches anything.
+        assert!(pending_engines(&buffer, 1, 20, &engines, |_| false).is_empty());
+        let (results, changed) = paginate(
+            &mut buffer,
+            1,
+            20,
+            &engines,
+            |_| false,
+            |_, _| async { Err::<SearchResults, _>("no engine page should be fetched".into()) },
+        )
+        .await
+        .unwrap();
+        assert!(!changed);
+        assert_eq!(
+            results
+                .results
+                .iter()
+                .map(|result| result.url.as_str())
+                .collect::<Vec<_>>(),
+            served_urls[20..40]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paginate_skips_the_hidden_results() {
+        let engines = [EngineHandler::new("duckduckgo").unwrap()];
+        let is_hidden = |result: &SearchResult| result.url.ends_with('0');
+        let mut buffer = SearchResults::default();
+
+        let (results, _) = paginate(
+            &mut buffer,
+            2,
+            10,
+            &engines,
+            is_hidden,
+            |page, engines| async move { Ok(mock_engine_page(page, &engines)) },
+        )
+        .await
+        .unwrap();
+
+        // The hidden results are kept in the buffer, so that it can be shared with the users
+        // who do not hide them, while the pages are still filled up.
+        assert_eq!(buffer.results.len(), 60);
+        assert_eq!(results.results.len(), 10);
+        assert!(!results.results.iter().any(is_hidden));
+        assert_eq!(results.results[0].url, "https://duckduckgo.example/23");
+        assert!(results.has_next_page);
+    }
+}
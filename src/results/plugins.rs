@@ -0,0 +1,455 @@
+//! This module provides the functionality to run the lua plugin hooks (`on_query` and
+//! `on_result`) defined in the config file or in the files of the `plugins` folder around the
+//! aggregation of the search results.
+//!
+//! Each worker thread gets its own sandboxed lua instance which only has access to the `table`,
+//! `string` and `math` standard libraries, so plugins can customize the search behaviour without
+//! recompiling the app but cannot touch the filesystem or the network. Every hook is stopped when
+//! it runs for longer than its time budget, so a plugin cannot stall the worker running it.
+
+use std::{
+    cell::Cell,
+    fs,
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use mlua::{Function, HookTriggers, Lua, LuaOptions, RegistryKey, StdLib, Table, Value};
+
+use super::{aggregator::sort_search_results, sanitizer::sanitize_html};
+use crate::{
+    handler::{file_path, FileType},
+    models::aggregation_models::{SearchResult, SearchResults},
+};
+
+/// A constant holding the maximum amount of memory (in bytes) a plugin lua instance may use.
+const PLUGIN_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// A constant holding the time a plugin hook (or the source of a plugin) may run for before it is
+/// stopped.
+const PLUGIN_TIME_BUDGET: Duration = Duration::from_millis(50);
+
+/// A constant holding the number of lua instructions run between two checks of the time budget.
+const PLUGIN_BUDGET_CHECK_INTERVAL: u32 = 1000;
+
+/// A constant holding the globals which are removed from the sandboxed lua instance as they allow
+/// reading files from the filesystem or catching the error stopping a hook which has run out of
+/// time.
+const UNSAFE_GLOBALS: [&str; 4] = ["dofile", "loadfile", "pcall", "xpcall"];
+
+thread_local! {
+    /// A thread local variable which stores the plugins loaded for the current worker thread or
+    /// `None` if no plugin hooks were found or the plugins failed to load.
+    static PLUGINS: Option<Plugins> = Plugins::load();
+}
+
+/// A named struct which stores the sandboxed lua instance along with the plugin hooks defined in
+/// it.
+struct Plugins {
+    /// The sandboxed lua instance in which the plugin hooks are run.
+    lua: Lua,
+    /// The `on_query` hooks with the name of the file which defined them.
+    query_hooks: Vec<(String, RegistryKey)>,
+    /// The `on_result` hooks with the name of the file which defined them.
+    result_hooks: Vec<(String, RegistryKey)>,
+    /// The time at which the running hook is stopped.
+    deadline: Rc<Cell<Instant>>,
+}
+
+impl Plugins {
+    /// Creates a new sandboxed lua instance with no hooks registered.
+    ///
+    /// # Error
+    ///
+    /// Returns a lua error if the lua instance could not be initialized.
+    fn new() -> mlua::Result<Self> {
+        let lua = Lua::new_with(
+            StdLib::TABLE | StdLib::STRING | StdLib::MATH,
+            LuaOptions::default(),
+        )?;
+        lua.set_memory_limit(PLUGIN_MEMORY_LIMIT)?;
+
+        let globals = lua.globals();
+        for name in UNSAFE_GLOBALS {
+            globals.set(name, Value::Nil)?;
+        }
+        drop(globals);
+
+        let deadline = Rc::new(Cell::new(Instant::now()));
+        let hook_deadline = Rc::clone(&deadline);
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(PLUGIN_BUDGET_CHECK_INTERVAL),
+            move |_, _| match Instant::now() > hook_deadline.get() {
+                true => Err(mlua::Error::runtime(format!(
+                    "the plugin ran for longer than {}ms",
+                    PLUGIN_TIME_BUDGET.as_millis()
+                ))),
+                false => Ok(()),
+            },
+        );
+
+        Ok(Self {
+            lua,
+            query_hooks: Vec::new(),
+            result_hooks: Vec::new(),
+            deadline,
+        })
+    }
+
+    /// Starts the time budget of the plugin hook (or the plugin source) about to run.
+    fn start_time_budget(&self) {
+        self.deadline.set(Instant::now() + PLUGIN_TIME_BUDGET);
+    }
+
+    /// Loads the hooks defined in the config file and in the lua files present in the plugins
+    /// folder (in alphabetical order) into a new sandboxed lua instance.
+    ///
+    /// # Returns
+    ///
+    /// Returns the loaded plugins or `None` if no hooks were defined. If the plugins fail to load
+    /// the error is logged and `None` is returned so that searches still work without them.
+    fn load() -> Option<Self> {
+        let load_all = || -> Result<Self, Box<dyn std::error::Error>> {
+            let mut plugins = Self::new()?;
+            plugins.load_source(
+                "config.lua",
+                &fs::read_to_string(file_path(FileType::Config)?)?,
+            )?;
+
+            if let Ok(plugins_folder) = file_path(FileType::Plugins) {
+                let mut plugin_paths: Vec<PathBuf> = fs::read_dir(plugins_folder)?
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "lua"))
+                    .collect();
+                plugin_paths.sort();
+
+                for path in plugin_paths {
+                    plugins
+                        .load_source(&path.display().to_string(), &fs::read_to_string(&path)?)?;
+                }
+            }
+
+            Ok(plugins)
+        };
+
+        match load_all() {
+            Ok(plugins) if plugins.query_hooks.is_empty() && plugins.result_hooks.is_empty() => {
+                None
+            }
+            Ok(plugins) => {
+                log::info!(
+                    "Loaded {} `on_query` and {} `on_result` plugin hooks",
+                    plugins.query_hooks.len(),
+                    plugins.result_hooks.len()
+                );
+                Some(plugins)
+            }
+            Err(error) => {
+                log::error!("Plugin Error: failed to load the plugins: {error}");
+                None
+            }
+        }
+    }
+
+    /// Runs the provided lua source in its own environment table (so plugins cannot overwrite each
+    /// other's hooks) and registers the `on_query` and `on_result` hooks it defines.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - It takes the name of the file the source was read from.
+    /// * `source` - It takes the lua source code as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns a lua error if the source fails to run.
+    fn load_source(&mut self, name: &str, source: &str) -> mlua::Result<()> {
+        let environment = self.lua.create_table()?;
+        let metatable = self.lua.create_table()?;
+        metatable.set("__index", self.lua.globals())?;
+        environment.set_metatable(Some(metatable));
+
+        self.start_time_budget();
+        self.lua
+            .load(source)
+            .set_name(name)
+            .set_environment(environment.clone())
+            .exec()?;
+
+        if let Some(hook) = environment.get::<_, Option<Function<'_>>>("on_query")? {
+            self.query_hooks
+                .push((name.to_owned(), self.lua.create_registry_value(hook)?));
+        }
+        if let Some(hook) = environment.get::<_, Option<Function<'_>>>("on_result")? {
+            self.result_hooks
+                .push((name.to_owned(), self.lua.create_registry_value(hook)?));
+        }
+
+        Ok(())
+    }
+
+    /// Runs all the `on_query` hooks one after the other. A hook can return a new query string to
+    /// rewrite the query or `nil` to keep it unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query provided by the user as an argument.
+    fn on_query(&self, query: &str) -> String {
+        let mut query = query.to_owned();
+
+        for (name, key) in &self.query_hooks {
+            self.start_time_budget();
+            let rewritten = self
+                .lua
+                .registry_value::<Function<'_>>(key)
+                .and_then(|hook| hook.call::<_, Option<String>>(query.as_str()));
+
+            match rewritten {
+                Ok(Some(rewritten)) if !rewritten.trim().is_empty() => query = rewritten,
+                Ok(_) => (),
+                Err(error) => {
+                    log::error!("Plugin Error: `on_query` hook in {name} failed: {error}")
+                }
+            }
+        }
+
+        query
+    }
+
+    /// Runs all the `on_result` hooks on a search result one after the other. A hook receives the
+    /// result as a table which it can modify in place or replace by returning a new table, it can
+    /// return `false` to drop the result or `nil` to keep the (modified) result.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - It takes the search result to run the hooks on as an argument.
+    ///
+    /// # Returns
+    ///
+    /// Returns the rewritten search result or `None` if a hook dropped it.
+    fn on_result(&self, mut result: SearchResult) -> Option<SearchResult> {
+        for (name, key) in &self.result_hooks {
+            self.start_time_budget();
+            let outcome = self
+                .lua
+                .registry_value::<Function<'_>>(key)
+                .and_then(|hook| {
+                    let table = search_result_to_table(&self.lua, &result)?;
+                    match hook.call::<_, Value<'_>>(table.clone())? {
+                        Value::Boolean(false) => Ok(None),
+                        Value::Table(returned) => {
+                            update_search_result(&mut result, &returned).map(Some)
+                        }
+                        _ => update_search_result(&mut result, &table).map(Some),
+                    }
+                });
+
+            match outcome {
+                Ok(Some(())) => (),
+                Ok(None) => return None,
+                Err(error) => {
+                    log::error!("Plugin Error: `on_result` hook in {name} failed: {error}")
+                }
+            }
+        }
+
+        Some(result)
+    }
+}
+
+/// Converts the search result into a lua table which is passed to the `on_result` hooks.
+///
+/// # Arguments
+///
+/// * `lua` - It takes the lua instance in which the table is created.
+/// * `result` - It takes the search result to convert as an argument.
+fn search_result_to_table<'lua>(
+    lua: &'lua Lua,
+    result: &SearchResult,
+) -> mlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    table.set("title", result.title.as_str())?;
+    table.set("url", result.url.as_str())?;
+    table.set("description", result.description.as_str())?;
+    table.set("engines", result.engine.clone())?;
+    table.set("score", result.relevance_score)?;
+    Ok(table)
+}
+
+/// Updates the search result with the fields present in the lua table returned or modified by an
+/// `on_result` hook. Fields missing from the table are left untouched, the title and description
+/// set by the hook are sanitized like the ones scraped from the upstream search engines.
+///
+/// # Arguments
+///
+/// * `result` - It takes the search result to update as an argument.
+/// * `table` - It takes the lua table returned or modified by the hook as an argument.
+fn update_search_result(result: &mut SearchResult, table: &Table<'_>) -> mlua::Result<()> {
+    if let Some(title) = table.get::<_, Option<String>>("title")? {
+        result.title = sanitize_html(&title);
+    }
+    if let Some(url) = table.get::<_, Option<String>>("url")? {
+        result.url = url;
+    }
+    if let Some(description) = table.get::<_, Option<String>>("description")? {
+        result.description = sanitize_html(&description);
+    }
+    if let Some(engines) = table.get::<_, Option<Vec<String>>>("engines")? {
+        result.engine = engines;
+    }
+    if let Some(score) = table.get::<_, Option<f32>>("score")? {
+        result.relevance_score = score;
+    }
+    Ok(())
+}
+
+/// Runs the `on_query` plugin hooks of the current worker on the search query.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query provided by the user as an argument.
+///
+/// # Returns
+///
+/// Returns the query rewritten by the hooks or the unchanged query if no hooks are defined.
+pub fn run_query_hooks(query: &str) -> String {
+    PLUGINS.with(|plugins| match plugins {
+        Some(plugins) => plugins.on_query(query),
+        None => query.to_owned(),
+    })
+}
+
+/// Runs the `on_result` plugin hooks of the current worker on every aggregated search result and
+/// sorts the results again as the hooks may have changed their scores.
+///
+/// # Arguments
+///
+/// * `search_results` - It takes the aggregated search results as an argument.
+pub fn run_result_hooks(search_results: &mut SearchResults) {
+    PLUGINS.with(|plugins| {
+        if let Some(plugins) = plugins {
+            apply_result_hooks(plugins, search_results);
+        }
+    })
+}
+
+/// A helper function which runs the `on_result` hooks of the provided plugins on every search
+/// result and sorts them again by their scores.
+///
+/// # Arguments
+///
+/// * `plugins` - It takes the loaded plugins as an argument.
+/// * `search_results` - It takes the aggregated search results as an argument.
+fn apply_result_hooks(plugins: &Plugins, search_results: &mut SearchResults) {
+    let mut results: Box<[SearchResult]> = std::mem::take(&mut search_results.results)
+        .into_vec()
+        .into_iter()
+        .filter_map(|result| plugins.on_result(result))
+        .collect();
+    sort_search_results(&mut results);
+    search_results.results = results;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A helper function which creates plugins from the provided lua source.
+    fn plugins_from_source(source: &str) -> Plugins {
+        let mut plugins = Plugins::new().unwrap();
+        plugins.load_source("test.lua", source).unwrap();
+        plugins
+    }
+
+    #[test]
+    fn test_query_hooks_rewrite_query() {
+        let plugins = plugins_from_source(
+            r#"
+            function on_query(query)
+                if query == "!rs" then
+                    return "rust programming language"
+                end
+            end
+            "#,
+        );
+
+        assert_eq!(plugins.on_query("!rs"), "rust programming language");
+        assert_eq!(plugins.on_query("websurfx"), "websurfx");
+    }
+
+    #[test]
+    fn test_result_hooks_drop_and_rewrite_results() {
+        let plugins = plugins_from_source(
+            r#"
+            function on_result(result)
+                if string.find(result.url, "pinterest", 1, true) then
+                    return false
+                end
+                result.url = string.gsub(result.url, "^http://", "https://")
+                result.score = result.score + 1
+            end
+            "#,
+        );
+
+        let mut search_results = SearchResults::new(
+            Box::new([
+                SearchResult::new("Pins", "https://www.pinterest.com/", "", &["bing"]),
+                SearchResult::new("Rust", "http://www.rust-lang.org/", "", &["bing"]),
+            ]),
+            Box::new([]),
+            Box::new([]),
+        );
+        apply_result_hooks(&plugins, &mut search_results);
+
+        assert_eq!(search_results.results.len(), 1);
+        assert_eq!(search_results.results[0].url, "https://www.rust-lang.org/");
+        assert_eq!(search_results.results[0].relevance_score, 1.0);
+    }
+
+    #[test]
+    fn test_plugins_are_sandboxed() {
+        let mut plugins = Plugins::new().unwrap();
+
+        assert!(plugins
+            .load_source("test.lua", r#"io.open("/etc/passwd")"#)
+            .is_err());
+        assert!(plugins
+            .load_source("test.lua", r#"dofile("/etc/passwd")"#)
+            .is_err());
+        assert!(plugins
+            .load_source("test.lua", "while true do end")
+            .is_err());
+    }
+
+    #[test]
+    fn test_endless_hooks_are_stopped() {
+        let plugins = plugins_from_source(
+            r#"
+            function on_query(query)
+                while true do end
+            end
+            function on_result(result)
+                while true do end
+            end
+            "#,
+        );
+
+        let started = Instant::now();
+        assert_eq!(plugins.on_query("rust"), "rust");
+        let mut search_results = SearchResults::new(
+            Box::new([SearchResult::new(
+                "Rust",
+                "https://www.rust-lang.org/",
+                "",
+                &["bing"],
+            )]),
+            Box::new([]),
+            Box::new([]),
+        );
+        apply_result_hooks(&plugins, &mut search_results);
+
+        // The hooks are stopped and the search still gets its results.
+        assert_eq!(search_results.results.len(), 1);
+        assert!(started.elapsed() < PLUGIN_TIME_BUDGET * 10);
+    }
+}
@@ -0,0 +1,281 @@
+//! This module provides the local safe search classifier, which scores every search result
+//! against the keyword and domain lists of the safe search levels shipped in the `safe_search`
+//! folder of the config directory. As the upstream search engines implement their own safe search
+//! differently (or not at all), the classifier makes the same safe search level remove comparable
+//! results no matter which engines are selected.
+//!
+//! Every level has its own list (`level_1.txt` to `level_3.txt`) and a level uses the lists of
+//! all the levels up to it, so that the higher levels are always stricter. Levels 3 and 4 use the
+//! same lists, as level 4 only additionally disallows the queries matching the blocklist. Every
+//! line of a list holds a rule:
+//!
+//! * `keyword` or `some phrase` - scores the results whose title, url or description contain the
+//!   keyword or phrase as whole words (compared case insensitively).
+//! * `||example.com^` (or any other rule supported by the domain filter lists) - scores the
+//!   results of the domain and its subdomains.
+//! * `$weight=0.5` can be appended to a rule to change its score (which defaults to 1).
+//! * Lines starting with `!` or `#` are comments.
+//!
+//! A result is removed once the scores of all the rules matching it add up to
+//! `UNSAFE_SCORE_THRESHOLD`.
+
+use std::{fs, path::Path};
+
+use super::domain_filter::{is_supported_rule, DomainFilter};
+use crate::models::aggregation_models::SearchResult;
+
+/// A constant holding the score at and above which a search result is considered unsafe.
+pub const UNSAFE_SCORE_THRESHOLD: f32 = 1.0;
+
+/// A constant holding the highest safe search level with its own list, the higher levels use the
+/// lists of all the levels up to it.
+const MAX_CLASSIFIER_LEVEL: u8 = 3;
+
+/// A named struct which stores the parsed rules of the list of a safe search level.
+#[derive(Default, Clone)]
+struct LevelList {
+    /// The keywords and phrases split into lowercase words along with their scores.
+    keywords: Vec<(Vec<String>, f32, String)>,
+    /// The domain rules along with their scores.
+    domains: Vec<(DomainFilter, f32)>,
+}
+
+/// A named struct which stores the lists of all the safe search levels.
+#[derive(Default, Clone)]
+pub struct SafeSearchClassifier {
+    /// The lists of the safe search levels 1 to `MAX_CLASSIFIER_LEVEL`.
+    levels: Vec<LevelList>,
+}
+
+/// A named struct which stores the score of a search result along with the rules which matched
+/// it.
+#[derive(Debug, Default, PartialEq)]
+pub struct Classification {
+    /// The sum of the scores of the matching rules.
+    pub score: f32,
+    /// The matching rules.
+    pub rules: Vec<String>,
+}
+
+impl Classification {
+    /// Returns whether the search result should be removed at the classified safe search level.
+    pub fn is_unsafe(&self) -> bool {
+        self.score >= UNSAFE_SCORE_THRESHOLD
+    }
+}
+
+impl SafeSearchClassifier {
+    /// Parses the lists of the safe search levels.
+    ///
+    /// # Arguments
+    ///
+    /// * `sources` - It takes the contents of the lists of the levels 1 to
+    ///   `MAX_CLASSIFIER_LEVEL` in order as an argument.
+    ///
+    /// # Returns
+    ///
+    /// Returns the classifier along with the level and the line numbers of the rules which are
+    /// not supported and were skipped.
+    pub fn parse(sources: &[&str]) -> (Self, Vec<(u8, usize)>) {
+        let mut unsupported_lines = Vec::new();
+        let mut levels = Vec::with_capacity(sources.len());
+
+        for (level, source) in (1..).zip(sources) {
+            let mut list = LevelList::default();
+            for (index, line) in source.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with(['!', '#']) {
+                    continue;
+                }
+
+                let (rule, score) = match line.rsplit_once("$weight=") {
+                    Some((rule, weight)) => match weight.trim().parse::<f32>() {
+                        Ok(score) if score.is_finite() => (rule.trim(), score),
+                        _ => {
+                            unsupported_lines.push((level, index + 1));
+                            continue;
+                        }
+                    },
+                    None => (line, 1.0),
+                };
+
+                if rule.starts_with("||") || rule.contains("://") || rule.starts_with("*.") {
+                    if !is_supported_rule(rule) {
+                        unsupported_lines.push((level, index + 1));
+                        continue;
+                    }
+                    let mut domains = DomainFilter::default();
+                    domains.extend(rule, rule);
+                    list.domains.push((domains, score));
+                } else {
+                    let words = words(rule);
+                    if words.is_empty() {
+                        unsupported_lines.push((level, index + 1));
+                        continue;
+                    }
+                    list.keywords.push((words, score, rule.to_lowercase()));
+                }
+            }
+            levels.push(list);
+        }
+
+        (Self { levels }, unsupported_lines)
+    }
+
+    /// Reads and parses the lists of the safe search levels from the provided folder. The
+    /// missing lists are treated as empty lists.
+    ///
+    /// # Arguments
+    ///
+    /// * `folder` - It takes the path of the folder holding the lists as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the classifier on success otherwise returns an io error if a list exists but
+    /// cannot be read.
+    pub fn load(folder: &str) -> Result<Self, std::io::Error> {
+        let mut sources = Vec::new();
+        for path in list_paths(folder) {
+            sources.push(match fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(error) => return Err(error),
+            });
+        }
+
+        let (classifier, unsupported_lines) =
+            Self::parse(&sources.iter().map(String::as_str).collect::<Vec<_>>());
+        for (level, line) in unsupported_lines {
+            log::warn!("Skipped the unsupported rule on line {line} of the safe search list of level {level}");
+        }
+        Ok(classifier)
+    }
+
+    /// Scores the search result against the lists of all the levels up to the safe search level.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - It takes the search result to be classified as an argument.
+    /// * `safe_search` - It takes the safe search level as an argument.
+    pub fn classify(&self, result: &SearchResult, safe_search: u8) -> Classification {
+        let mut classification = Classification::default();
+        let levels = safe_search.min(MAX_CLASSIFIER_LEVEL) as usize;
+        if levels == 0 {
+            return classification;
+        }
+
+        let text = words(&format!(
+            "{} {} {}",
+            result.title, result.url, result.description
+        ));
+
+        for list in self.levels.iter().take(levels) {
+            for (keyword, score, rule) in &list.keywords {
+                if text
+                    .windows(keyword.len())
+                    .any(|window| window == keyword.as_slice())
+                {
+                    classification.score += score;
+                    classification.rules.push(rule.clone());
+                }
+            }
+            for (domains, score) in &list.domains {
+                if let Some(blocking_rule) = domains.blocking_rule(&result.url) {
+                    classification.score += score;
+                    classification.rules.push(blocking_rule.rule);
+                }
+            }
+        }
+
+        classification
+    }
+
+    /// Returns the number of rules of all the levels.
+    pub fn len(&self) -> usize {
+        self.levels
+            .iter()
+            .map(|list| list.keywords.len() + list.domains.len())
+            .sum()
+    }
+
+    /// Returns whether no level has any rules.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Returns the paths of the lists of the safe search levels in the provided folder.
+///
+/// # Arguments
+///
+/// * `folder` - It takes the path of the folder holding the lists as an argument.
+pub fn list_paths(folder: &str) -> Vec<String> {
+    (1..=MAX_CLASSIFIER_LEVEL)
+        .map(|level| {
+            Path::new(folder)
+                .join(format!("level_{level}.txt"))
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect()
+}
+
+/// Splits the text into lowercase words, treating every character which is not alphanumeric as a
+/// separator.
+///
+/// # Arguments
+///
+/// * `text` - It takes the text to be split as an argument.
+fn words(text: &str) -> Vec<String> {
+    text.split(|character: char| !character.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_uses_the_lists_up_to_the_level() {
+        let (classifier, unsupported_lines) = SafeSearchClassifier::parse(&[
+            "! Level 1\nexplicit phrase\n||adult.example^\n",
+            "racy$weight=0.5\nrisque$weight=0.5\ncasino$weight=abc\n",
+            "gambling\n",
+        ]);
+        assert_eq!(unsupported_lines, [(2, 3)]);
+        assert_eq!(classifier.len(), 5);
+
+        let result = |title: &str, url: &str| SearchResult::new(title, url, "", &["bing"]);
+
+        let explicit = result("An Explicit Phrase here", "https://example.com/");
+        assert!(!classifier.classify(&explicit, 0).is_unsafe());
+        assert!(classifier.classify(&explicit, 1).is_unsafe());
+        // Only whole words are matched.
+        assert!(!classifier
+            .classify(&result("inexplicit phrasebook", "https://example.com/"), 4)
+            .is_unsafe());
+
+        let domain = result("Nothing to see", "https://www.adult.example/page");
+        assert_eq!(
+            classifier.classify(&domain, 2),
+            Classification {
+                score: 1.0,
+                rules: vec!["adult.example".to_owned()]
+            }
+        );
+
+        // The scores of the rules add up.
+        let racy = result("racy", "https://example.com/");
+        assert!(!classifier.classify(&racy, 2).is_unsafe());
+        let racy_and_risque = result("racy and risque", "https://example.com/");
+        assert!(!classifier.classify(&racy_and_risque, 1).is_unsafe());
+        assert!(classifier.classify(&racy_and_risque, 2).is_unsafe());
+
+        let gambling = result("Gambling", "https://example.com/");
+        assert!(!classifier.classify(&gambling, 2).is_unsafe());
+        assert!(classifier.classify(&gambling, 3).is_unsafe());
+        assert!(classifier.classify(&gambling, 4).is_unsafe());
+    }
+}
@@ -0,0 +1,138 @@
+//! This module provides the functionality to sanitize the html markup in the titles and the
+//! descriptions of the search results scraped from the upstream search engines, so that only the
+//! harmless inline tags used for highlighting the query are kept and everything else is escaped.
+
+use scraper::{ElementRef, Html, Node};
+
+/// A constant holding the inline tags which are kept in the sanitized html. These tags are used by
+/// the upstream search engines to highlight the query in the search results. All the attributes of
+/// these tags are removed.
+const ALLOWED_TAGS: [&str; 8] = ["b", "strong", "em", "i", "mark", "u", "sub", "sup"];
+
+/// A constant holding the tags which are removed from the sanitized html along with all their
+/// content, as their content is never meant to be displayed as text.
+const DROPPED_TAGS: [&str; 12] = [
+    "script", "style", "noscript", "template", "iframe", "object", "embed", "svg", "math",
+    "textarea", "select", "title",
+];
+
+/// A constant holding the maximum depth of nested elements which is sanitized. The content nested
+/// deeper is dropped.
+const MAX_NESTING_DEPTH: usize = 32;
+
+/// Sanitizes the provided html fragment by keeping only the allowed inline tags (without any
+/// attributes), removing the tags listed in `DROPPED_TAGS` along with their content, unwrapping
+/// all the other tags and escaping the text.
+///
+/// # Arguments
+///
+/// * `html` - It takes the html fragment scraped from the upstream search engine as an argument.
+///
+/// # Returns
+///
+/// Returns the sanitized html which is safe to be rendered without escaping.
+pub fn sanitize_html(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut sanitized_html = String::with_capacity(html.len());
+    sanitize_children(fragment.root_element(), &mut sanitized_html, 0);
+    sanitized_html
+}
+
+/// Recursively writes the sanitized children of the provided element to the output string.
+///
+/// # Arguments
+///
+/// * `element` - It takes the element whose children should be sanitized as an argument.
+/// * `output` - It takes the string to which the sanitized html is written as an argument.
+/// * `depth` - It takes the nesting depth of the element as an argument.
+fn sanitize_children(element: ElementRef<'_>, output: &mut String, depth: usize) {
+    if depth > MAX_NESTING_DEPTH {
+        return;
+    }
+
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => escape_html(text, output),
+            Node::Element(child_element) => {
+                let name = child_element.name();
+                let Some(child) = ElementRef::wrap(child) else {
+                    continue;
+                };
+
+                if ALLOWED_TAGS.contains(&name) {
+                    output.push_str(&format!("<{name}>"));
+                    sanitize_children(child, output, depth + 1);
+                    output.push_str(&format!("</{name}>"));
+                } else if !DROPPED_TAGS.contains(&name) {
+                    sanitize_children(child, output, depth + 1);
+                }
+            }
+            // Comments, doctypes and processing instructions are removed.
+            _ => (),
+        }
+    }
+}
+
+/// Escapes the html special characters in the provided text and writes it to the output string.
+///
+/// # Arguments
+///
+/// * `text` - It takes the text to be escaped as an argument.
+/// * `output` - It takes the string to which the escaped text is written as an argument.
+fn escape_html(text: &str, output: &mut String) {
+    for character in text.chars() {
+        match character {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            _ => output.push(character),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_html() {
+        let cases = [
+            // The highlighting tags are kept without their attributes.
+            (
+                r#"Learn <b>Rust</b> with <strong class="x" onclick="evil()">examples</strong>"#,
+                "Learn <b>Rust</b> with <strong>examples</strong>",
+            ),
+            (
+                r#"Rust <b onclick="alert(1)">lang</b><script>alert(document.cookie)</script>"#,
+                "Rust <b>lang</b>",
+            ),
+            // The scripts, the embedded content and the comments are removed while the other
+            // tags are unwrapped.
+            (
+                r#"<img src=x onerror="alert(1)">Hello<script>alert(document.cookie)</script> <a href="javascript:alert(1)">world</a><!-- comment --><style>body{display:none}</style>"#,
+                "Hello world",
+            ),
+            (
+                r#"<em><iframe src="https://evil.example"></iframe>safe</em>"#,
+                "<em>safe</em>",
+            ),
+            (
+                r#"<img src=x onerror="alert(1)">A <em onmouseover="alert(1)">safe</em> language<iframe src="javascript:alert(1)"></iframe><!-- <script>alert(1)</script> -->"#,
+                "A <em>safe</em> language",
+            ),
+            // The text is escaped.
+            (
+                "Vec&lt;String&gt; &amp; \"quotes\" 'single'",
+                "Vec&lt;String&gt; &amp; &quot;quotes&quot; &#39;single&#39;",
+            ),
+            // The sanitized html is left unchanged.
+            ("<b>a &lt;script&gt;</b>", "<b>a &lt;script&gt;</b>"),
+        ];
+
+        for (html, sanitized_html) in cases {
+            assert_eq!(sanitize_html(html), sanitized_html, "{html}");
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! This module provides the functionality to clean the urls of the search results scraped from the
+//! upstream search engines by unwrapping the known upstream redirect wrappers and removing the
+//! tracking parameters from them.
+
+use base64::{
+    alphabet,
+    engine::{general_purpose::GeneralPurpose, DecodePaddingMode, GeneralPurposeConfig},
+    Engine,
+};
+use reqwest::Url;
+
+/// A constant holding the default tracking parameters removed from the result urls when none are
+/// provided via the config file. A trailing `*` matches all the parameters starting with the
+/// given prefix.
+pub const DEFAULT_TRACKING_PARAMETERS: [&str; 13] = [
+    "utm_*", "fbclid", "gclid", "gclsrc", "dclid", "msclkid", "yclid", "mc_cid", "mc_eid",
+    "_hsenc", "_hsmi", "igshid", "_ga",
+];
+
+/// A constant holding the maximum number of nested redirect wrappers that are unwrapped.
+const MAX_UNWRAP_DEPTH: usize = 3;
+
+/// The base64 engine used to decode the urls wrapped by Bing, which are url safe base64 encoded
+/// with the padding usually left out.
+const BING_BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Cleans the provided url by unwrapping the known upstream redirect wrappers and then removing
+/// the tracking parameters from it.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the search result as an argument.
+/// * `tracking_parameters` - It takes the names of the tracking parameters to be removed as an
+///   argument. A trailing `*` matches all the parameters starting with the given prefix.
+///
+/// # Returns
+///
+/// Returns the cleaned url or the url unchanged if it could not be parsed.
+pub fn clean_url(url: &str, tracking_parameters: &[String]) -> String {
+    // Some engines provide protocol relative urls (like `//duckduckgo.com/l/?uddg=`).
+    let parsed_url = match url.strip_prefix("//") {
+        Some(url) => Url::parse(&format!("https://{url}")),
+        None => Url::parse(url),
+    };
+
+    let Ok(mut parsed_url) = parsed_url else {
+        return url.to_owned();
+    };
+
+    for _ in 0..MAX_UNWRAP_DEPTH {
+        match unwrap_redirect(&parsed_url) {
+            Some(unwrapped_url) => parsed_url = unwrapped_url,
+            None => break,
+        }
+    }
+
+    remove_tracking_parameters(&mut parsed_url, tracking_parameters);
+
+    parsed_url.into()
+}
+
+/// Unwraps the url if it is a known upstream redirect wrapper.
+///
+/// # Arguments
+///
+/// * `url` - It takes the parsed url as an argument.
+///
+/// # Returns
+///
+/// Returns the wrapped destination url or `None` if the url is not a known redirect wrapper.
+fn unwrap_redirect(url: &Url) -> Option<Url> {
+    let host = url.host_str()?;
+    let query_parameter = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+
+    let destination = if is_host(host, "duckduckgo.com") && url.path() == "/l/" {
+        // DuckDuckGo: `//duckduckgo.com/l/?uddg=<percent encoded url>&rut=...`
+        query_parameter("uddg")?
+    } else if is_host(host, "bing.com") && url.path() == "/ck/a" {
+        // Bing: `https://www.bing.com/ck/a?!&&p=...&u=a1<base64 encoded url>&ntb=1`
+        let encoded = query_parameter("u")?;
+        String::from_utf8(BING_BASE64.decode(encoded.strip_prefix("a1")?).ok()?).ok()?
+    } else if host.split('.').any(|label| label == "google") && url.path() == "/url" {
+        // Google (proxied by engines like Searx and Startpage): `https://www.google.com/url?q=<url>`
+        query_parameter("q").or_else(|| query_parameter("url"))?
+    } else {
+        return None;
+    };
+
+    Url::parse(&destination)
+        .ok()
+        .filter(|destination| matches!(destination.scheme(), "http" | "https"))
+}
+
+/// Removes the tracking parameters from the query of the url. The url is left untouched if it does
+/// not contain any tracking parameters, so that the encoding of its query is preserved.
+///
+/// # Arguments
+///
+/// * `url` - It takes the parsed url as an argument.
+/// * `tracking_parameters` - It takes the names of the tracking parameters to be removed as an
+///   argument.
+fn remove_tracking_parameters(url: &mut Url, tracking_parameters: &[String]) {
+    let is_tracking_parameter = |key: &str| {
+        let key = key.to_lowercase();
+        tracking_parameters
+            .iter()
+            .any(|parameter| match parameter.strip_suffix('*') {
+                Some(prefix) => key.starts_with(prefix),
+                None => key == *parameter,
+            })
+    };
+
+    if !url
+        .query_pairs()
+        .any(|(key, _)| is_tracking_parameter(&key))
+    {
+        return;
+    }
+
+    let remaining_pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !is_tracking_parameter(key))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+
+    if remaining_pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(remaining_pairs);
+    }
+}
+
+/// Checks whether the host is the provided domain or one of its subdomains.
+///
+/// # Arguments
+///
+/// * `host` - It takes the host of the url as an argument.
+/// * `domain` - It takes the domain to check against as an argument.
+fn is_host(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|subdomain| subdomain.ends_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A helper function which returns the default tracking parameters as owned strings.
+    fn tracking_parameters() -> Vec<String> {
+        DEFAULT_TRACKING_PARAMETERS
+            .iter()
+            .map(|parameter| parameter.to_string())
+            .collect()
+    }
+
+    /// A helper function which asserts that every wrapped url fixture is cleaned to the url of its
+    /// destination.
+    fn assert_unwrapped(fixtures: &[(&str, &str)]) {
+        for (wrapped_url, destination) in fixtures {
+            assert_eq!(
+                clean_url(wrapped_url, &tracking_parameters()),
+                *destination,
+                "{wrapped_url}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unwrap_duckduckgo_redirect() {
+        assert_unwrapped(&[
+            (
+                "//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust%2Dlang.org%2Flearn%3Fref%3Dddg&rut=a1b2c3",
+                "https://www.rust-lang.org/learn?ref=ddg",
+            ),
+            // The html version of DuckDuckGo puts the wrapped url last.
+            (
+                "https://html.duckduckgo.com/l/?kh=-1&uddg=https%3A%2F%2Fdoc.rust-lang.org%2Fbook%2Fch01-00-getting-started.html%23installation",
+                "https://doc.rust-lang.org/book/ch01-00-getting-started.html#installation",
+            ),
+            (
+                "//duckduckgo.com/l/?uddg=https%3A%2F%2Fcrates.io%2Fsearch%3Fq%3Dasync%2520runtime%26utm_source%3Dddg&rut=0f9e",
+                "https://crates.io/search?q=async+runtime",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_unwrap_bing_redirect() {
+        assert_unwrapped(&[
+            (
+                "https://www.bing.com/ck/a?!&&p=5f1e2d&ptn=3&u=a1aHR0cHM6Ly93d3cucnVzdC1sYW5nLm9yZy8&ntb=1",
+                "https://www.rust-lang.org/",
+            ),
+            // The padding is kept by some of the wrapped urls.
+            (
+                "https://www.bing.com/ck/a?!&&p=9c2a&ptn=3&ver=2&hsh=3&u=a1aHR0cHM6Ly93d3cucnVzdC1sYW5nLm9yZy9sZWFybg==&ntb=1",
+                "https://www.rust-lang.org/learn",
+            ),
+            // The url safe alphabet uses `-` and `_` instead of `+` and `/`.
+            (
+                "https://bing.com/ck/a?!&&p=77&u=a1aHR0cHM6Ly9kb2NzLnJzL3Rva2lvL2xhdGVzdC90b2tpby8_c2VhcmNoPXNwYXdufg&ntb=1",
+                "https://docs.rs/tokio/latest/tokio/?search=spawn~",
+            ),
+            (
+                "https://www.bing.com/ck/a?!&&p=1&u=a1aHR0cHM6Ly9jcmF0ZXMuaW8vY3JhdGVzL3NlcmRlP3V0bV9zb3VyY2U9YmluZw&ntb=1",
+                "https://crates.io/crates/serde",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_unwrap_google_redirect() {
+        assert_unwrapped(&[
+            (
+                "https://www.google.com/url?q=https://www.rust-lang.org/%3Futm_source%3Dgoogle&sa=U",
+                "https://www.rust-lang.org/",
+            ),
+            // The regional domains and the `url` parameter are unwrapped as well.
+            (
+                "https://www.google.co.uk/url?sa=t&rct=j&url=https%3A%2F%2Fdoc.rust-lang.org%2Fstd%2F&ved=2ahUKEwi",
+                "https://doc.rust-lang.org/std/",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_unwrap_nested_and_unsafe_redirects() {
+        assert_unwrapped(&[
+            // A Google redirect wrapped by DuckDuckGo.
+            (
+                "//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.google.com%2Furl%3Fq%3Dhttps%3A%2F%2Fwww.rust-lang.org%2Fcommunity%26sa%3DU&rut=9d",
+                "https://www.rust-lang.org/community",
+            ),
+            // Non http destinations are never unwrapped.
+            (
+                "https://www.google.com/url?q=javascript:alert(1)",
+                "https://www.google.com/url?q=javascript:alert(1)",
+            ),
+            (
+                "https://www.bing.com/ck/a?!&&u=a1not-base64!&ntb=1",
+                "https://www.bing.com/ck/a?!&&u=a1not-base64!&ntb=1",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_remove_tracking_parameters() {
+        assert_eq!(
+            clean_url(
+                "https://example.com/page?id=42&utm_source=news&utm_medium=email&fbclid=abc&GCLID=def#top",
+                &tracking_parameters()
+            ),
+            "https://example.com/page?id=42#top"
+        );
+        assert_eq!(
+            clean_url(
+                "https://example.com/page?utm_campaign=spring",
+                &tracking_parameters()
+            ),
+            "https://example.com/page"
+        );
+    }
+
+    #[test]
+    fn test_clean_url_keeps_untouched_urls() {
+        let url = "https://example.com/search?q=rust+lang&page=2";
+        assert_eq!(clean_url(url, &tracking_parameters()), url);
+        assert_eq!(clean_url("not a url", &tracking_parameters()), "not a url");
+        // Non http destinations are not unwrapped.
+        let url = "https://duckduckgo.com/l/?uddg=javascript%3Aalert(1)";
+        assert_eq!(clean_url(url, &tracking_parameters()), url);
+    }
+}
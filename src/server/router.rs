@@ -5,20 +5,33 @@
 use crate::{
     config::parser::Config,
     handler::{file_path, FileType},
+    models::server_models::{
+        preferences_token, BlockSiteParams, Cookie, COOKIE_NAME, PREFERENCES_TOKEN_PARAM,
+    },
 };
-use actix_web::{get, http::header::ContentType, web, HttpRequest, HttpResponse};
+use actix_web::{
+    cookie::{time::Duration, Cookie as HttpCookie, SameSite},
+    get,
+    http::header::{self, ContentType},
+    post, web, HttpRequest, HttpResponse,
+};
+use reqwest::Url;
+use std::{borrow::Cow, collections::HashMap};
 use tokio::fs::read_to_string;
 
 /// Handles the route of index page or main page of the `websurfx` meta search engine website.
 #[get("/")]
 pub async fn index(
+    req: HttpRequest,
     config: web::Data<&'static Config>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let preferences = Cookie::from_request(&req, &config);
     Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
         crate::templates::views::index::index(
-            &config.style.colorscheme,
-            &config.style.theme,
-            &config.style.animation,
+            &preferences.colorscheme,
+            &preferences.theme,
+            preferences.animation.as_deref(),
+            preferences_token(&req, &config).as_deref(),
         )
         .0,
     ))
@@ -27,13 +40,15 @@ pub async fn index(
 /// Handles the route of any other accessed route/page which is not provided by the
 /// website essentially the 404 error page.
 pub async fn not_found(
+    req: HttpRequest,
     config: web::Data<&'static Config>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let preferences = Cookie::from_request(&req, &config);
     Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
         crate::templates::views::not_found::not_found(
-            &config.style.colorscheme,
-            &config.style.theme,
-            &config.style.animation,
+            &preferences.colorscheme,
+            &preferences.theme,
+            preferences.animation.as_deref(),
         )
         .0,
     ))
@@ -52,13 +67,15 @@ pub async fn robots_data(_req: HttpRequest) -> Result<HttpResponse, Box<dyn std:
 /// Handles the route of about page of the `websurfx` meta search engine website.
 #[get("/about")]
 pub async fn about(
+    req: HttpRequest,
     config: web::Data<&'static Config>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let preferences = Cookie::from_request(&req, &config);
     Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
         crate::templates::views::about::about(
-            &config.style.colorscheme,
-            &config.style.theme,
-            &config.style.animation,
+            &preferences.colorscheme,
+            &preferences.theme,
+            preferences.animation.as_deref(),
         )
         .0,
     ))
@@ -67,16 +84,191 @@ pub async fn about(
 /// Handles the route of settings page of the `websurfx` meta search engine website.
 #[get("/settings")]
 pub async fn settings(
+    req: HttpRequest,
     config: web::Data<&'static Config>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let mut preferences = Cookie::from_request(&req, &config);
+    let exported_preferences_token =
+        preferences.to_token(config.preferences_token_secret.as_deref())?;
+
+    let engine_names: HashMap<String, bool> = config
+        .upstream_search_engines
+        .keys()
+        .map(|engine| {
+            let selected = preferences
+                .engines
+                .iter()
+                .any(|selected_engine| selected_engine.eq_ignore_ascii_case(engine));
+            (engine.to_owned(), selected)
+        })
+        .collect();
+
+    // The safe search level of the admin takes precedence if it is set to 3 or 4.
+    if config.safe_search >= 3 {
+        preferences.safe_search_level = config.safe_search;
+    }
+    preferences.privacy_redirects = Some(
+        preferences
+            .privacy_redirects
+            .unwrap_or(config.privacy_redirects.enabled),
+    );
+    preferences.results_per_page = Some(
+        preferences
+            .results_per_page
+            .unwrap_or(config.results_per_page),
+    );
+
+    let cookie = req.cookie(COOKIE_NAME);
+
     Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
         crate::templates::views::settings::settings(
-            config.safe_search,
-            &config.style.colorscheme,
-            &config.style.theme,
-            &config.style.animation,
-            &config.upstream_search_engines,
+            &preferences,
+            &engine_names,
+            cookie.as_ref().map(|cookie| cookie.value()),
+            &exported_preferences_token,
         )?
         .0,
     ))
 }
+
+/// Handles the submission of the settings page form by saving the submitted preferences in the
+/// `appCookie` and redirecting back to the settings page, so that the preferences can be saved
+/// without any javascript. The forms submitted from other sites are rejected.
+#[post("/settings")]
+pub async fn save_settings(
+    req: HttpRequest,
+    form: web::Form<Vec<(String, String)>>,
+    config: web::Data<&'static Config>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    if !is_same_origin(&req) {
+        return Ok(cross_site_response());
+    }
+
+    let preferences = Cookie::from_settings_form(&form, &config);
+
+    Ok(HttpResponse::SeeOther()
+        .cookie(preferences_cookie(&preferences)?)
+        .insert_header((header::LOCATION, "/settings"))
+        .finish())
+}
+
+/// Handles the "hide this site" button of a search result by adding the site to the sites hidden
+/// by the user and redirecting back to the search page. The site is added to the preferences
+/// token when the search page was opened with one, otherwise it is saved in the `appCookie`. The
+/// forms submitted from other sites are rejected.
+#[post("/settings/blocked_domains")]
+pub async fn block_site(
+    req: HttpRequest,
+    form: web::Form<BlockSiteParams>,
+    config: web::Data<&'static Config>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    if !is_same_origin(&req) {
+        return Ok(cross_site_response());
+    }
+
+    let secret = config.preferences_token_secret.as_deref();
+    let token_preferences = form
+        .preferences
+        .as_deref()
+        .and_then(|token| Cookie::from_token(token, secret));
+    let is_token = token_preferences.is_some();
+
+    let mut preferences = match token_preferences {
+        Some(preferences) => preferences.validate(&config),
+        None => Cookie::from_request(&req, &config),
+    };
+    let mut blocked_domains = preferences.blocked_domains.into_owned();
+    blocked_domains.push(Cow::Borrowed(form.domain.as_str()));
+    preferences.blocked_domains = Cow::Owned(blocked_domains);
+    let preferences = preferences.validate(&config);
+
+    let mut params = vec![
+        ("q", form.q.clone()),
+        ("page", form.page.unwrap_or(1).to_string()),
+    ];
+    if let Some(safesearch) = form.safesearch {
+        params.push(("safesearch", safesearch.to_string()));
+    }
+    if is_token {
+        params.push((PREFERENCES_TOKEN_PARAM, preferences.to_token(secret)?));
+    }
+    let search_url = Url::parse_with_params("http://localhost/search", &params)?;
+    let location = format!(
+        "{}?{}",
+        search_url.path(),
+        search_url.query().unwrap_or_default()
+    );
+
+    let mut response = HttpResponse::SeeOther();
+    if !is_token {
+        response.cookie(preferences_cookie(&preferences)?);
+    }
+    Ok(response
+        .insert_header((header::LOCATION, location))
+        .finish())
+}
+
+/// A helper function which checks that a form was submitted from the pages of this server, so
+/// that other sites cannot change the preferences of the users (cross-site request forgery). The
+/// origin of the form is read from the `Origin` header or otherwise from the `Referer` header and
+/// compared with the host the request was sent to. The requests without both the headers are
+/// accepted, as the browsers always send one of them with the forms of other sites.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request submitting the form as an argument.
+///
+/// # Returns
+///
+/// Returns `true` if the form was submitted from the same origin otherwise returns `false`.
+fn is_same_origin(req: &HttpRequest) -> bool {
+    let headers = req.headers();
+    let Some(source) = headers
+        .get(header::ORIGIN)
+        .or_else(|| headers.get(header::REFERER))
+    else {
+        return true;
+    };
+
+    // The opaque `null` origin sent by sandboxed pages fails to parse and is rejected.
+    let Some(source) = source
+        .to_str()
+        .ok()
+        .and_then(|source| Url::parse(source).ok())
+    else {
+        return false;
+    };
+    // The scheme of the source is used for the host of the request as well, so that the servers
+    // behind a proxy terminating the https connections compare the same default ports.
+    let connection_info = req.connection_info();
+    Url::parse(&format!("{}://{}", source.scheme(), connection_info.host())).is_ok_and(|target| {
+        source.host_str() == target.host_str()
+            && source.port_or_known_default() == target.port_or_known_default()
+    })
+}
+
+/// A helper function which builds the response rejecting a form submitted from another site.
+fn cross_site_response() -> HttpResponse {
+    HttpResponse::Forbidden()
+        .content_type(ContentType::plaintext())
+        .body("The settings can only be changed from the settings page of this website.")
+}
+
+/// A helper function which builds the `appCookie` saving the user's preferences.
+///
+/// # Arguments
+///
+/// * `preferences` - It takes the preferences of the user as an argument.
+///
+/// # Error
+///
+/// Returns the cookie on success otherwise returns a serialization error.
+fn preferences_cookie(preferences: &Cookie<'_>) -> Result<HttpCookie<'static>, serde_json::Error> {
+    Ok(
+        HttpCookie::build(COOKIE_NAME, serde_json::to_string(preferences)?)
+            .path("/")
+            .max_age(Duration::days(365))
+            .same_site(SameSite::Lax)
+            .finish(),
+    )
+}
@@ -0,0 +1,185 @@
+//! This module handles the admin routes of the search engine website, which let the admins of the
+//! server inspect and purge the cache.
+
+use crate::{
+    cache::cacher::SharedCache, config::parser::Config, models::server_models::PurgeParams,
+};
+use actix_web::{get, http::header, post, web, HttpRequest, HttpResponse};
+
+/// Handles the route returning the statistics of the cache as json.
+///
+/// # Example
+///
+/// ```bash
+/// curl -H "Authorization: Bearer <token>" "http://127.0.0.1:8080/admin/cache/stats"
+/// ```
+#[get("/admin/cache/stats")]
+pub async fn cache_stats(
+    req: HttpRequest,
+    config: web::Data<&'static Config>,
+    cache: web::Data<&'static SharedCache>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    if let Err(response) = authorize(&req, &config) {
+        return Ok(response);
+    }
+    Ok(HttpResponse::Ok().json(cache.stats().await?))
+}
+
+/// Handles the route removing the cached results of the search query given with the `q`
+/// parameter, or all the cached results when it is left out, and returns the number of removed
+/// results as json.
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer <token>" "http://127.0.0.1:8080/admin/cache/purge?q=sweden"
+/// ```
+#[post("/admin/cache/purge")]
+pub async fn purge_cache(
+    req: HttpRequest,
+    params: web::Query<PurgeParams>,
+    config: web::Data<&'static Config>,
+    cache: web::Data<&'static SharedCache>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    if let Err(response) = authorize(&req, &config) {
+        return Ok(response);
+    }
+    let purged = cache.purge(params.q.as_deref()).await?;
+    log::info!(
+        "Purged {purged} cached results of {}",
+        params
+            .q
+            .as_deref()
+            .map_or("all the queries".to_owned(), |query| format!("`{query}`"))
+    );
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "purged": purged })))
+}
+
+/// A helper function which checks that the request is authorized with the admin token given in
+/// the config file.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to the admin route as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+///
+/// # Error
+///
+/// Returns an unit type if the request is authorized otherwise returns a not found response if
+/// the admin routes are disabled or an unauthorized response if the token is missing or wrong.
+fn authorize(req: &HttpRequest, config: &Config) -> Result<(), HttpResponse> {
+    let Some(admin_token) = config.admin_token.as_deref() else {
+        return Err(HttpResponse::NotFound().finish());
+    };
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    // `blake3::Hash` compares in constant time.
+    match blake3::hash(token.trim().as_bytes()) == blake3::hash(admin_token.as_bytes()) {
+        true => Ok(()),
+        false => Err(HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+            .finish()),
+    }
+}
+
+#[cfg(all(test, feature = "memory-cache"))]
+mod tests {
+    use super::*;
+    use crate::{
+        cache::{
+            cache_key::CacheKey,
+            cacher::{Cacher, InMemoryCache},
+        },
+        models::aggregation_models::SearchResults,
+    };
+    use actix_web::{http::StatusCode, test, App};
+
+    /// A helper function which builds the config and the shared in-memory cache served by the
+    /// admin routes, which are leaked as they are shared with the routes.
+    async fn admin_state(admin_token: Option<&str>) -> (&'static Config, &'static SharedCache) {
+        let mut config = Config::parse(true).unwrap();
+        config.admin_token = admin_token.map(str::to_owned);
+        let cache = SharedCache::new(InMemoryCache::build(&config).await, &config);
+        (Box::leak(Box::new(config)), Box::leak(Box::new(cache)))
+    }
+
+    #[actix_web::test]
+    async fn test_admin_routes_require_the_token() {
+        for (admin_token, authorization, status) in [
+            (None, "Bearer secret", StatusCode::NOT_FOUND),
+            (Some("secret"), "Bearer wrong", StatusCode::UNAUTHORIZED),
+            (Some("secret"), "secret", StatusCode::UNAUTHORIZED),
+            (Some("secret"), "Bearer secret", StatusCode::OK),
+        ] {
+            let (config, cache) = admin_state(admin_token).await;
+            let app = test::init_service(
+                App::new()
+                    .app_data(web::Data::new(config))
+                    .app_data(web::Data::new(cache))
+                    .service(cache_stats),
+            )
+            .await;
+            let request = test::TestRequest::get()
+                .uri("/admin/cache/stats")
+                .insert_header((header::AUTHORIZATION, authorization))
+                .to_request();
+            assert_eq!(test::call_service(&app, request).await.status(), status);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_cache_is_purged_by_query() {
+        let (config, cache) = admin_state(Some("secret")).await;
+        let urls: Vec<String> = ["rust", "Rust", "lua"]
+            .iter()
+            .enumerate()
+            .map(|(page, query)| {
+                CacheKey::new(query, ["bing"], 2)
+                    .with_page(page as u32)
+                    .to_string()
+            })
+            .collect();
+        cache
+            .cache_results(&vec![SearchResults::default(); urls.len()], &urls)
+            .await
+            .unwrap();
+        cache.cached_results(&urls[0]).await.unwrap();
+        assert!(cache
+            .cached_results(&CacheKey::new("zig", ["bing"], 2).to_string())
+            .await
+            .is_err());
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(cache))
+                .service(cache_stats)
+                .service(purge_cache),
+        )
+        .await;
+        let request = test::TestRequest::post()
+            .uri("/admin/cache/purge?q=rust")
+            .insert_header((header::AUTHORIZATION, "Bearer secret"))
+            .to_request();
+        let purged: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(purged["purged"], 2);
+
+        let request = test::TestRequest::get()
+            .uri("/admin/cache/stats")
+            .insert_header((header::AUTHORIZATION, "Bearer secret"))
+            .to_request();
+        let stats: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(
+            (stats["hits"].clone(), stats["misses"].clone()),
+            (1.into(), 1.into())
+        );
+        assert_eq!(stats["entries"], 1);
+        assert!(cache.cached_results(&urls[2]).await.is_ok());
+        assert!(cache.cached_results(&urls[1]).await.is_err());
+    }
+}
@@ -1,25 +1,38 @@
 //! This module handles the search route of the search engine website.
 
 use crate::{
-    cache::cacher::SharedCache,
+    cache::{cache_key::CacheKey, cacher::SharedCache},
     config::parser::Config,
-    handler::{file_path, FileType},
     models::{
-        aggregation_models::SearchResults,
-        engine_models::EngineHandler,
+        aggregation_models::{EngineErrorInfo, EngineResults, SearchResults},
+        engine_models::{EngineError, EngineHandler, SafeSearchMode},
+        parser_models::PrefetchPolicy,
         server_models::{self, SearchParams},
     },
-    results::aggregator::aggregate,
+    results::{
+        aggregator::{aggregate, fetch_engine_results, EngineResponses},
+        domain_filter::DomainFilter,
+        engine_health::is_struggling,
+        filter_lists::filter_lists,
+        paginator::{paginate, pending_engines},
+        plugins::{run_query_hooks, run_result_hooks},
+    },
+};
+use actix_web::{
+    body::{BodySize, MessageBody},
+    get,
+    http::header::ContentType,
+    web::{self, Bytes},
+    HttpRequest, HttpResponse,
 };
-use actix_web::{get, http::header::ContentType, web, HttpRequest, HttpResponse};
-use itertools::Itertools;
-use regex::Regex;
+use error_stack::Report;
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::{borrow::Cow, time::Duration};
-use tokio::{
-    fs::File,
-    io::{AsyncBufReadExt, BufReader},
-    join,
+use std::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
 };
 
 /// Handles the route of search page of the `websurfx` meta search engine website and it takes
@@ -42,6 +55,7 @@ pub async fn search(
     config: web::Data<&'static Config>,
     cache: web::Data<&'static SharedCache>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let (config, cache): (&'static Config, &'static SharedCache) = (**config, **cache);
     let params = web::Query::<SearchParams>::from_query(req.query_string())?;
     match &params.q {
         Some(query) => {
@@ -51,24 +65,8 @@ pub async fn search(
                     .finish());
             }
 
-            let cookie = req.cookie("appCookie");
-
             // Get search settings using the user's cookie or from the server's config
-            let mut search_settings: server_models::Cookie<'_> = cookie
-                .and_then(|cookie_value| serde_json::from_str(cookie_value.value()).ok())
-                .unwrap_or_else(|| {
-                    server_models::Cookie::build(
-                        &config.style,
-                        config
-                            .upstream_search_engines
-                            .iter()
-                            .filter_map(|(engine, enabled)| {
-                                enabled.then_some(Cow::Borrowed(engine.as_str()))
-                            })
-                            .collect(),
-                        config.safe_search,
-                    )
-                });
+            let mut search_settings = server_models::Cookie::from_request(&req, config);
 
             search_settings.safe_search_level = get_safesearch_level(
                 params.safesearch,
@@ -76,13 +74,8 @@ pub async fn search(
                 config.safe_search,
             );
 
-            // Closure wrapping the results function capturing local references
-            let get_results = |page| results(&config, &cache, query, page, &search_settings);
-
             // .max(1) makes sure that the page >= 0.
             let page = params.page.unwrap_or(1).max(1) - 1;
-            let previous_page = page.saturating_sub(1);
-            let next_page = page + 1;
 
             // Add a random delay before making the request.
             if config.aggregator.random_delay || config.debug {
@@ -91,49 +84,65 @@ pub async fn search(
                 tokio::time::sleep(Duration::from_secs(delay)).await;
             }
 
-            let results: (SearchResults, String, bool);
-            if page != previous_page {
-                let (previous_results, current_results, next_results) = join!(
-                    get_results(previous_page),
-                    get_results(page),
-                    get_results(next_page)
-                );
-
-                results = current_results?;
+            // The filtered results can only be revealed when the admin allows it.
+            let reveal_filtered = config.reveal_filtered_results && params.reveal.unwrap_or(false);
 
-                let (results_list, cache_keys): (Vec<SearchResults>, Vec<String>) =
-                    [previous_results?, results.clone(), next_results?]
-                        .into_iter()
-                        .filter_map(|(result, cache_key, flag)| flag.then_some((result, cache_key)))
-                        .multiunzip();
+            let search_results = results(
+                config,
+                cache,
+                query,
+                page,
+                &search_settings,
+                reveal_filtered,
+            )
+            .await?;
 
-                tokio::spawn(async move { cache.cache_results(&results_list, &cache_keys).await });
-            } else {
-                let (current_results, next_results) =
-                    join!(get_results(page), get_results(page + 1));
+            let body = crate::templates::views::search::search(
+                &search_settings,
+                query,
+                page,
+                &search_results,
+                server_models::preferences_token(&req, config).as_deref(),
+                config.reveal_filtered_results,
+            )
+            .0;
 
-                results = current_results?;
-
-                let (results_list, cache_keys): (Vec<SearchResults>, Vec<String>) =
-                    [results.clone(), next_results?]
-                        .into_iter()
-                        .filter_map(|(result, cache_key, flag)| flag.then_some((result, cache_key)))
-                        .multiunzip();
-
-                tokio::spawn(async move { cache.cache_results(&results_list, &cache_keys).await });
-            }
+            // Prefetch the pages around the requested page in the background once the response
+            // has been sent, unless an upstream engine is struggling, as the extra requests could
+            // get the server rate limited.
+            let prefetch_pages = prefetch_pages(
+                config.aggregator.prefetch,
+                page,
+                search_results.has_next_page,
+            );
+            let prefetch = (!prefetch_pages.is_empty()
+                && !search_settings
+                    .engines
+                    .iter()
+                    .any(|engine| is_struggling(&engine.to_lowercase())))
+            .then(|| {
+                let query = query.to_string();
+                Box::pin(async move {
+                    for page in prefetch_pages {
+                        if let Err(error) = results(
+                            config,
+                            cache,
+                            &query,
+                            page,
+                            &search_settings,
+                            reveal_filtered,
+                        )
+                        .await
+                        {
+                            log::error!("Prefetch Error: {error}");
+                        }
+                    }
+                }) as Pin<Box<dyn Future<Output = ()>>>
+            });
 
-            Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
-                crate::templates::views::search::search(
-                    &config.style.colorscheme,
-                    &config.style.theme,
-                    &config.style.animation,
-                    query,
-                    page,
-                    &results.0,
-                )
-                .0,
-            ))
+            Ok(HttpResponse::Ok()
+                .content_type(ContentType::html())
+                .body(PrefetchingBody { body, prefetch }))
         }
         None => Ok(HttpResponse::TemporaryRedirect()
             .insert_header(("location", "/"))
@@ -141,16 +150,21 @@ pub async fn search(
     }
 }
 
-/// Fetches the results for a query and page. It First checks the redis cache, if that
-/// fails it gets proper results by requesting from the upstream search engines.
+/// Fetches the results for a query and page. The page is served from the pagination buffer of
+/// the query, which is first looked up in the cache and extended with the results of the upstream
+/// search engines if it does not hold enough results for the page. The identical searches running
+/// concurrently are coalesced and an expired buffer is served while it is refreshed in the
+/// background.
 ///
 /// # Arguments
 ///
-/// * `url` - It takes the url of the current page that requested the search results for a
-///   particular search query.
 /// * `config` - It takes a parsed config struct.
-/// * `query` - It takes the page number as u32 value.
-/// * `req` - It takes the `HttpRequest` struct as a value.
+/// * `cache` - It takes the shared cache as an argument.
+/// * `query` - It takes the search query provided by the user as an argument.
+/// * `page` - It takes the zero based page number as u32 value.
+/// * `search_settings` - It takes the preferences of the user as an argument.
+/// * `reveal_filtered` - It takes whether the results removed by the filter lists should be
+///   revealed as an argument.
 ///
 /// # Error
 ///
@@ -161,110 +175,402 @@ async fn results(
     cache: &'static SharedCache,
     query: &str,
     page: u32,
-    search_settings: &server_models::Cookie<'_>,
-) -> Result<(SearchResults, String, bool), Box<dyn std::error::Error>> {
+    search_settings: &server_models::Cookie<'static>,
+    reveal_filtered: bool,
+) -> Result<SearchResults, Box<dyn std::error::Error>> {
     // eagerly parse cookie value to evaluate safe search level
     let safe_search_level = search_settings.safe_search_level;
+    let privacy_redirects = search_settings
+        .privacy_redirects
+        .unwrap_or(config.privacy_redirects.enabled);
+    let results_per_page = search_settings
+        .results_per_page
+        .unwrap_or(config.results_per_page);
 
-    let cache_key = format!(
-        "http://{}:{}/search?q={}&page={}&safesearch={}&engines={}",
-        config.binding_ip,
-        config.port,
-        query,
-        page,
-        safe_search_level,
-        search_settings.engines.join(",")
-    );
+    // check if the cookie value is empty or not if it is empty then use the
+    // default selected upstream search engines from the config file otherwise
+    // parse the non-empty cookie and grab the user selected engines from the
+    // UI and use that.
+    if search_settings.engines.is_empty() {
+        let mut search_results = SearchResults::default();
+        search_results.set_no_engines_selected();
+        search_results.set_safe_search_level(safe_search_level);
+        return Ok(search_results);
+    }
 
-    // fetch the cached results json.
-    let cached_results = cache.cached_results(&cache_key).await;
-    // check if fetched cache results was indeed fetched or it was an error and if so
-    // handle the data accordingly.
-    match cached_results {
-        Ok(results) => Ok((results, cache_key, false)),
-        Err(_) => {
-            if safe_search_level == 4 {
-                let mut results: SearchResults = SearchResults::default();
-
-                let flag: bool =
-                    !is_match_from_filter_list(file_path(FileType::BlockList)?, query).await?;
-                // Return early when query contains disallowed words,
-                if flag {
-                    results.set_disallowed();
+    // Return early when query contains disallowed words,
+    if safe_search_level == 4 && filter_lists().blocklist.is_match(query) {
+        let mut results = SearchResults::default();
+        results.set_disallowed();
+        results.set_safe_search_level(safe_search_level);
+        return Ok(results);
+    }
+
+    // The pagination buffer does not depend on the page, the number of results per page or the
+    // sites hidden by the user, so that all the pages are sliced from the same merged ranking.
+    let buffer_key = CacheKey::new(query, search_settings.engines.iter(), safe_search_level)
+        .with_option("privacy_redirects", privacy_redirects)
+        .with_option("reveal", reveal_filtered);
+    let cache_key = buffer_key.to_string();
+    let engines: Vec<EngineHandler> = search_settings
+        .engines
+        .iter()
+        .filter_map(|engine| EngineHandler::new(engine).ok())
+        .collect();
+    let blocked_sites = blocked_sites(search_settings);
+
+    // The buffer is only extended inside the coalesced call of its key, so that the concurrent
+    // searches for different pages of the query extend it one after the other instead of
+    // overwriting the extensions of each other. The searches which need more results than the
+    // extension they have waited for extend the buffer again.
+    let mut buffer = loop {
+        let buffer = cache
+            .coalesce(&format!("extend:{cache_key}"), || async {
+                // Fetch the cached pagination buffer, starting a new one if it is not cached.
+                let (fetch_query, fetch_settings) = (query.to_owned(), search_settings.clone());
+                let mut buffer = cache
+                    .cached_or_fetched(&cache_key, move || async move {
+                        let mut buffer = SearchResults::default();
+                        extend_buffer(
+                            config,
+                            cache,
+                            &fetch_query,
+                            &mut buffer,
+                            page,
+                            &fetch_settings,
+                            reveal_filtered,
+                        )
+                        .await?;
+                        Ok(buffer)
+                    })
+                    .await?;
+
+                let (_, changed) = extend_buffer(
+                    config,
+                    cache,
+                    query,
+                    &mut buffer,
+                    page,
+                    search_settings,
+                    reveal_filtered,
+                )
+                .await?;
+                if changed {
                     cache
-                        .cache_results(&[results.clone()], &[cache_key.clone()])
+                        .cache_results(&[buffer.clone()], &[cache_key.clone()])
                         .await?;
-                    results.set_safe_search_level(safe_search_level);
-                    return Ok((results, cache_key, true));
                 }
-            }
+                Ok(buffer)
+            })
+            .await?;
 
-            // check if the cookie value is empty or not if it is empty then use the
-            // default selected upstream search engines from the config file otherwise
-            // parse the non-empty cookie and grab the user selected engines from the
-            // UI and use that.
-            let mut results: SearchResults = match search_settings.engines.is_empty() {
-                false => {
-                    aggregate(
-                        query,
-                        page,
-                        config,
-                        &search_settings
-                            .engines
-                            .iter()
-                            .filter_map(|engine| EngineHandler::new(engine).ok())
-                            .collect::<Vec<EngineHandler>>(),
-                        safe_search_level,
-                    )
-                    .await?
-                }
-                true => {
-                    let mut search_results = SearchResults::default();
-                    search_results.set_no_engines_selected();
-                    search_results
-                }
-            };
-            let (engine_errors_info, results_empty_check, no_engines_selected) = (
-                results.engine_errors_info().is_empty(),
-                results.results().is_empty(),
-                results.no_engines_selected(),
-            );
-            results.set_filtered(engine_errors_info & results_empty_check & !no_engines_selected);
-            cache
-                .cache_results(&[results.clone()], &[cache_key.clone()])
-                .await?;
-            results.set_safe_search_level(safe_search_level);
-            Ok((results, cache_key, true))
+        if pending_engines(
+            &buffer,
+            page,
+            results_per_page as usize,
+            &engines,
+            |result| blocked_sites.is_blocked(&result.url),
+        )
+        .is_empty()
+        {
+            break buffer;
         }
-    }
+    };
+    // The buffer holds the page, so the page is only sliced from it.
+    let (mut results, _) = extend_buffer(
+        config,
+        cache,
+        query,
+        &mut buffer,
+        page,
+        search_settings,
+        reveal_filtered,
+    )
+    .await?;
+
+    let (engine_errors_info, results_empty_check, no_engines_selected) = (
+        results.engine_errors_info.is_empty(),
+        results.results.is_empty(),
+        results.no_engines_selected(),
+    );
+    results.set_filtered(
+        engine_errors_info & results_empty_check & !no_engines_selected & (page == 0),
+    );
+    results.set_safe_search_level(safe_search_level);
+    Ok(results)
 }
 
-/// A helper function which checks whether the search query contains any keywords which should be
-/// disallowed/allowed based on the regex based rules present in the blocklist and allowlist files.
+/// Serves the requested page from the pagination buffer of a query, extending the buffer with the
+/// aggregated results of the next pages of the upstream search engines when it does not hold
+/// enough results for the page.
 ///
 /// # Arguments
 ///
-/// * `file_path` - It takes the file path of the list as the argument.
-/// * `query` - It takes the search query to be checked against the list as an argument.
+/// * `config` - It takes a parsed config struct.
+/// * `cache` - It takes the shared cache as an argument.
+/// * `query` - It takes the search query provided by the user as an argument.
+/// * `buffer` - It takes the pagination buffer of the query as an argument.
+/// * `page` - It takes the zero based page number as u32 value.
+/// * `search_settings` - It takes the preferences of the user as an argument.
+/// * `reveal_filtered` - It takes whether the results removed by the filter lists should be
+///   revealed as an argument.
 ///
 /// # Error
 ///
-/// Returns a bool indicating whether the results were found in the list or not on success
-/// otherwise returns a standard error type on a failure.
-async fn is_match_from_filter_list(
-    file_path: &str,
+/// Returns the requested page along with a flag indicating whether the buffer has changed on
+/// success otherwise returns an error if the results could not be aggregated.
+async fn extend_buffer(
+    config: &'static Config,
+    cache: &'static SharedCache,
     query: &str,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    let reader = BufReader::new(File::open(file_path).await?);
-    let mut lines = reader.lines();
-    while let Some(line) = lines.next_line().await? {
-        let re = Regex::new(&line)?;
-        if re.is_match(query) {
-            return Ok(true);
+    buffer: &mut SearchResults,
+    page: u32,
+    search_settings: &server_models::Cookie<'_>,
+    reveal_filtered: bool,
+) -> Result<(SearchResults, bool), Box<dyn std::error::Error>> {
+    let safe_search_level = search_settings.safe_search_level;
+    let privacy_redirects = search_settings
+        .privacy_redirects
+        .unwrap_or(config.privacy_redirects.enabled);
+    let results_per_page = search_settings
+        .results_per_page
+        .unwrap_or(config.results_per_page);
+
+    let blocked_sites = blocked_sites(search_settings);
+
+    // Run the lua plugin hooks around the aggregation so that they can rewrite
+    // the query and drop, rewrite or rescore the results of every engine page.
+    let query = run_query_hooks(query);
+    paginate(
+        buffer,
+        page,
+        results_per_page as usize,
+        &search_settings
+            .engines
+            .iter()
+            .filter_map(|engine| EngineHandler::new(engine).ok())
+            .collect::<Vec<EngineHandler>>(),
+        |result| blocked_sites.is_blocked(&result.url),
+        |engine_page, engines| {
+            let query = &query;
+            async move {
+                let responses = engine_results(
+                    config,
+                    cache,
+                    query,
+                    engine_page,
+                    &engines,
+                    safe_search_level,
+                )
+                .await?;
+                let mut results = aggregate(
+                    query,
+                    config,
+                    responses,
+                    &filter_lists(),
+                    safe_search_level,
+                    privacy_redirects,
+                    reveal_filtered,
+                )
+                .await?;
+                run_result_hooks(&mut results);
+                Ok(results)
+            }
+        },
+    )
+    .await
+}
+
+/// A helper function which builds the filter of the sites hidden by the user, which are skipped
+/// when the pages are sliced from the pagination buffer shared with the other users.
+///
+/// # Arguments
+///
+/// * `search_settings` - It takes the preferences of the user as an argument.
+///
+/// # Returns
+///
+/// Returns the filter matching the results of the sites hidden by the user.
+fn blocked_sites(search_settings: &server_models::Cookie<'_>) -> DomainFilter {
+    let mut blocked_sites = DomainFilter::default();
+    blocked_sites.extend("blocked sites", &search_settings.blocked_domains.join("\n"));
+    blocked_sites
+}
+
+/// Fetches the raw results of a page of the upstream search engines. The raw results of every
+/// engine are cached on their own, so that the users selecting different engines share the results
+/// of the engines they have in common and only the engines missing from the cache are requested.
+/// The engines which failed to provide results or had no results are cached as failures for the
+/// short failure time of the cache, so that they are not requested again by every search while
+/// they keep failing.
+///
+/// # Arguments
+///
+/// * `config` - It takes a parsed config struct.
+/// * `cache` - It takes the shared cache as an argument.
+/// * `query` - It takes the search query sent to the upstream search engines as an argument.
+/// * `page` - It takes the zero based page number of the upstream search engines as an argument.
+/// * `engines` - It takes the upstream search engines selected by the user as an argument.
+/// * `safe_search` - It takes the safe search level as an argument.
+///
+/// # Error
+///
+/// Returns the raw results or the errors of the engines in the order in which they were selected
+/// on success otherwise returns an error if the fetched results cannot be cached.
+async fn engine_results(
+    config: &Config,
+    cache: &SharedCache,
+    query: &str,
+    page: u32,
+    engines: &[EngineHandler],
+    safe_search: u8,
+) -> Result<EngineResponses, Box<dyn std::error::Error>> {
+    let cache_keys: Vec<String> = engines
+        .iter()
+        .map(|engine| engine_cache_key(engine.name(), query, page, safe_search))
+        .collect();
+
+    let mut cached_responses: Vec<Option<Result<EngineResults, Report<EngineError>>>> =
+        Vec::with_capacity(engines.len());
+    let mut missing_engines: Vec<EngineHandler> = Vec::new();
+    for (engine, cache_key) in engines.iter().zip(&cache_keys) {
+        let cached = cache.cached_results(cache_key).await.ok();
+        if cached.is_none() {
+            missing_engines.push(engine.clone());
         }
+        cached_responses.push(
+            cached.map(|results| match results.engine_errors_info.first() {
+                Some(error_info) => Err(Report::new(error_info.engine_error())),
+                None => Ok(EngineResults::from(results)),
+            }),
+        );
+    }
+
+    let mut fetched_responses = match missing_engines.is_empty() {
+        true => Vec::new(),
+        false => fetch_engine_results(query, page, config, &missing_engines, safe_search).await,
+    };
+
+    // The unexpected errors are not cached, so that the engines which hit them are requested
+    // again.
+    let (fetched_results, fetched_cache_keys): (Vec<SearchResults>, Vec<String>) =
+        fetched_responses
+            .iter()
+            .filter_map(|(name, response)| {
+                let index = engines.iter().position(|engine| engine.name() == *name)?;
+                let results = match response {
+                    Ok(engine_results) => SearchResults::from(engine_results),
+                    Err(error) => match error.downcast_ref::<EngineError>()? {
+                        error @ (EngineError::RequestError | EngineError::EmptyResultSet) => {
+                            SearchResults::new(
+                                Box::default(),
+                                Box::new([EngineErrorInfo::new(error, name)]),
+                                Box::default(),
+                            )
+                        }
+                        _ => return None,
+                    },
+                };
+                Some((results, cache_keys[index].clone()))
+            })
+            .unzip();
+    if !fetched_results.is_empty() {
+        cache
+            .cache_results(&fetched_results, &fetched_cache_keys)
+            .await?;
+    }
+
+    Ok(engines
+        .iter()
+        .zip(cached_responses)
+        .filter_map(|(engine, cached)| match cached {
+            Some(response) => Some((engine.name(), response)),
+            None => fetched_responses
+                .iter()
+                .position(|(name, _)| *name == engine.name())
+                .map(|index| fetched_responses.swap_remove(index)),
+        })
+        .collect())
+}
+
+/// A helper function which builds the cache key of the raw results of an upstream search engine.
+/// The key uses the native safe search mode of the engines rather than the safe search level, as
+/// the levels sharing a mode get the same results from the engines and are only filtered
+/// differently afterwards.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the upstream search engine as an argument.
+/// * `query` - It takes the search query sent to the engine as an argument.
+/// * `page` - It takes the zero based page number of the engine as an argument.
+/// * `safe_search` - It takes the safe search level as an argument.
+///
+/// # Returns
+///
+/// Returns the cache key of the raw results of the engine.
+fn engine_cache_key(engine: &str, query: &str, page: u32, safe_search: u8) -> String {
+    CacheKey::new(
+        query,
+        [engine],
+        format!("{:?}", SafeSearchMode::from_level(safe_search)),
+    )
+    .with_page(page)
+    .to_string()
+}
+
+/// A helper function which chooses the pages to prefetch in the background based on the prefetch
+/// policy configured in the config file. The previous page is only fetched from the upstream
+/// search engines when it is not held by the pagination buffer of the query anymore, as the
+/// prefetch is otherwise sliced from the cached buffer.
+///
+/// # Arguments
+///
+/// * `policy` - It takes the configured prefetch policy as an argument.
+/// * `page` - It takes the zero based page number requested by the user as an argument.
+/// * `has_next_page` - It takes whether there are more results after the requested page as an
+///   argument.
+///
+/// # Returns
+///
+/// Returns the zero based page numbers of the pages to prefetch.
+fn prefetch_pages(policy: PrefetchPolicy, page: u32, has_next_page: bool) -> Vec<u32> {
+    let previous_page = (policy == PrefetchPolicy::Both && page > 0).then(|| page - 1);
+    let next_page = (policy != PrefetchPolicy::Off && has_next_page).then_some(page + 1);
+
+    previous_page.into_iter().chain(next_page).collect()
+}
+
+/// A named struct which stores the html of the search page along with the prefetch of the pages
+/// around it, which is started when the body is dropped once it has been sent to the user, so that the
+/// prefetch does not delay the response.
+struct PrefetchingBody {
+    /// The html of the search page.
+    body: String,
+    /// The prefetch of the pages or `None` if no page should be prefetched.
+    prefetch: Option<Pin<Box<dyn Future<Output = ()>>>>,
+}
+
+impl MessageBody for PrefetchingBody {
+    type Error = Infallible;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
     }
 
-    Ok(false)
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        Pin::new(&mut self.get_mut().body).poll_next(cx)
+    }
+}
+
+impl Drop for PrefetchingBody {
+    fn drop(&mut self) {
+        if let Some(prefetch) = self.prefetch.take() {
+            actix_web::rt::spawn(prefetch);
+        }
+    }
 }
 
 /// A helper function to choose the safe search level value based on the URL parameters,
@@ -295,7 +601,8 @@ fn get_safesearch_level(
 
 #[cfg(test)]
 mod tests {
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use super::*;
+    use crate::models::aggregation_models::SearchResult;
 
     /// A helper function which creates a random mock safe search level value.
     ///
@@ -358,4 +665,147 @@ mod tests {
             safe_search_level_value_from_branchless_code
         );
     }
+
+    #[test]
+    fn test_prefetch_pages_follow_policy() {
+        assert!(prefetch_pages(PrefetchPolicy::Off, 2, true).is_empty());
+        assert_eq!(prefetch_pages(PrefetchPolicy::Next, 2, true), vec![3]);
+        assert!(prefetch_pages(PrefetchPolicy::Next, 2, false).is_empty());
+        assert_eq!(prefetch_pages(PrefetchPolicy::Both, 2, true), vec![1, 3]);
+        assert_eq!(prefetch_pages(PrefetchPolicy::Both, 2, false), vec![1]);
+        assert_eq!(prefetch_pages(PrefetchPolicy::Both, 0, true), vec![1]);
+    }
+
+    #[actix_web::test]
+    async fn test_prefetch_starts_once_the_body_is_sent() {
+        use std::{cell::Cell, rc::Rc};
+
+        let started = Rc::new(Cell::new(false));
+        let prefetch_started = Rc::clone(&started);
+        let body = PrefetchingBody {
+            body: "results".to_owned(),
+            prefetch: Some(Box::pin(async move { prefetch_started.set(true) })),
+        };
+
+        let bytes = actix_web::body::to_bytes(body).await.unwrap();
+        assert_eq!(bytes, "results");
+        tokio::task::yield_now().await;
+        assert!(started.get());
+    }
+
+    #[cfg(feature = "memory-cache")]
+    #[tokio::test]
+    async fn test_engine_results_are_shared_between_engine_selections() {
+        use crate::cache::cacher::{Cacher, InMemoryCache};
+
+        let config = Config::parse(true).unwrap();
+        let cache = SharedCache::new(InMemoryCache::build(&config).await, &config);
+        let engines = [
+            EngineHandler::new("duckduckgo").unwrap(),
+            EngineHandler::new("bing").unwrap(),
+        ];
+
+        // Cache the raw results of both the engines as if another user had selected them.
+        let (results, cache_keys): (Vec<SearchResults>, Vec<String>) = engines
+            .iter()
+            .map(|engine| {
+                let url = format!("https://{}.example/", engine.name());
+                let result = SearchResult::new("title", &url, "description", &[engine.name()]);
+                (
+                    SearchResults::from(&EngineResults::from(vec![(url, result)])),
+                    engine_cache_key(engine.name(), "rust", 0, 2),
+                )
+            })
+            .unzip();
+        cache.cache_results(&results, &cache_keys).await.unwrap();
+
+        // The safe search levels 2 and 3 share the native safe search mode of the engines, so
+        // the results of Bing are served from the cache without requesting it.
+        let responses = engine_results(&config, &cache, "rust", 0, &engines[1..], 3)
+            .await
+            .unwrap();
+        assert_eq!(responses.len(), 1);
+        let (name, response) = &responses[0];
+        assert_eq!(*name, "bing");
+        let engine_results = response.as_ref().ok().unwrap();
+        assert_eq!(engine_results.results[0].0, "https://bing.example/");
+    }
+
+    #[cfg(feature = "memory-cache")]
+    #[tokio::test]
+    async fn test_engine_failures_are_served_from_the_cache() {
+        use crate::cache::cacher::{Cacher, InMemoryCache};
+
+        let config = Config::parse(true).unwrap();
+        let cache = SharedCache::new(InMemoryCache::build(&config).await, &config);
+        let engines = [EngineHandler::new("bing").unwrap()];
+
+        // Cache the failure of Bing as if it had just returned no results.
+        let failure = SearchResults::new(
+            Box::default(),
+            Box::new([EngineErrorInfo::new(&EngineError::EmptyResultSet, "bing")]),
+            Box::default(),
+        );
+        cache
+            .cache_results(&[failure], &[engine_cache_key("bing", "rust", 0, 2)])
+            .await
+            .unwrap();
+
+        // The failure is served without requesting Bing again.
+        let responses = engine_results(&config, &cache, "rust", 0, &engines, 2)
+            .await
+            .unwrap();
+        assert_eq!(responses.len(), 1);
+        let error = responses[0].1.as_ref().err().unwrap();
+        assert!(matches!(
+            error.downcast_ref::<EngineError>(),
+            Some(EngineError::EmptyResultSet)
+        ));
+    }
+
+    #[cfg(feature = "memory-cache")]
+    #[tokio::test]
+    async fn test_strictest_safe_search_only_disallows_blocklisted_queries() {
+        use crate::cache::cacher::{Cacher, InMemoryCache};
+        use std::borrow::Cow;
+
+        let config: &'static Config = Box::leak(Box::new(Config::parse(true).unwrap()));
+        let cache: &'static SharedCache = Box::leak(Box::new(SharedCache::new(
+            InMemoryCache::build(config).await,
+            config,
+        )));
+        let mut search_settings = server_models::Cookie::from_config(config);
+        search_settings.engines = Cow::Owned(vec![Cow::Borrowed("bing")]);
+        search_settings.safe_search_level = 4;
+        assert!(!filter_lists().blocklist.is_match("rust"));
+
+        // Cache a pagination buffer which Bing has been paged through, so that the search is
+        // served without requesting it.
+        let url = "https://www.rust-lang.org/";
+        let mut buffer = SearchResults::new(
+            Box::new([SearchResult::new("title", url, "description", &["bing"])]),
+            Box::default(),
+            Box::default(),
+        );
+        buffer.pagination.exhausted_engines = vec!["bing".to_owned()];
+        let buffer_key = CacheKey::new("rust", search_settings.engines.iter(), 4)
+            .with_option(
+                "privacy_redirects",
+                search_settings
+                    .privacy_redirects
+                    .unwrap_or(config.privacy_redirects.enabled),
+            )
+            .with_option("reveal", false);
+        cache
+            .cache_results(&[buffer], &[buffer_key.to_string()])
+            .await
+            .unwrap();
+
+        let search_results = results(config, cache, "rust", 0, &search_settings, false)
+            .await
+            .unwrap();
+        assert!(!search_results.disallowed);
+        assert_eq!(search_results.results.len(), 1);
+        assert_eq!(search_results.results[0].url, url);
+    }
 }
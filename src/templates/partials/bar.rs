@@ -8,15 +8,20 @@ use maud::{html, Markup, PreEscaped};
 /// # Arguments
 ///
 /// * `query` - It takes the current search query provided by user as an argument.
+/// * `preferences_token` - It takes the preferences token from the url as an argument, which is
+///   passed along with the search, or `None` if the preferences are read from the cookie.
 ///
 /// # Returns
 ///
 /// It returns the compiled html code for the search bar as a result.
-pub fn bar(query: &str) -> Markup {
+pub fn bar(query: &str, preferences_token: Option<&str>) -> Markup {
     html!(
         (PreEscaped("<form action=\"/search\">"))
         (PreEscaped("<div class=\"search_bar\">"))
             input type="search" name="q" value=(query) placeholder="Type to search";
+            @if let Some(preferences_token) = preferences_token {
+                input type="hidden" name="preferences" value=(preferences_token);
+            }
             button type="button" onclick="clearSearchText()" {
                 img src="./images/close.svg" alt="Clear button icon for clearing search input text";
             }
@@ -0,0 +1,106 @@
+//! A module that handles the notice shown when some of the search results were removed by the
+//! filter lists in the `websurfx` frontend.
+
+use maud::{html, Markup};
+
+use crate::models::aggregation_models::FilteredResultsInfo;
+
+/// A function that handles the html code for the notice shown when some of the search results
+/// were removed by the blocklist or the domain filter lists, along with the number of results
+/// every rule removed.
+///
+/// # Arguments
+///
+/// * `filtered_results` - It takes the number of search results removed by every rule as an
+///   argument.
+/// * `revealed` - It takes whether the removed search results have been revealed as an argument.
+/// * `toggle_href` - It takes the link which reveals the removed search results or hides them
+///   again as an argument or `None` if the server does not allow revealing them.
+///
+/// # Returns
+///
+/// It returns the compiled html markup code for the notice or an empty markup if no search
+/// results were removed.
+pub fn filtered_notice(
+    filtered_results: &[FilteredResultsInfo],
+    revealed: bool,
+    toggle_href: Option<&str>,
+) -> Markup {
+    let hidden_results: u32 = filtered_results.iter().map(|info| info.count).sum();
+    let mut lists: Vec<&str> = Vec::new();
+    for info in filtered_results {
+        if !lists.contains(&info.list.as_str()) {
+            lists.push(&info.list);
+        }
+    }
+
+    html!(
+        @if revealed {
+            p class="filtered_notice"{
+                "The results hidden by filters are shown. "
+                @if let Some(toggle_href) = toggle_href {
+                    a href=(toggle_href){"Hide them again"}
+                    "."
+                }
+            }
+        }
+        @else if hidden_results > 0 {
+            details class="filtered_notice"{
+                summary{
+                    (hidden_results)
+                    @if hidden_results == 1 {" result was"} @else {" results were"}
+                    " hidden by filters ("
+                    (lists.join(", "))
+                    ")."
+                    @if let Some(toggle_href) = toggle_href {
+                        " "
+                        a href=(toggle_href){"Show them"}
+                    }
+                }
+                ul{
+                    @for info in filtered_results {
+                        li{
+                            code{(info.rule)}
+                            " (" (info.list) "): " (info.count)
+                        }
+                    }
+                }
+            }
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filtered_notice_counts_the_hidden_results() {
+        let filtered_results = [
+            FilteredResultsInfo {
+                list: "spam.txt".to_owned(),
+                rule: "||spam.example^".to_owned(),
+                count: 2,
+            },
+            FilteredResultsInfo {
+                list: "safe search".to_owned(),
+                rule: "casino".to_owned(),
+                count: 1,
+            },
+        ];
+
+        let notice = filtered_notice(&filtered_results, false, Some("/search?q=a%26b")).0;
+        assert!(notice.contains("3 results were hidden by filters (spam.txt, safe search)."));
+        assert!(notice.contains("<code>||spam.example^</code> (spam.txt): 2"));
+        assert!(notice.contains(r#"<a href="/search?q=a%26b">Show them</a>"#));
+
+        let notice = filtered_notice(&filtered_results[1..], false, None).0;
+        assert!(notice.contains("1 result was hidden"));
+        assert!(!notice.contains("Show them"));
+
+        assert!(filtered_notice(&[], false, Some("/search")).0.is_empty());
+        assert!(filtered_notice(&[], true, Some("/search"))
+            .0
+            .contains("Hide them again"));
+    }
+}
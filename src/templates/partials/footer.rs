@@ -22,7 +22,6 @@ pub fn footer() -> Markup {
               }
            }
         }
-        script src="static/settings.js"{}
         (PreEscaped("</body>"))
         (PreEscaped("</html>"))
     )
@@ -9,11 +9,13 @@ use maud::{html, Markup, PreEscaped, DOCTYPE};
 ///
 /// * `colorscheme` - It takes the colorscheme name as an argument.
 /// * `theme` - It takes the theme name as an argument.
+/// * `animation` - It takes the animation name as an argument or `None` if no animation should
+///   be used.
 ///
 /// # Returns
 ///
 /// It returns the compiled html markup code for the header as a result.
-pub fn header(colorscheme: &str, theme: &str, animation: &Option<String>) -> Markup {
+pub fn header(colorscheme: &str, theme: &str, animation: Option<&str>) -> Markup {
     html!(
         (DOCTYPE)
         html lang="en"
@@ -24,12 +26,12 @@ pub fn header(colorscheme: &str, theme: &str, animation: &Option<String>) -> Mar
             meta name="viewport" content="width=device-width, initial-scale=1";
             link href=(format!("static/colorschemes/{colorscheme}.css")) rel="stylesheet" type="text/css";
             link href=(format!("static/themes/{theme}.css")) rel="stylesheet" type="text/css";
-            @if animation.is_some() {
-                    link href=(format!("static/animations/{}.css", animation.as_ref().unwrap())) rel="stylesheet" type="text/css";
+            @if let Some(animation) = animation {
+                    link href=(format!("static/animations/{animation}.css")) rel="stylesheet" type="text/css";
             }
         }
 
-        (PreEscaped("<body onload=\"getClientSettings()\">"))
+        (PreEscaped("<body>"))
             header{
                 h1{a href="/"{"Websurfx"}}
                 (navbar())
@@ -1,8 +1,10 @@
 //! This module provides other modules to handle the partials for the views in the `websurfx` frontend.
 
 pub mod bar;
+pub mod filtered_notice;
 pub mod footer;
 pub mod header;
 pub mod navbar;
 pub mod search_bar;
+pub mod settings_notice;
 pub mod settings_tabs;
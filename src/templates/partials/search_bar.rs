@@ -15,6 +15,8 @@ const SAFE_SEARCH_LEVELS_NAME: [&str; 3] = ["None", "Low", "Moderate"];
 ///   search engine which failed to provide results as an argument.
 /// * `safe_search_level` - It takes the safe search level with values from 0-2 as an argument.
 /// * `query` - It takes the current search query provided by user as an argument.
+/// * `preferences_token` - It takes the preferences token from the url as an argument or `None`
+///   if the preferences are read from the cookie.
 ///
 /// # Returns
 ///
@@ -23,10 +25,11 @@ pub fn search_bar(
     engine_errors_info: &[EngineErrorInfo],
     safe_search_level: u8,
     query: &str,
+    preferences_token: Option<&str>,
 ) -> Markup {
     html!(
         .search_area{
-            (bar(query))
+            (bar(query, preferences_token))
                 .error_box {
                    @if !engine_errors_info.is_empty(){
                       button type="button" onclick="toggleErrorBox()" class="error_box_toggle_button"{
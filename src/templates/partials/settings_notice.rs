@@ -0,0 +1,29 @@
+//! A module that handles the notice shown when some of the saved settings were rejected in the
+//! `websurfx` frontend.
+
+use maud::{html, Markup};
+
+/// A function that handles the html code for the notice shown when some of the settings saved in
+/// the user's cookie were rejected and replaced with the server defaults.
+///
+/// # Arguments
+///
+/// * `rejected_fields` - It takes the names of the rejected settings as an argument.
+///
+/// # Returns
+///
+/// It returns the compiled html markup code for the notice or an empty markup if no settings
+/// were rejected.
+pub fn settings_notice(rejected_fields: &[&str]) -> Markup {
+    html!(
+        @if !rejected_fields.is_empty() {
+            p class="settings_notice"{
+                "⚠️ Some of your saved settings ("
+                (rejected_fields.iter().map(|field| field.replace('_', " ")).collect::<Vec<_>>().join(", "))
+                ") are no longer valid and the defaults are used instead. Please review and save them again on the "
+                a href="/settings"{"settings page"}
+                "."
+            }
+        }
+    )
+}
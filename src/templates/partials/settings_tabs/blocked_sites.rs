@@ -0,0 +1,50 @@
+//! A module that handles the blocked sites tab for setting page view in the `websurfx` frontend.
+
+use std::borrow::Cow;
+
+use maud::{html, Markup};
+
+/// A functions that handles the html code for the blocked sites tab for the settings page for the
+/// search page.
+///
+/// # Arguments
+///
+/// * `blocked_domains` - It takes the domain filter rules of the sites the user has hidden from
+///   the search results as an argument.
+///
+/// # Returns
+///
+/// It returns the compiled html markup code for the blocked sites tab.
+pub fn blocked_sites(blocked_domains: &[Cow<'_, str>]) -> Markup {
+    html!(
+        div class="blocked_sites tab"{
+           h1{"Blocked Sites"}
+           p class="description"{
+               "The results of these sites are hidden from your search results. Uncheck a site
+               and save the settings to show its results again."
+           }
+           @if blocked_domains.is_empty() {
+               p class="description"{"No sites have been hidden yet."}
+           }
+           @else {
+               table class="blocked_domains"{
+                  @for rule in blocked_domains {
+                     tr{
+                        td{
+                           input type="checkbox" name="blocked_domains" value=(rule) checked;
+                        }
+                        td{(rule)}
+                     }
+                  }
+               }
+           }
+           h3{"Hide more sites"}
+           p class="description"{
+               "Enter the domains of the sites to hide, separated by spaces or commas. A domain
+               hides all of its subdomains as well, while a domain prefixed with `*.` only hides
+               its subdomains."
+           }
+           input type="text" name="blocked_domains_add" placeholder="example.com, *.example.org";
+        }
+    )
+}
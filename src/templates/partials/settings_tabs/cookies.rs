@@ -4,10 +4,17 @@ use maud::{html, Markup};
 
 /// A functions that handles the html code for the cookies tab for the settings page for the search page.
 ///
+/// # Arguments
+///
+/// * `cookie` - It takes the value of the preferences cookie saved on the user's system as an
+///   argument or `None` if no cookie has been saved.
+/// * `preferences_token` - It takes the preferences token encoding the current preferences as an
+///   argument.
+///
 /// # Returns
 ///
 /// It returns the compiled html markup code for the cookies tab.
-pub fn cookies() -> Markup {
+pub fn cookies(cookie: Option<&str>, preferences_token: &str) -> Markup {
     html!(
         div class="cookies tab"{
            h1{"Cookies"}
@@ -15,11 +22,25 @@ pub fn cookies() -> Markup {
                "This is the cookies are saved on your system and it contains the preferences
                you chose in the settings page"
            }
-           input type="text" name="cookie_field" value="" readonly;
+           input type="text" value=(cookie.unwrap_or("No cookies have been saved on your system")) readonly;
            p class="description"{
                "The cookies stored are not used by us for any malicious intend or for
                tracking you in any way."
            }
+           h3{"Export preferences"}
+           p class="description"{
+               "Copy this token to use your preferences on another machine or in a private
+               window, or bookmark the link below to search with them without any cookies."
+           }
+           input type="text" value=(preferences_token) readonly;
+           a href=(format!("/?preferences={preferences_token}")){"Search with these preferences"}
+           h3{"Import preferences"}
+           p class="description"{
+               "Paste a preferences token and press import to load it, then press save to keep
+               it in the cookie."
+           }
+           input type="text" name="preferences" form="import_preferences" placeholder="Preferences token";
+           button type="submit" form="import_preferences"{"Import"}
         }
     )
 }
@@ -46,26 +46,14 @@ pub fn engines(engine_names: &HashMap<String, bool>) -> Markup {
                }
                hr;
                @for (engine_name, selected) in engine_names{
-                   // Checks whether the `engine_name` is selected or not if they are then the
-                   // checked `engine` button is rendered otherwise the unchecked version is
-                   // rendered.
-                   @if *selected {
-                       .toggle_btn{
-                           label class="switch"{
-                              input type="checkbox" class="engine" checked;
-                              span class="slider round"{}
-                           }
-                           (format!("{}{}",&engine_name[..1].to_uppercase(), &engine_name[1..]))
-                       }
-                   }
-                   @else {
-                       .toggle_btn {
-                           label class="switch"{
-                              input type="checkbox" class="engine";
-                              span class="slider round"{}
-                           }
-                           (format!("{}{}",&engine_name[..1], &engine_name[1..]))
+                   // The engine checkboxes are submitted with the settings form, so the
+                   // `engine_name` is used as their value.
+                   .toggle_btn{
+                       label class="switch"{
+                          input type="checkbox" class="engine" name="engines" value=(engine_name) checked[*selected];
+                          span class="slider round"{}
                        }
+                       (format!("{}{}",&engine_name[..1].to_uppercase(), &engine_name[1..]))
                    }
                }
            }
@@ -5,16 +5,21 @@ use maud::{html, Markup};
 /// A constant holding the named safe search level options for the corresponding values 0, 1 and 2.
 const SAFE_SEARCH_LEVELS: [(u8, &str); 3] = [(0, "None"), (1, "Low"), (2, "Moderate")];
 
+/// A constant holding the options for the number of search results shown on a page.
+const RESULTS_PER_PAGE_OPTIONS: [u8; 5] = [10, 20, 30, 40, 50];
+
 /// A functions that handles the html code for the general tab for the settings page for the search page.
 ///
 /// # Arguments
 ///
 /// * `safe_search_level` - It takes the safe search level as an argument.
+/// * `privacy_redirects` - It takes whether the privacy redirects are enabled as an argument.
+/// * `results_per_page` - It takes the number of search results shown on a page as an argument.
 ///
 /// # Returns
 ///
 /// It returns the compiled html markup code for the general tab.
-pub fn general(safe_search_level: u8) -> Markup {
+pub fn general(safe_search_level: u8, privacy_redirects: bool, results_per_page: u8) -> Markup {
     html!(
         div class="general tab active"{
            h1{"General"}
@@ -37,6 +42,29 @@ pub fn general(safe_search_level: u8) -> Markup {
                      option value=(SAFE_SEARCH_LEVELS[2].0){(SAFE_SEARCH_LEVELS[2].1)}
                }
            }
+           h3{"Results per page"}
+           p class="description"{
+               "Select the number of search results shown on a page."
+           }
+           select name="results_per_page" {
+               // Sets the user selected number of results as the first option in the selection list.
+               option value=(results_per_page){(results_per_page)}
+               @for option in RESULTS_PER_PAGE_OPTIONS.iter().filter(|option| **option != results_per_page){
+                 option value=(option){(option)}
+               }
+           }
+           h3{"Privacy redirects"}
+           p class="description"{
+               "Rewrite the links of the search results to the privacy respecting alternative
+               front-ends configured by the server administrator."
+           }
+           .toggle_btn{
+              label class="switch"{
+                 input type="checkbox" name="privacy_redirects" class="privacy_redirects" checked[privacy_redirects];
+                 span class="slider round"{}
+              }
+              "Enable privacy redirects"
+           }
         }
     )
 }
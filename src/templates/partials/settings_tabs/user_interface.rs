@@ -35,7 +35,7 @@ fn style_option_list(
         }
     }
 
-    if style_type == "animations" {
+    if style_type == "animations" && !selected_style.is_empty() {
         style_option_names.push((String::default(), "none".to_owned()))
     }
 
@@ -44,6 +44,13 @@ fn style_option_list(
 
 /// A functions that handles the html code for the user interface tab for the settings page for the search page.
 ///
+/// # Arguments
+///
+/// * `theme` - It takes the currently selected theme name as an argument.
+/// * `colorscheme` - It takes the currently selected colorscheme name as an argument.
+/// * `animation` - It takes the currently selected animation name as an argument or `None` if no
+///   animation is selected.
+///
 /// # Error
 ///
 /// It returns the compiled html markup code for the user interface tab on success otherwise
@@ -51,7 +58,7 @@ fn style_option_list(
 pub fn user_interface(
     theme: &str,
     colorscheme: &str,
-    animation: &Option<String>,
+    animation: Option<&str>,
 ) -> Result<Markup, Box<dyn std::error::Error>> {
     Ok(html!(
         div class="user_interface tab"{
@@ -83,10 +90,11 @@ pub fn user_interface(
                "Select the animation for your theme to be used in user interface"
            }
            select name="animations"{
-               @let default_animation = &String::default();
-               @let animation = animation.as_ref().unwrap_or(default_animation);
+               @let animation = animation.unwrap_or_default();
                // Sets the user selected animation name from the config file as the first option in the selection list.
-               option value=(animation){(animation.replace('-'," "))}
+               option value=(animation){
+                   @if animation.is_empty() { "none" } @else { (animation.replace('-'," ")) }
+               }
                @for (k,v) in style_option_list("animations", animation)?{
                    option value=(k){(v)}
                }
@@ -13,7 +13,7 @@ use maud::{html, Markup};
 /// # Returns
 ///
 /// It returns the compiled html markup code as a result.
-pub fn not_found(colorscheme: &str, theme: &str, animation: &Option<String>) -> Markup {
+pub fn not_found(colorscheme: &str, theme: &str, animation: Option<&str>) -> Markup {
     html!(
         (header(colorscheme, theme, animation))
         main class="error_container"{
@@ -1,40 +1,82 @@
 //! A module that handles the view for the search page in the `websurfx` frontend.
 
 use maud::{html, Markup, PreEscaped};
+use reqwest::Url;
 
 use crate::{
-    models::aggregation_models::SearchResults,
-    templates::partials::{footer::footer, header::header, search_bar::search_bar},
+    models::{aggregation_models::SearchResults, server_models::Cookie},
+    results::domain_filter::site_domain,
+    templates::partials::{
+        filtered_notice::filtered_notice, footer::footer, header::header, search_bar::search_bar,
+        settings_notice::settings_notice,
+    },
 };
 
 /// A function that handles the html code for the search page view in the search engine frontend.
 ///
 /// # Arguments
 ///
-/// * `colorscheme` - It takes the colorscheme name as an argument.
-/// * `theme` - It takes the theme name as an argument.
+/// * `preferences` - It takes the preferences of the user (colorscheme, theme and animation) as
+///   an argument.
 /// * `query` - It takes the current search query provided by the user as an argument.
+/// * `page` - It takes the zero based page number of the search results as an argument.
 /// * `search_results` - It takes the aggregated search results as an argument.
+/// * `preferences_token` - It takes the preferences token from the url as an argument, which is
+///   added to all the search links, or `None` if the preferences are read from the cookie.
+/// * `reveal_permitted` - It takes whether the server allows revealing the search results removed
+///   by the filter lists as an argument.
 ///
 /// # Returns
 ///
 /// It returns the compiled html markup code as a result.
 pub fn search(
-    colorscheme: &str,
-    theme: &str,
-    animation: &Option<String>,
+    preferences: &Cookie<'_>,
     query: &str,
     page: u32,
     search_results: &SearchResults,
+    preferences_token: Option<&str>,
+    reveal_permitted: bool,
 ) -> Markup {
+    let safe_search_level = search_results.safe_search_level.to_string();
+    // The links to the other pages keep the preferences token, and the revealed results stay
+    // revealed while paging through them.
+    let page_href = |page: u32, revealed: bool| {
+        let page = page.to_string();
+        let mut params = vec![
+            ("q", query),
+            ("safesearch", &safe_search_level),
+            ("page", &page),
+        ];
+        if revealed {
+            params.push(("reveal", "true"));
+        }
+        if let Some(preferences_token) = preferences_token {
+            params.push(("preferences", preferences_token));
+        }
+        search_href(&params)
+    };
+    let related_search_href = |related_search: &str| {
+        let mut params = vec![("q", related_search), ("safesearch", &safe_search_level)];
+        if let Some(preferences_token) = preferences_token {
+            params.push(("preferences", preferences_token));
+        }
+        search_href(&params)
+    };
+    let toggle_reveal_href =
+        reveal_permitted.then(|| page_href(page + 1, !search_results.revealed));
+
     html!(
-        (header(colorscheme, theme, animation))
+        (header(&preferences.colorscheme, &preferences.theme, preferences.animation.as_deref()))
         main class="results"{
-           (search_bar(&search_results.engine_errors_info, search_results.safe_search_level, query))
+           (search_bar(&search_results.engine_errors_info, search_results.safe_search_level, query, preferences_token))
+           (settings_notice(&preferences.rejected_fields))
+           (filtered_notice(&search_results.filtered_results, search_results.revealed, toggle_reveal_href.as_deref()))
            .results_aggregated{
               @if !search_results.results.is_empty() {
                   @for result in search_results.results.iter(){
                       .result {
+                         // The title and the description are sanitized when the search result is
+                         // constructed, so only the highlighting tags are left unescaped.
                          h1{a href=(result.url){(PreEscaped(&result.title))}}
                          small{(result.url)}
                          p{(PreEscaped(&result.description))}
@@ -43,6 +85,18 @@ pub fn search(
                                span{(name)}
                             }
                          }
+                         @if let Some(domain) = site_domain(&result.url) {
+                            form class="hide_site" method="post" action="/settings/blocked_domains"{
+                               input type="hidden" name="domain" value=(domain);
+                               input type="hidden" name="q" value=(query);
+                               input type="hidden" name="page" value=(page + 1);
+                               input type="hidden" name="safesearch" value=(search_results.safe_search_level);
+                               @if let Some(preferences_token) = preferences_token {
+                                  input type="hidden" name="preferences" value=(preferences_token);
+                               }
+                               button type="submit" title=(format!("Hide all the results of {domain}")){"hide this site"}
+                            }
+                         }
                       }
                   }
               }
@@ -108,12 +162,26 @@ pub fn search(
                  }
               }
             }
+            @if !search_results.related_searches.is_empty() {
+               .related_searches{
+                  h3{"Related searches"}
+                  ul{
+                     @for related_search in search_results.related_searches.iter() {
+                        li{
+                           a href=(related_search_href(related_search)){(related_search)}
+                        }
+                     }
+                  }
+               }
+            }
             .page_navigation {
-               a href=(format!("/search?q={}&safesearch={}&page={}", query, search_results.safe_search_level, if page > 1 {page-1} else {1})) {
+               a href=(page_href(page.max(1), search_results.revealed)) {
                    (PreEscaped("&#8592;")) "previous"
                }
-               a href=(format!("/search?q={}&safesearch={}&page={}", query, search_results.safe_search_level, page+2)) {
-                  "next" (PreEscaped("&#8594;"))}
+               @if search_results.has_next_page {
+                  a href=(page_href(page + 2, search_results.revealed)) {
+                     "next" (PreEscaped("&#8594;"))}
+               }
             }
         }
         script src="static/index.js"{}
@@ -121,3 +189,37 @@ pub fn search(
         (footer())
     )
 }
+
+/// A helper function which builds the link to the search page with the given url parameters,
+/// which are percent-encoded so that the search query cannot break out of its parameter.
+///
+/// # Arguments
+///
+/// * `params` - It takes the names of the url parameters along with their values as an argument.
+///
+/// # Returns
+///
+/// Returns the relative link to the search page.
+fn search_href(params: &[(&str, &str)]) -> String {
+    match Url::parse_with_params("http://localhost/search", params) {
+        Ok(url) => format!("/search?{}", url.query().unwrap_or_default()),
+        Err(_) => "/search".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_href_encodes_the_query() {
+        assert_eq!(
+            search_href(&[("q", "rust & lua #1"), ("page", "2")]),
+            "/search?q=rust+%26+lua+%231&page=2"
+        );
+        assert_eq!(
+            search_href(&[("q", r#""><script>"#)]),
+            "/search?q=%22%3E%3Cscript%3E"
+        );
+    }
+}
@@ -2,13 +2,19 @@
 
 use std::collections::HashMap;
 
-use maud::{html, Markup};
+use maud::{html, Markup, PreEscaped};
 
-use crate::templates::partials::{
-    footer::footer,
-    header::header,
-    settings_tabs::{
-        cookies::cookies, engines::engines, general::general, user_interface::user_interface,
+use crate::{
+    config::parser::DEFAULT_RESULTS_PER_PAGE,
+    models::server_models::Cookie,
+    templates::partials::{
+        footer::footer,
+        header::header,
+        settings_notice::settings_notice,
+        settings_tabs::{
+            blocked_sites::blocked_sites, cookies::cookies, engines::engines, general::general,
+            user_interface::user_interface,
+        },
     },
 };
 
@@ -16,47 +22,65 @@ use crate::templates::partials::{
 ///
 /// # Arguments
 ///
-/// * `safe_search_level` - It takes the safe search level as an argument.
-/// * `colorscheme` - It takes the colorscheme name as an argument.
-/// * `theme` - It takes the theme name as an argument.
-/// * `animation` - It takes the animation name as an argument.
+/// * `preferences` - It takes the current preferences of the user (safe search level, privacy
+///   redirects, results per page, hidden sites, colorscheme, theme and animation) as an argument.
 /// * `engine_names` - It takes a list of engine names as an argument.
+/// * `cookie` - It takes the value of the preferences cookie saved on the user's system as an
+///   argument or `None` if no cookie has been saved.
+/// * `preferences_token` - It takes the preferences token encoding the current preferences as an
+///   argument.
 ///
 /// # Error
 ///
 /// This function returns a compiled html markup code on success otherwise returns a standard error
 /// message.
 pub fn settings(
-    safe_search_level: u8,
-    colorscheme: &str,
-    theme: &str,
-    animation: &Option<String>,
+    preferences: &Cookie<'_>,
     engine_names: &HashMap<String, bool>,
+    cookie: Option<&str>,
+    preferences_token: &str,
 ) -> Result<Markup, Box<dyn std::error::Error>> {
+    let (colorscheme, theme, animation) = (
+        &preferences.colorscheme,
+        &preferences.theme,
+        preferences.animation.as_deref(),
+    );
+
     Ok(html!(
         (header(colorscheme, theme, animation))
         main class="settings"{
            h1{"Settings"}
            hr;
+           (settings_notice(&preferences.rejected_fields))
+           // Without javascript the tabs cannot be switched, so all of them are shown at once.
+           noscript{
+              style{(PreEscaped(".settings_container .sidebar{display:none}.settings_container .tab{display:flex;gap:1.2rem;flex-direction:column}.settings_container .main_container{width:100%}"))}
+           }
            .settings_container{
               .sidebar{
                   div class="btn active" onclick="setActiveTab(this)"{"general"}
                   .btn onclick="setActiveTab(this)"{"user interface"}
                   .btn onclick="setActiveTab(this)"{"engines"}
+                  .btn onclick="setActiveTab(this)"{"blocked sites"}
                   .btn onclick="setActiveTab(this)"{"cookies"}
               }
-              .main_container{
-                  (general(safe_search_level))
+              form class="main_container" method="post" action="/settings"{
+                  (general(
+                      preferences.safe_search_level,
+                      preferences.privacy_redirects.unwrap_or_default(),
+                      preferences.results_per_page.unwrap_or(DEFAULT_RESULTS_PER_PAGE),
+                  ))
                   (user_interface(theme, colorscheme, animation)?)
                   (engines(engine_names))
-                  (cookies())
-                  p class="message"{}
-                  button type="submit" onclick="setClientSettings()"{"Save"}
+                  (blocked_sites(&preferences.blocked_domains))
+                  (cookies(cookie, preferences_token))
+                  button type="submit"{"Save"}
               }
+              // The import box of the cookies tab submits this form, as forms cannot be nested.
+              form id="import_preferences" method="get" action="/settings"{}
            }
         }
         script src="static/settings.js"{}
-        script src="static/cookies.js"{}
         (footer())
     ))
 }
@@ -1,26 +1,51 @@
-use std::{net::TcpListener, sync::OnceLock};
+use std::net::TcpListener;
 
+use tempfile::TempDir;
 use websurfx::{config::parser::Config, run, templates::views};
 
-/// A static constant for holding the parsed config.
-static CONFIG: OnceLock<Config> = OnceLock::new();
+/// A struct holding the address of a test server along with the folder of its disk cache, which
+/// is removed once the test is done.
+struct TestApp {
+    /// The address of the server.
+    address: String,
+    /// The folder holding the disk cache of the server.
+    _cache_folder: TempDir,
+}
 
 // Starts a new instance of the HTTP server, bound to a random available port
-async fn spawn_app() -> String {
+async fn spawn_app() -> TestApp {
     // Binding to port 0 will trigger the OS to assign a port for us.
     let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
     let port = listener.local_addr().unwrap().port();
-    let config = CONFIG.get_or_init(|| Config::parse(false).unwrap());
+    // Every server gets its own config, so that the servers do not share the disk cache which
+    // can only be opened once.
+    #[allow(unused_mut)] // the cache path is only set when the disk cache is enabled
+    let mut config = Config::parse(true).unwrap();
+    let cache_folder = tempfile::tempdir().expect("Failed to create the cache folder");
+    #[cfg(feature = "disk-cache")]
+    {
+        config.disk_cache_path = cache_folder
+            .path()
+            .join("cache.redb")
+            .to_string_lossy()
+            .into_owned();
+    }
+    let config: &'static Config = Box::leak(Box::new(config));
+
     let cache = websurfx::cache::cacher::create_cache(config).await;
     let server = run(listener, config, cache).expect("Failed to bind address");
 
     tokio::spawn(server);
-    format!("http://127.0.0.1:{}/", port)
+    TestApp {
+        address: format!("http://127.0.0.1:{}/", port),
+        _cache_folder: cache_folder,
+    }
 }
 
 #[tokio::test]
 async fn test_index() {
-    let address = spawn_app().await;
+    let app = spawn_app().await;
+    let address = &app.address;
 
     let client = reqwest::Client::new();
     let res = client.get(address).send().await.unwrap();
@@ -30,11 +55,110 @@ async fn test_index() {
     let template = views::index::index(
         &config.style.colorscheme,
         &config.style.theme,
-        &config.style.animation,
+        config.style.animation.as_deref(),
+        None,
     )
     .0;
     assert_eq!(res.text().await.unwrap(), template);
 }
 
+#[tokio::test]
+async fn test_settings_form_sets_preferences_cookie() {
+    let app = spawn_app().await;
+    let address = &app.address;
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let res = client
+        .post(format!("{address}settings"))
+        .form(&[
+            ("themes", "simple"),
+            ("colorschemes", "monokai"),
+            ("animations", ""),
+            ("safe_search_levels", "1"),
+            ("engines", "duckduckgo"),
+            ("engines", "not-an-engine"),
+            ("privacy_redirects", "on"),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 303);
+
+    let cookie = res
+        .headers()
+        .get("set-cookie")
+        .and_then(|cookie| cookie.to_str().ok())
+        .and_then(|cookie| cookie.split(';').next())
+        .unwrap()
+        .to_owned();
+    assert!(cookie.starts_with("appCookie="), "{cookie}");
+    assert!(cookie.contains(r#""engines":["DuckDuckGo"]"#), "{cookie}");
+
+    // The saved preferences are applied server-side without any javascript.
+    let res = client
+        .get(address)
+        .header("cookie", cookie)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("static/colorschemes/monokai.css"));
+}
+
+#[tokio::test]
+async fn test_settings_form_rejects_cross_site_requests() {
+    let app = spawn_app().await;
+    let address = &app.address;
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let form = [("themes", "simple"), ("colorschemes", "monokai")];
+
+    for (header, value) in [
+        ("origin", "https://evil.example"),
+        ("origin", "null"),
+        ("referer", "https://evil.example/settings"),
+    ] {
+        let res = client
+            .post(format!("{address}settings"))
+            .header(header, value)
+            .form(&form)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 403, "{header}: {value}");
+        assert!(res.headers().get("set-cookie").is_none());
+
+        let res = client
+            .post(format!("{address}settings/blocked_domains"))
+            .header(header, value)
+            .form(&[("q", "rust"), ("domain", "spam.example")])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 403, "{header}: {value}");
+    }
+
+    // The forms submitted from the settings page are accepted.
+    let res = client
+        .post(format!("{address}settings"))
+        .header("origin", address.trim_end_matches('/'))
+        .header("referer", format!("{address}settings"))
+        .form(&form)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 303);
+}
+
 // TODO: Write tests for testing parameters for search function that if provided with something
 // other than u32 like alphabets and special characters than it should panic
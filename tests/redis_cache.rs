@@ -0,0 +1,411 @@
+#![cfg(feature = "redis-cache")]
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use websurfx::{cache::redis_cacher::RedisCache, models::parser_models::RedisConfig};
+
+/// A minimal stand-in for a redis server, which understands the commands sent by the redis cache
+/// and records them along with the connection which sent them. It also acts as a sentinel and as
+/// a single node cluster owning all the slots.
+struct RedisStandIn {
+    /// The url of the stand-in.
+    url: String,
+    /// The commands received by the stand-in along with the ids of their connections.
+    commands: Arc<Mutex<Vec<(usize, Vec<String>)>>>,
+    /// The tasks serving the open connections.
+    connections: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    /// The address of the primary server reported when acting as a sentinel.
+    primary: Arc<Mutex<Option<(String, u16)>>>,
+    /// The task accepting the connections.
+    listener: JoinHandle<()>,
+}
+
+/// The state shared by the connections of a stand-in.
+#[derive(Clone)]
+struct Shared {
+    /// The port of the stand-in.
+    port: u16,
+    /// The values stored in the stand-in.
+    store: Arc<Mutex<HashMap<String, String>>>,
+    /// The commands received by the stand-in along with the ids of their connections.
+    commands: Arc<Mutex<Vec<(usize, Vec<String>)>>>,
+    /// The address of the primary server reported when acting as a sentinel.
+    primary: Arc<Mutex<Option<(String, u16)>>>,
+}
+
+impl RedisStandIn {
+    async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let shared = Shared {
+            port: address.port(),
+            store: Default::default(),
+            commands: Default::default(),
+            primary: Default::default(),
+        };
+        let connections: Arc<Mutex<Vec<JoinHandle<()>>>> = Default::default();
+
+        let (task_shared, task_connections) = (shared.clone(), connections.clone());
+        let listener = tokio::spawn(async move {
+            let next_id = AtomicUsize::new(0);
+            while let Ok((stream, _)) = listener.accept().await {
+                let id = next_id.fetch_add(1, Ordering::Relaxed);
+                let connection = tokio::spawn(serve(stream, id, task_shared.clone()));
+                task_connections.lock().unwrap().push(connection);
+            }
+        });
+
+        RedisStandIn {
+            url: format!("redis://{address}"),
+            commands: shared.commands,
+            connections,
+            primary: shared.primary,
+            listener,
+        }
+    }
+
+    /// Reports the given stand-in as the primary server when acting as a sentinel.
+    fn monitor(&self, primary: &RedisStandIn) {
+        let port = primary.url.rsplit(':').next().unwrap().parse().unwrap();
+        *self.primary.lock().unwrap() = Some(("127.0.0.1".to_owned(), port));
+    }
+
+    /// Stops the stand-in as if the server had crashed.
+    fn stop(&self) {
+        self.listener.abort();
+        self.drop_connections();
+    }
+
+    /// Closes all the open connections as if the server had restarted.
+    fn drop_connections(&self) {
+        for connection in self.connections.lock().unwrap().drain(..) {
+            connection.abort();
+        }
+    }
+
+    /// Returns the commands with the given name along with the ids of their connections.
+    fn commands(&self, name: &str) -> Vec<(usize, Vec<String>)> {
+        self.commands
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, command)| command[0].eq_ignore_ascii_case(name))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Serves the commands of a connection until it is closed.
+async fn serve(stream: TcpStream, id: usize, shared: Shared) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    while let Some(command) = read_command(&mut reader).await {
+        let reply = {
+            let mut store = shared.store.lock().unwrap();
+            match command[0].to_ascii_uppercase().as_str() {
+                "GET" => bulk_string(store.get(&command[1])),
+                "SET" => {
+                    let has = |flag: &str| command.iter().any(|arg| arg.eq_ignore_ascii_case(flag));
+                    let previous = store.get(&command[1]).cloned();
+                    if previous.is_none() || !has("NX") {
+                        store.insert(command[1].clone(), command[2].clone());
+                    }
+                    match has("GET") {
+                        true => bulk_string(previous.as_ref()),
+                        false => "+OK\r\n".to_owned(),
+                    }
+                }
+                "SENTINEL" => match shared.primary.lock().unwrap().as_ref() {
+                    Some((host, port)) => format!(
+                        "*2\r\n{}{}",
+                        bulk_string(Some(host)),
+                        bulk_string(Some(&port.to_string()))
+                    ),
+                    None => "*-1\r\n".to_owned(),
+                },
+                "SCAN" | "KEYS" => {
+                    let pattern = command.iter().skip_while(|arg| !arg.eq_ignore_ascii_case("MATCH"));
+                    let prefix = match (command[0].eq_ignore_ascii_case("KEYS"), pattern.last()) {
+                        (true, _) => command[1].trim_end_matches('*').to_owned(),
+                        (false, Some(pattern)) => pattern.trim_end_matches('*').to_owned(),
+                        (false, None) => String::new(),
+                    };
+                    let keys: Vec<&String> =
+                        store.keys().filter(|key| key.starts_with(&prefix)).collect();
+                    let keys = format!(
+                        "*{}\r\n{}",
+                        keys.len(),
+                        keys.iter().map(|key| bulk_string(Some(key))).collect::<String>()
+                    );
+                    match command[0].eq_ignore_ascii_case("SCAN") {
+                        true => format!("*2\r\n{}{keys}", bulk_string(Some(&"0".to_owned()))),
+                        false => keys,
+                    }
+                }
+                "UNLINK" => {
+                    let removed = command[1..]
+                        .iter()
+                        .filter(|key| store.remove(*key).is_some())
+                        .count();
+                    format!(":{removed}\r\n")
+                }
+                "DBSIZE" => format!(":{}\r\n", store.len()),
+                "INFO" => bulk_string(Some(&"# Memory\r\nused_memory:1024\r\n# Stats\r\nexpired_keys:2\r\nevicted_keys:1\r\n".to_owned())),
+                "CLUSTER" => format!(
+                    "*1\r\n*3\r\n:0\r\n:16383\r\n*2\r\n{}:{}\r\n",
+                    bulk_string(Some(&"127.0.0.1".to_owned())),
+                    shared.port
+                ),
+                _ => "+OK\r\n".to_owned(),
+            }
+        };
+        shared.commands.lock().unwrap().push((id, command));
+        if writer.write_all(reply.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads a command sent as an array of bulk strings.
+async fn read_command(
+    reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+) -> Option<Vec<String>> {
+    let length = read_length(reader, '*').await?;
+    let mut command = Vec::with_capacity(length);
+    for _ in 0..length {
+        let mut argument = vec![0; read_length(reader, '$').await? + 2];
+        reader.read_exact(&mut argument).await.ok()?;
+        argument.truncate(argument.len() - 2);
+        command.push(String::from_utf8(argument).ok()?);
+    }
+    Some(command)
+}
+
+/// Reads the length following the given prefix.
+async fn read_length(
+    reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>,
+    prefix: char,
+) -> Option<usize> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await.ok()?;
+    line.trim_end().strip_prefix(prefix)?.parse().ok()
+}
+
+/// Encodes an optional value as a bulk string.
+fn bulk_string(value: Option<&String>) -> String {
+    match value {
+        Some(value) => format!("${}\r\n{}\r\n", value.len(), value),
+        None => "$-1\r\n".to_owned(),
+    }
+}
+
+/// Builds the options used to connect to the given stand-in as a single redis server.
+fn standalone(redis: &RedisStandIn) -> RedisConfig {
+    RedisConfig::new(
+        "standalone",
+        vec![redis.url.clone()],
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_cache_json_sends_only_the_given_results() {
+    let redis = RedisStandIn::start().await;
+    let cache = RedisCache::new(&standalone(&redis), 1, 60).await.unwrap();
+
+    for (key, json) in [("rust", "results"), ("lua", "other results")] {
+        cache
+            .cache_json([json.to_owned()].into_iter(), [key.to_owned()].into_iter())
+            .await
+            .unwrap();
+    }
+
+    // Every call sends its own results only, instead of resending the previous ones.
+    let keys: Vec<String> = redis
+        .commands("SET")
+        .into_iter()
+        .map(|(_, command)| command[1].clone())
+        .collect();
+    assert_eq!(keys, vec!["rust", "lua"]);
+    assert_eq!(cache.cached_json("rust").await.unwrap(), "results");
+    assert_eq!(cache.cached_json("lua").await.unwrap(), "other results");
+}
+
+#[tokio::test]
+async fn test_cached_results_are_overwritten() {
+    let redis = RedisStandIn::start().await;
+    let cache = RedisCache::new(&standalone(&redis), 1, 60).await.unwrap();
+
+    for json in ["stale results", "refreshed results"] {
+        cache
+            .cache_json(
+                [json.to_owned()].into_iter(),
+                ["rust".to_owned()].into_iter(),
+            )
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(
+        cache.cached_json("rust").await.unwrap(),
+        "refreshed results"
+    );
+    let (_, command) = redis.commands("SET").pop().unwrap();
+    assert_eq!(command[3..], ["EX".to_owned(), "60".to_owned()]);
+}
+
+#[tokio::test]
+async fn test_commands_are_spread_over_the_connections() {
+    let redis = RedisStandIn::start().await;
+    let cache = RedisCache::new(&standalone(&redis), 3, 60).await.unwrap();
+    cache
+        .cache_json(
+            ["results".to_owned()].into_iter(),
+            ["rust".to_owned()].into_iter(),
+        )
+        .await
+        .unwrap();
+
+    for _ in 0..6 {
+        assert_eq!(cache.cached_json("rust").await.unwrap(), "results");
+    }
+
+    let gets = redis.commands("GET");
+    for connection in 0..3 {
+        assert_eq!(
+            gets.iter().filter(|(id, _)| *id == connection).count(),
+            2,
+            "the commands should be spread evenly over the connections"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_dropped_connections_are_reconnected() {
+    let redis = RedisStandIn::start().await;
+    let cache = RedisCache::new(&standalone(&redis), 2, 60).await.unwrap();
+    cache
+        .cache_json(
+            ["results".to_owned()].into_iter(),
+            ["rust".to_owned()].into_iter(),
+        )
+        .await
+        .unwrap();
+
+    redis.drop_connections();
+
+    // The commands fail over while the dropped connections reconnect in the background.
+    let mut reconnected = false;
+    for _ in 0..50 {
+        if let Ok(results) = cache.cached_json("rust").await {
+            assert_eq!(results, "results");
+            reconnected = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(reconnected, "the dropped connections should be reconnected");
+    assert!(redis.commands("GET").iter().all(|(id, _)| *id >= 2));
+}
+
+#[tokio::test]
+async fn test_sentinel_primary_is_failed_over() {
+    let (sentinel, primary, replica) = (
+        RedisStandIn::start().await,
+        RedisStandIn::start().await,
+        RedisStandIn::start().await,
+    );
+    sentinel.monitor(&primary);
+    let options = RedisConfig::new(
+        "sentinel",
+        vec![sentinel.url.clone()],
+        Some("primary".to_owned()),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let cache = RedisCache::new(&options, 2, 60).await.unwrap();
+    cache
+        .cache_json(
+            ["results".to_owned()].into_iter(),
+            ["rust".to_owned()].into_iter(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(primary.commands("SET").len(), 1);
+
+    // The sentinels promote the replica once the primary server has crashed.
+    sentinel.monitor(&replica);
+    primary.stop();
+
+    cache
+        .cache_json(
+            ["other results".to_owned()].into_iter(),
+            ["lua".to_owned()].into_iter(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(cache.cached_json("lua").await.unwrap(), "other results");
+    assert_eq!(replica.commands("SET").len(), 1);
+}
+
+#[tokio::test]
+async fn test_cluster_keys_are_sent_separately() {
+    let node = RedisStandIn::start().await;
+    let options =
+        RedisConfig::new("cluster", vec![node.url.clone()], None, None, None, None).unwrap();
+    let cache = RedisCache::new(&options, 5, 60).await.unwrap();
+
+    cache
+        .cache_json(
+            ["results".to_owned(), "other results".to_owned()].into_iter(),
+            ["rust".to_owned(), "lua".to_owned()].into_iter(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(node.commands("SET").len(), 2);
+    assert_eq!(cache.cached_json("rust").await.unwrap(), "results");
+    assert_eq!(cache.cached_json("lua").await.unwrap(), "other results");
+}
+
+#[tokio::test]
+async fn test_keys_are_purged_by_pattern() {
+    let redis = RedisStandIn::start().await;
+    let cache = RedisCache::new(&standalone(&redis), 2, 60).await.unwrap();
+    cache
+        .cache_json(
+            ["results", "results", "other results"]
+                .map(str::to_owned)
+                .into_iter(),
+            ["rust:1", "rust:2", "lua:1"].map(str::to_owned).into_iter(),
+        )
+        .await
+        .unwrap();
+
+    let stats = cache.usage().await.unwrap();
+    assert_eq!((stats.entries, stats.size, stats.evictions), (3, 1024, 3));
+
+    assert_eq!(cache.remove_keys("rust:*").await.unwrap(), 2);
+    assert!(cache.cached_json("rust:1").await.is_err());
+    assert_eq!(cache.cached_json("lua:1").await.unwrap(), "other results");
+    assert_eq!(cache.remove_keys("*").await.unwrap(), 1);
+}
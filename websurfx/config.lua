@@ -9,6 +9,7 @@ binding_ip = "127.0.0.1" --ip address on the which server should be launched.
 production_use = false -- whether to use production mode or not (in other words this option should be used if it is to be used to host it on the server to provide a service to a large number of users (more than one))
 -- if production_use is set to true
 -- There will be a random delay before sending the request to the search engines, this is to prevent DDoSing the upstream search engines from a large number of simultaneous requests.
+prefetch = "next" -- which pages around the requested page to prefetch in the background after the response is sent ("off", "next" or "both"). Prefetching is skipped while an upstream search engine keeps failing.
 request_timeout = 30 -- timeout for the search requests sent to the upstream search engines to be fetched (value in seconds).
 tcp_connection_keep_alive = 30 -- the amount of time the tcp connection should remain alive to the upstream search engines (or connected to the server). (value in seconds).
 pool_idle_connection_timeout = 30 -- timeout for the idle connections in the reqwest HTTP connection pool (value in seconds).
@@ -33,6 +34,14 @@ client_connection_keep_alive = 120
 -- 4 - Aggressive
 -- }}
 safe_search = 2
+results_per_page = 10 -- the number of search results shown on a page by default (from 1 to 50, users can change it from the settings page).
+-- The domain filter lists (in the adblock `||example.com^` syntax) used to remove the results of
+-- unwanted domains, like a local copy of a community list of search spam domains. The relative
+-- paths are resolved against the folder of this config file.
+domain_filter_lists = {
+    -- "search-spam.txt",
+}
+reveal_filtered_results = false -- whether to allow the users to temporarily reveal the results removed by the filter lists and the safe search.
 
 -- ### Website ###
 -- The different colorschemes provided are:
@@ -62,9 +71,69 @@ theme = "simple" -- the theme name which should be used for the website
 -- }}
 animation = "simple-frosted-glow" -- the animation name which should be used with the theme or `nil` if you don't want any animations.
 
+-- ### Privacy Redirects ###
+privacy_redirects = false -- whether to rewrite the result urls to the alternative front-ends below by default (users can toggle it from the settings page).
+-- The hostnames (subdomains included) of the result urls to rewrite mapped to the hostname of the
+-- alternative front-end instance they should be redirected to.
+privacy_redirect_rules = {
+    ["youtube.com"] = "yewtu.be", -- Invidious
+    ["twitter.com"] = "nitter.net", -- Nitter
+    ["x.com"] = "nitter.net", -- Nitter
+    ["reddit.com"] = "safereddit.com", -- Redlib
+    ["medium.com"] = "scribe.rip", -- Scribe
+}
+
+-- ### Tracking Parameters ###
+-- The query parameters removed from the result urls (a trailing `*` matches all parameters starting with the prefix).
+tracking_parameters = {
+    "utm_*",
+    "fbclid",
+    "gclid",
+    "gclsrc",
+    "dclid",
+    "msclkid",
+    "yclid",
+    "mc_cid",
+    "mc_eid",
+    "_hsenc",
+    "_hsmi",
+    "igshid",
+    "_ga",
+}
+
+-- ### Preferences Token ###
+-- The secret used to sign the shareable preferences tokens. When set, only the tokens created by this
+-- instance are accepted. Leave it unset (nil) to use unsigned tokens.
+-- preferences_token_secret = "change me to a long random string"
+
+-- ### Admin ###
+-- The token authorizing the requests to the admin routes (like `/admin/cache/stats`), which is passed in
+-- the `Authorization: Bearer <token>` header. Leave it unset (nil) to disable the admin routes.
+-- admin_token = "change me to a long random string"
+
 -- ### Caching ###
 redis_url = "redis://127.0.0.1:8082" -- redis connection url address on which the client should connect on.
+-- The topology of the redis deployment: `standalone`, `sentinel` or `cluster`. In the `sentinel` and
+-- `cluster` modes `redis_nodes` lists the urls of the sentinels or of the initial nodes of the cluster.
+-- redis_mode = "standalone"
+-- redis_nodes = { "redis://10.0.0.1:26379", "redis://10.0.0.2:26379" }
+-- redis_sentinel_master = "mymaster" -- The name of the primary server monitored by the sentinels.
+-- The PEM file of the custom CA verifying the `rediss://` connections and the file holding the password
+-- used to authenticate to the redis servers. The relative paths are resolved against the folder of this config file.
+-- redis_tls_ca_file = "redis_ca.pem"
+-- redis_username = "websurfx"
+-- redis_password_file = "redis_password"
 cache_expiry_time = 600 -- This option takes the expiry time of the search results (value in seconds and the value should be greater than or equal to 60 seconds).
+cache_stale_time = 60 -- The time the expired search results are still served while they are refreshed in the background (value in seconds, 0 disables it).
+cache_failure_time = 30 -- The time the failures of the upstream search engines (and the search results holding them) are cached before the engines are requested again (value in seconds).
+-- The file holding the `<id>:<base64 key>` keys used to encrypt the cached results (only used with
+-- the `encrypt-cache-results` and `cec-cache-results` features). The first key encrypts the new results.
+-- cache_encryption_key_file = "cache_keys.txt"
+-- The database file storing the cached results and its max size in megabytes (only used with the
+-- `disk-cache` feature). The relative path is resolved against the folder of this config file.
+-- disk_cache_path = "cache.redb"
+-- disk_cache_max_size = 256
+
 -- ### Search Engines ###
 upstream_search_engines = {
     DuckDuckGo = true,
@@ -76,4 +145,19 @@ upstream_search_engines = {
     Bing = false,
 } -- select the upstream search engines from which the results should be fetched.
 
-proxy = nil -- Proxy to send outgoing requests through. Set to nil to disable.
\ No newline at end of file
+proxy = nil -- Proxy to send outgoing requests through. Set to nil to disable.
+-- ### Plugins ###
+-- Functions to customize the search behaviour without recompiling the app. They can also be defined
+-- in `.lua` files placed in the `plugins` folder next to this file. Plugins run in a sandbox which
+-- only provides the `table`, `string` and `math` libraries.
+--
+-- function on_query(query) -- return a new query string to rewrite the query or nil to keep it.
+--     return query
+-- end
+--
+-- function on_result(result) -- modify the `title`, `url`, `description`, `engines` and `score`
+--     -- fields in place or return a new table, return false to drop the result.
+--     if string.find(result.url, "pinterest.com", 1, true) then
+--         return false
+--     end
+-- end